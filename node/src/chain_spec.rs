@@ -1,6 +1,6 @@
 use node_template_runtime::{
-	AccountId, AuraConfig, BalancesConfig, GenesisConfig, GrandpaConfig, Signature, SudoConfig,
-	SystemConfig, WASM_BINARY,
+	AccountId, AuraConfig, BalancesConfig, BeaconConfig, GenesisConfig, GrandpaConfig, IdentityConfig, Signature,
+	SudoConfig, SystemConfig, WASM_BINARY,
 };
 use sc_service::ChainType;
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
@@ -59,6 +59,8 @@ pub fn development_config() -> Result<ChainSpec, String> {
 					get_account_id_from_seed::<sr25519::Public>("Alice//stash"),
 					get_account_id_from_seed::<sr25519::Public>("Bob//stash"),
 				],
+				studentid_genesis(),
+				beacon_genesis(),
 				true,
 			)
 		},
@@ -106,6 +108,8 @@ pub fn local_testnet_config() -> Result<ChainSpec, String> {
 					get_account_id_from_seed::<sr25519::Public>("Eve//stash"),
 					get_account_id_from_seed::<sr25519::Public>("Ferdie//stash"),
 				],
+				studentid_genesis(),
+				beacon_genesis(),
 				true,
 			)
 		},
@@ -122,12 +126,44 @@ pub fn local_testnet_config() -> Result<ChainSpec, String> {
 	))
 }
 
+/// Student identities for dev chains: Alice and Bob are registered (password `password`) and
+/// linked to their own accounts, Alice is a registrar and operates the `moodle` service.
+fn studentid_genesis() -> IdentityConfig {
+	let alice = get_account_id_from_seed::<sr25519::Public>("Alice");
+	let bob = get_account_id_from_seed::<sr25519::Public>("Bob");
+	IdentityConfig {
+		identities: vec![
+			(b"alice@example.edu".to_vec(), alice.clone(), b"password".to_vec()),
+			(b"bob@example.edu".to_vec(), bob.clone(), b"password".to_vec()),
+		],
+		email_links: vec![
+			(b"alice@example.edu".to_vec(), alice.clone()),
+			(b"bob@example.edu".to_vec(), bob),
+		],
+		services: vec![(b"moodle".to_vec(), alice.clone())],
+		registrars: vec![alice],
+	}
+}
+
+/// Beacon participants for dev chains, so kitties get fresh randomness once Alice and Bob run the
+/// commit-reveal rounds.
+fn beacon_genesis() -> BeaconConfig {
+	BeaconConfig {
+		participants: vec![
+			get_account_id_from_seed::<sr25519::Public>("Alice"),
+			get_account_id_from_seed::<sr25519::Public>("Bob"),
+		],
+	}
+}
+
 /// Configure initial storage state for FRAME modules.
 fn testnet_genesis(
 	wasm_binary: &[u8],
 	initial_authorities: Vec<(AuraId, GrandpaId)>,
 	root_key: AccountId,
 	endowed_accounts: Vec<AccountId>,
+	identity: IdentityConfig,
+	beacon: BeaconConfig,
 	_enable_println: bool,
 ) -> GenesisConfig {
 	GenesisConfig {
@@ -150,5 +186,7 @@ fn testnet_genesis(
 			// Assign network admin rights.
 			key: root_key,
 		},
+		identity,
+		beacon,
 	}
 }
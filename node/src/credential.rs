@@ -0,0 +1,360 @@
+//! W3C Verifiable Credentials for student identities.
+//!
+//! A credential is assembled from the `pallet_studentid` identity stored under an email hash and
+//! the `pallet_docverify` entries the caller names. Instead of a signature it carries a storage
+//! proof of every value it was built from, taken at a given block. Anyone holding a trusted state
+//! root for that block (e.g. from a light client) can check it offline with
+//! [`verify_credential`], without trusting the node that issued it.
+
+use std::{collections::HashMap, sync::Arc};
+
+use codec::Decode;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result as RpcResult};
+use jsonrpc_derive::rpc;
+use node_template_runtime::{opaque::Block, AccountId, Hash, Runtime};
+use pallet_studentid_rpc::{did_of, parse_did};
+use sc_client_api::ProofProvider;
+use serde_json::{json, Value};
+use sp_blockchain::HeaderBackend;
+use sp_core::{
+	bytes::from_hex,
+	hashing::{blake2_128, twox_128, twox_64},
+	hexdisplay::HexDisplay,
+	Bytes, H256,
+};
+use sp_runtime::{
+	generic::BlockId,
+	traits::{BlakeTwo256, Header as HeaderT},
+};
+use sp_state_machine::{read_proof_check, StorageProof};
+
+/// `type` of the proof object embedded in every credential.
+pub const PROOF_TYPE: &str = "SubstrateStorageProof2021";
+
+/// Why a credential could not be issued or verified.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CredentialError {
+	/// There is no identity under the requested email hash.
+	NoIdentity,
+	/// A requested document does not exist, is not verified or was not submitted by the identity.
+	DocumentNotVerified(Vec<u8>),
+	/// The credential JSON is missing a field or has a malformed one.
+	Malformed(&'static str),
+	/// The proof was made against a different state root than the trusted one.
+	StateRootMismatch,
+	/// The proof does not prove the values the credential claims.
+	InvalidProof,
+	/// The node could not produce the proof.
+	Client(String),
+}
+
+/// Storage key of `pallet.item`.
+fn prefix(pallet: &str, item: &str) -> Vec<u8> {
+	[twox_128(pallet.as_bytes()), twox_128(item.as_bytes())].concat()
+}
+
+/// Storage key of `Identity.Identity1Of[email_hash]` (`Twox64Concat`).
+fn identity_key(email_hash: &[u8; 32]) -> Vec<u8> {
+	[prefix("Identity", "Identity1Of"), twox_64(email_hash).to_vec(), email_hash.to_vec()].concat()
+}
+
+/// Storage key of `Identity.LinkedAccounts[email_hash]` (`Twox64Concat`).
+fn linked_accounts_key(email_hash: &[u8; 32]) -> Vec<u8> {
+	[prefix("Identity", "LinkedAccounts"), twox_64(email_hash).to_vec(), email_hash.to_vec()]
+		.concat()
+}
+
+/// Storage key of `Docverify.Documentverify[document]` (`Blake2_128Concat` of the encoded hash).
+fn document_key(document: &[u8]) -> Vec<u8> {
+	let encoded = codec::Encode::encode(&document.to_vec());
+	[prefix("Docverify", "Documentverify"), blake2_128(&encoded).to_vec(), encoded].concat()
+}
+
+/// Storage key of `Timestamp.Now`.
+fn timestamp_key() -> Vec<u8> {
+	prefix("Timestamp", "Now")
+}
+
+fn hex(bytes: &[u8]) -> String {
+	format!("0x{}", HexDisplay::from(&bytes))
+}
+
+/// Format a unix timestamp in milliseconds as an ISO 8601 UTC date-time.
+fn iso8601(millis: u64) -> String {
+	let secs = millis / 1000;
+	let (days, rem) = ((secs / 86_400) as i64, secs % 86_400);
+	// Civil date from days since 1970-01-01, see http://howardhinnant.github.io/date_algorithms.html
+	let z = days + 719_468;
+	let era = z.div_euclid(146_097);
+	let doe = z.rem_euclid(146_097);
+	let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let day = doy - (153 * mp + 2) / 5 + 1;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 };
+	let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+	format!(
+		"{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+		year,
+		month,
+		day,
+		rem / 3600,
+		rem % 3600 / 60,
+		rem % 60
+	)
+}
+
+/// The values a credential is built from, as read from (or checked against) a storage proof.
+struct ProvenValues(HashMap<Vec<u8>, Option<Vec<u8>>>);
+
+impl ProvenValues {
+	fn decode<T: Decode>(&self, key: &[u8]) -> Option<T> {
+		self.0.get(key)?.as_ref().and_then(|value| T::decode(&mut &value[..]).ok())
+	}
+
+	/// Build the credential subject these values prove.
+	fn subject(&self, email_hash: &[u8; 32], documents: &[Vec<u8>]) -> Result<Value, CredentialError> {
+		let identity: pallet_studentid::RegistrationOf<Runtime> =
+			self.decode(&identity_key(email_hash)).ok_or(CredentialError::NoIdentity)?;
+		let linked: Vec<AccountId> =
+			self.decode(&linked_accounts_key(email_hash)).unwrap_or_default();
+
+		let documents = documents
+			.iter()
+			.map(|document| {
+				let (submitter, verifier, link, status): (AccountId, AccountId, Vec<u8>, Vec<u8>) =
+					self.decode(&document_key(document))
+						.ok_or_else(|| CredentialError::DocumentNotVerified(document.clone()))?;
+				let by_identity = submitter == identity.owner || linked.contains(&submitter);
+				if status != pallet_docverify::DOCUMENT_VERIFIED || !by_identity {
+					return Err(CredentialError::DocumentNotVerified(document.clone()))
+				}
+				Ok(json!({
+					"documentHash": hex(document),
+					"documentLink": String::from_utf8_lossy(&link),
+					"verifier": verifier.to_string(),
+				}))
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+
+		Ok(json!({
+			"id": did_of(email_hash),
+			"owner": identity.owner.to_string(),
+			"linkedAccounts": linked.iter().map(ToString::to_string).collect::<Vec<_>>(),
+			"verifiedDocuments": documents,
+		}))
+	}
+}
+
+/// All storage keys a credential for `email_hash` and `documents` is built from.
+fn credential_keys(email_hash: &[u8; 32], documents: &[Vec<u8>]) -> Vec<Vec<u8>> {
+	let mut keys = vec![identity_key(email_hash), linked_accounts_key(email_hash), timestamp_key()];
+	keys.extend(documents.iter().map(|document| document_key(document)));
+	keys
+}
+
+/// Assemble the credential for `email_hash` from `proof`, which must prove the values of
+/// [`credential_keys`] under `state_root`.
+fn build_credential(
+	email_hash: [u8; 32],
+	documents: &[Vec<u8>],
+	block_hash: H256,
+	state_root: H256,
+	proof: StorageProof,
+) -> Result<Value, CredentialError> {
+	let keys = credential_keys(&email_hash, documents);
+	let values = ProvenValues(
+		read_proof_check::<BlakeTwo256, _>(state_root, proof.clone(), &keys)
+			.map_err(|_| CredentialError::InvalidProof)?,
+	);
+	let subject = values.subject(&email_hash, documents)?;
+	let issued = iso8601(values.decode::<u64>(&timestamp_key()).unwrap_or_default());
+
+	Ok(json!({
+		"@context": ["https://www.w3.org/2018/credentials/v1"],
+		"type": ["VerifiableCredential", "StudentIdentityCredential"],
+		// Self-issued: the claims are backed by the storage proof rather than by an issuer's
+		// signature, so the issuer is the subject, whose DID `studentid_resolveDid` resolves.
+		"issuer": did_of(&email_hash),
+		"issuanceDate": issued,
+		"credentialSubject": subject,
+		"proof": {
+			"type": PROOF_TYPE,
+			"created": issued,
+			"blockHash": hex(block_hash.as_bytes()),
+			"stateRoot": hex(state_root.as_bytes()),
+			"documents": documents.iter().map(|document| hex(document)).collect::<Vec<_>>(),
+			"proofNodes": proof.iter_nodes().map(|node| hex(&node)).collect::<Vec<_>>(),
+		},
+	}))
+}
+
+fn hex_field(value: &Value, field: &'static str) -> Result<Vec<u8>, CredentialError> {
+	value
+		.get(field)
+		.and_then(Value::as_str)
+		.and_then(|s| from_hex(s).ok())
+		.ok_or(CredentialError::Malformed(field))
+}
+
+fn hex_list(value: &Value, field: &'static str) -> Result<Vec<Vec<u8>>, CredentialError> {
+	value
+		.get(field)
+		.and_then(Value::as_array)
+		.ok_or(CredentialError::Malformed(field))?
+		.iter()
+		.map(|item| item.as_str().and_then(|s| from_hex(s).ok()).ok_or(CredentialError::Malformed(field)))
+		.collect()
+}
+
+/// Check `credential` against `trusted_state_root`, the state root of the block named in its
+/// proof as obtained from a source the verifier trusts.
+///
+/// Rebuilds the credential from the embedded proof and succeeds only if the result is identical
+/// to `credential`, so no claim can be added, dropped or altered.
+pub fn verify_credential(credential: &Value, trusted_state_root: H256) -> Result<(), CredentialError> {
+	let proof = credential.get("proof").ok_or(CredentialError::Malformed("proof"))?;
+	if proof.get("type").and_then(Value::as_str) != Some(PROOF_TYPE) {
+		return Err(CredentialError::Malformed("type"))
+	}
+	let state_root = hex_field(proof, "stateRoot")?;
+	if state_root != trusted_state_root.as_bytes() {
+		return Err(CredentialError::StateRootMismatch)
+	}
+	let block_hash = hex_field(proof, "blockHash")?;
+	if block_hash.len() != 32 {
+		return Err(CredentialError::Malformed("blockHash"))
+	}
+	let block_hash = H256::from_slice(&block_hash);
+
+	let email_hash = credential
+		.pointer("/credentialSubject/id")
+		.and_then(Value::as_str)
+		.and_then(parse_did)
+		.ok_or(CredentialError::Malformed("credentialSubject"))?;
+
+	let documents = hex_list(proof, "documents")?;
+	let nodes = StorageProof::new(hex_list(proof, "proofNodes")?);
+
+	let rebuilt = build_credential(email_hash, &documents, block_hash, trusted_state_root, nodes)?;
+	if &rebuilt != credential {
+		return Err(CredentialError::InvalidProof)
+	}
+	Ok(())
+}
+
+/// Verifiable credential RPC methods.
+#[rpc]
+pub trait CredentialApi<BlockHash> {
+	/// A W3C Verifiable Credential for the identity under `email_hash`, listing the given
+	/// verified `documents`, proven against the state at `at` (best block by default).
+	#[rpc(name = "studentid_credential")]
+	fn credential(
+		&self,
+		email_hash: H256,
+		documents: Vec<Bytes>,
+		at: Option<BlockHash>,
+	) -> RpcResult<Value>;
+}
+
+/// Issues credentials from the client's state.
+pub struct Credentials<C> {
+	client: Arc<C>,
+}
+
+impl<C> Credentials<C> {
+	/// Create new `Credentials` with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client }
+	}
+}
+
+impl<C> Credentials<C>
+where
+	C: ProofProvider<Block> + HeaderBackend<Block>,
+{
+	fn issue(
+		&self,
+		email_hash: [u8; 32],
+		documents: &[Vec<u8>],
+		at: Option<Hash>,
+	) -> Result<Value, CredentialError> {
+		let block_hash = at.unwrap_or_else(|| self.client.info().best_hash);
+		let client_error = |e: sp_blockchain::Error| CredentialError::Client(e.to_string());
+		let state_root = *self
+			.client
+			.header(BlockId::Hash(block_hash))
+			.map_err(client_error)?
+			.ok_or_else(|| CredentialError::Client("unknown block".into()))?
+			.state_root();
+
+		let keys = credential_keys(&email_hash, documents);
+		let proof = self
+			.client
+			.read_proof(&BlockId::Hash(block_hash), &mut keys.iter().map(Vec::as_slice))
+			.map_err(client_error)?;
+
+		build_credential(email_hash, documents, block_hash, state_root, proof)
+	}
+}
+
+impl<C> CredentialApi<Hash> for Credentials<C>
+where
+	C: ProofProvider<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+{
+	fn credential(
+		&self,
+		email_hash: H256,
+		documents: Vec<Bytes>,
+		at: Option<Hash>,
+	) -> RpcResult<Value> {
+		let documents: Vec<Vec<u8>> = documents.into_iter().map(|d| d.to_vec()).collect();
+		self.issue(email_hash.into(), &documents, at).map_err(|e| RpcError {
+			code: ErrorCode::ServerError(1),
+			message: "Unable to issue credential.".into(),
+			data: Some(format!("{:?}", e).into()),
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn iso8601_formats_unix_millis() {
+		assert_eq!(iso8601(0), "1970-01-01T00:00:00Z");
+		assert_eq!(iso8601(951_782_400_000), "2000-02-29T00:00:00Z");
+		assert_eq!(iso8601(1_634_567_890_123), "2021-10-18T14:38:10Z");
+	}
+
+	#[test]
+	fn verify_rejects_untrusted_state_root() {
+		let credential = json!({
+			"proof": { "type": PROOF_TYPE, "stateRoot": hex(&[1u8; 32]) },
+		});
+		assert_eq!(
+			verify_credential(&credential, H256::repeat_byte(2)),
+			Err(CredentialError::StateRootMismatch)
+		);
+	}
+
+	#[test]
+	fn subject_and_issuer_are_resolvable_dids() {
+		let email_hash = [7u8; 32];
+		assert_eq!(parse_did(&did_of(&email_hash)), Some(email_hash));
+
+		let credential = json!({
+			"proof": {
+				"type": PROOF_TYPE,
+				"stateRoot": hex(&[1u8; 32]),
+				"blockHash": hex(&[2u8; 32]),
+			},
+			"credentialSubject": { "id": "did:studentid:chain" },
+		});
+		assert_eq!(
+			verify_credential(&credential, H256::repeat_byte(1)),
+			Err(CredentialError::Malformed("credentialSubject"))
+		);
+	}
+}
@@ -0,0 +1,301 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! A commit-reveal randomness beacon.
+//!
+//! Time is split into rounds of `CommitPeriod + RevealPeriod` blocks. During the commit phase
+//! every registered participant may submit `hash((who, secret))`; during the reveal phase they
+//! submit the `secret` itself. When a round ends, the revealed secrets are XOR-ed together and
+//! hashed with the previous seed to give the new seed, which the pallet exposes through
+//! [`Randomness`](frame_support::traits::Randomness).
+//!
+//! A participant that commits and then does not reveal can bias the seed by withholding, so
+//! missed reveals are counted and a participant that misses `MaxMissedReveals` in a row is
+//! removed. A round with no reveals at all keeps the previous seed.
+pub use pallet::*;
+
+mod mock;
+#[cfg(test)]
+mod tests;
+
+#[frame_support::pallet]
+pub mod pallet {
+	use frame_support::pallet_prelude::*;
+	use frame_system::pallet_prelude::*;
+	use frame_support::traits::Randomness;
+	use sp_runtime::traits::{Hash, Saturating, UniqueSaturatedInto, Zero};
+	use sp_std::prelude::*;
+
+	/// Index of a commit-reveal round.
+	pub type RoundIndex = u32;
+
+	#[pallet::pallet]
+	#[pallet::generate_store(pub(super) trait Store)]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// Because this pallet emits events, it depends on the runtime's definition of an event.
+		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+
+		/// Origin allowed to add and remove participants.
+		type RegistrarOrigin: EnsureOrigin<Self::Origin>;
+
+		/// The maximum number of registered participants.
+		#[pallet::constant]
+		type MaxParticipants: Get<u32>;
+
+		/// Blocks at the start of each round during which commitments are accepted.
+		#[pallet::constant]
+		type CommitPeriod: Get<Self::BlockNumber>;
+
+		/// Blocks after the commit phase during which secrets are revealed.
+		#[pallet::constant]
+		type RevealPeriod: Get<Self::BlockNumber>;
+
+		/// Consecutive missed reveals after which a participant is removed.
+		#[pallet::constant]
+		type MaxMissedReveals: Get<u32>;
+	}
+
+	#[pallet::storage]
+	#[pallet::getter(fn participants)]
+	/// Registered participants and the number of reveals each has missed in a row.
+	pub(super) type Participants<T: Config> = StorageMap<_, Twox64Concat, T::AccountId, u32>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn participant_count)]
+	pub(super) type ParticipantCount<T: Config> = StorageValue<_, u32, ValueQuery>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn commitments)]
+	/// Commitments not revealed yet, by round. Revealing removes the entry.
+	pub(super) type Commitments<T: Config> =
+		StorageDoubleMap<_, Twox64Concat, RoundIndex, Twox64Concat, T::AccountId, T::Hash>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn reveals)]
+	/// The XOR of the secrets revealed in a round so far, and how many there were.
+	pub(super) type Reveals<T: Config> = StorageMap<_, Twox64Concat, RoundIndex, (T::Hash, u32)>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn seed)]
+	/// The current seed and the block it became known at.
+	pub(super) type Seed<T: Config> = StorageValue<_, (T::Hash, T::BlockNumber), ValueQuery>;
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig<T: Config> {
+		/// Participants registered from the first round, so the seed is not left at its
+		/// default until the registrar gets around to registering anyone.
+		pub participants: Vec<T::AccountId>,
+	}
+
+	#[cfg(feature = "std")]
+	impl<T: Config> Default for GenesisConfig<T> {
+		fn default() -> Self {
+			Self { participants: Default::default() }
+		}
+	}
+
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
+		fn build(&self) {
+			assert!(
+				self.participants.len() <= T::MaxParticipants::get() as usize,
+				"Too many participants"
+			);
+			for who in &self.participants {
+				assert!(!<Participants<T>>::contains_key(who), "Participant registered twice");
+				<Participants<T>>::insert(who, 0);
+			}
+			<ParticipantCount<T>>::put(self.participants.len() as u32);
+		}
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// A participant was registered. \[participant\]
+		ParticipantAdded(T::AccountId),
+		/// A participant was removed. \[participant\]
+		ParticipantRemoved(T::AccountId),
+		/// A commitment was made. \[round, participant\]
+		Committed(RoundIndex, T::AccountId),
+		/// A commitment was revealed. \[round, participant\]
+		Revealed(RoundIndex, T::AccountId),
+		/// A participant committed but did not reveal. \[round, participant\]
+		RevealMissed(RoundIndex, T::AccountId),
+		/// A round ended with reveals and gave a new seed. \[round, seed\]
+		SeedUpdated(RoundIndex, T::Hash),
+		/// A round ended without reveals, the seed is unchanged. \[round\]
+		NoReveals(RoundIndex),
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The account is already a participant.
+		AlreadyParticipant,
+		/// The account is not a participant.
+		NotParticipant,
+		/// The maximum number of participants is reached.
+		TooManyParticipants,
+		/// The current round is not in its commit phase.
+		NotCommitPhase,
+		/// The current round is not in its reveal phase.
+		NotRevealPhase,
+		/// The participant already committed in this round.
+		AlreadyCommitted,
+		/// The participant has nothing to reveal in this round.
+		NoCommitment,
+		/// The secret does not match the commitment.
+		BadReveal,
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_initialize(n: T::BlockNumber) -> Weight {
+			// Account for closing the round in `on_finalize`.
+			if Self::round_end(n).is_some() {
+				let max = T::MaxParticipants::get() as Weight;
+				T::DbWeight::get().reads_writes(2 + 2 * max, 2 + 2 * max)
+			} else {
+				0
+			}
+		}
+
+		fn on_finalize(n: T::BlockNumber) {
+			if let Some(round) = Self::round_end(n) {
+				Self::close_round(round, n);
+			}
+		}
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Register `who` as a participant.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(2, 2))]
+		pub fn register(origin: OriginFor<T>, who: T::AccountId) -> DispatchResult {
+			T::RegistrarOrigin::ensure_origin(origin)?;
+			ensure!(!<Participants<T>>::contains_key(&who), Error::<T>::AlreadyParticipant);
+			let count = Self::participant_count();
+			ensure!(count < T::MaxParticipants::get(), Error::<T>::TooManyParticipants);
+
+			<Participants<T>>::insert(&who, 0);
+			<ParticipantCount<T>>::put(count + 1);
+			Self::deposit_event(Event::ParticipantAdded(who));
+			Ok(())
+		}
+
+		/// Remove `who` from the participants. A commitment `who` made this round is dropped.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(2, 3))]
+		pub fn deregister(origin: OriginFor<T>, who: T::AccountId) -> DispatchResult {
+			T::RegistrarOrigin::ensure_origin(origin)?;
+			ensure!(<Participants<T>>::contains_key(&who), Error::<T>::NotParticipant);
+
+			let (round, _) = Self::phase(<frame_system::Pallet<T>>::block_number());
+			<Commitments<T>>::remove(round, &who);
+			Self::remove_participant(who);
+			Ok(())
+		}
+
+		/// Commit to a secret for the current round. `commitment` is the hash of
+		/// `(sender, secret)`.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(2, 1))]
+		pub fn commit(origin: OriginFor<T>, commitment: T::Hash) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(<Participants<T>>::contains_key(&who), Error::<T>::NotParticipant);
+
+			let (round, revealing) = Self::phase(<frame_system::Pallet<T>>::block_number());
+			ensure!(!revealing, Error::<T>::NotCommitPhase);
+			ensure!(!<Commitments<T>>::contains_key(round, &who), Error::<T>::AlreadyCommitted);
+
+			<Commitments<T>>::insert(round, &who, commitment);
+			Self::deposit_event(Event::Committed(round, who));
+			Ok(())
+		}
+
+		/// Reveal the secret committed to in the current round.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(3, 3))]
+		pub fn reveal(origin: OriginFor<T>, secret: T::Hash) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let (round, revealing) = Self::phase(<frame_system::Pallet<T>>::block_number());
+			ensure!(revealing, Error::<T>::NotRevealPhase);
+			let commitment = <Commitments<T>>::get(round, &who).ok_or(Error::<T>::NoCommitment)?;
+			ensure!(T::Hashing::hash_of(&(&who, secret)) == commitment, Error::<T>::BadReveal);
+
+			<Commitments<T>>::remove(round, &who);
+			<Participants<T>>::mutate(&who, |missed| if let Some(missed) = missed {
+				*missed = 0;
+			});
+			<Reveals<T>>::mutate(round, |reveals| {
+				let (acc, count) = reveals.get_or_insert_with(Default::default);
+				for (a, s) in acc.as_mut().iter_mut().zip(secret.as_ref()) {
+					*a ^= s;
+				}
+				*count += 1;
+			});
+			Self::deposit_event(Event::Revealed(round, who));
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// The round block `n` belongs to, and whether it is in its reveal phase.
+		pub fn phase(n: T::BlockNumber) -> (RoundIndex, bool) {
+			let commit = T::CommitPeriod::get();
+			let length = commit.saturating_add(T::RevealPeriod::get());
+			if length.is_zero() {
+				return (0, false)
+			}
+			let round: RoundIndex = (n / length).unique_saturated_into();
+			(round, n % length >= commit)
+		}
+
+		/// The round that ends with block `n`, if any.
+		fn round_end(n: T::BlockNumber) -> Option<RoundIndex> {
+			let (round, revealing) = Self::phase(n);
+			let (next, _) = Self::phase(n.saturating_add(1u32.into()));
+			(revealing && next != round).then(|| round)
+		}
+
+		/// Penalise participants that did not reveal and fold the reveals into the seed.
+		fn close_round(round: RoundIndex, n: T::BlockNumber) {
+			for (who, _) in <Commitments<T>>::drain_prefix(round) {
+				let missed = match <Participants<T>>::get(&who) {
+					Some(missed) => missed.saturating_add(1),
+					None => continue,
+				};
+				Self::deposit_event(Event::RevealMissed(round, who.clone()));
+				if missed >= T::MaxMissedReveals::get() {
+					Self::remove_participant(who);
+				} else {
+					<Participants<T>>::insert(&who, missed);
+				}
+			}
+
+			match <Reveals<T>>::take(round) {
+				Some((acc, _)) => {
+					let seed = T::Hashing::hash_of(&(Self::seed().0, acc));
+					<Seed<T>>::put((seed, n));
+					Self::deposit_event(Event::SeedUpdated(round, seed));
+				},
+				None => Self::deposit_event(Event::NoReveals(round)),
+			}
+		}
+
+		fn remove_participant(who: T::AccountId) {
+			<Participants<T>>::remove(&who);
+			<ParticipantCount<T>>::mutate(|count| *count = count.saturating_sub(1));
+			Self::deposit_event(Event::ParticipantRemoved(who));
+		}
+	}
+
+	impl<T: Config> Randomness<T::Hash, T::BlockNumber> for Pallet<T> {
+		/// Mix `subject` into the current seed. The block number returned is the one the seed
+		/// became known at, so the output is only as fresh as the last round with reveals.
+		fn random(subject: &[u8]) -> (T::Hash, T::BlockNumber) {
+			let (seed, known_since) = Self::seed();
+			(T::Hashing::hash_of(&(seed, subject)), known_since)
+		}
+	}
+}
@@ -0,0 +1,94 @@
+#![cfg(test)]
+
+use crate as pallet_beacon;
+use frame_support::{parameter_types, traits::{GenesisBuild, Hooks}};
+use frame_system::EnsureRoot;
+use sp_core::H256;
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, IdentityLookup},
+};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		Beacon: pallet_beacon::{Pallet, Call, Storage, Config<T>, Event<T>},
+	}
+);
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+	pub const SS58Prefix: u8 = 42;
+}
+
+impl frame_system::Config for Test {
+	type AccountData = ();
+	type AccountId = u64;
+	type BaseCallFilter = frame_support::traits::AllowAll;
+	type BlockHashCount = BlockHashCount;
+	type BlockLength = ();
+	type BlockNumber = u64;
+	type BlockWeights = ();
+	type Call = Call;
+	type DbWeight = ();
+	type Event = Event;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type Header = Header;
+	type Index = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type OnKilledAccount = ();
+	type OnNewAccount = ();
+	type OnSetCode = ();
+	type Origin = Origin;
+	type PalletInfo = PalletInfo;
+	type SS58Prefix = SS58Prefix;
+	type SystemWeightInfo = ();
+	type Version = ();
+}
+
+parameter_types! {
+	pub const MaxParticipants: u32 = 3;
+	// Rounds are 5 blocks: commit in the first 3, reveal in the last 2.
+	pub const CommitPeriod: u64 = 3;
+	pub const RevealPeriod: u64 = 2;
+	pub const MaxMissedReveals: u32 = 2;
+}
+
+impl pallet_beacon::Config for Test {
+	type Event = Event;
+	type RegistrarOrigin = EnsureRoot<u64>;
+	type MaxParticipants = MaxParticipants;
+	type CommitPeriod = CommitPeriod;
+	type RevealPeriod = RevealPeriod;
+	type MaxMissedReveals = MaxMissedReveals;
+}
+
+/// Finalize the current block and move to block `n`.
+pub(crate) fn run_to_block(n: u64) {
+	while System::block_number() < n {
+		Beacon::on_finalize(System::block_number());
+		System::set_block_number(System::block_number() + 1);
+	}
+}
+
+pub(crate) fn new_test_ext() -> sp_io::TestExternalities {
+	new_test_ext_with_participants(vec![])
+}
+
+/// A chain whose beacon starts with `participants` registered at genesis.
+pub(crate) fn new_test_ext_with_participants(participants: Vec<u64>) -> sp_io::TestExternalities {
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	pallet_beacon::GenesisConfig::<Test> { participants }.assimilate_storage(&mut t).unwrap();
+	let mut ext = sp_io::TestExternalities::new(t);
+	ext.execute_with(|| System::set_block_number(1));
+	ext
+}
@@ -0,0 +1,120 @@
+use crate::{mock::*, Error, Event as BeaconEvent};
+use frame_support::{assert_noop, assert_ok, traits::Randomness};
+use sp_core::H256;
+use sp_runtime::traits::{BlakeTwo256, Hash};
+
+fn commitment(who: u64, secret: H256) -> H256 {
+	BlakeTwo256::hash_of(&(who, secret))
+}
+
+fn register(who: &[u64]) {
+	for who in who {
+		assert_ok!(Beacon::register(Origin::root(), *who));
+	}
+}
+
+#[test]
+fn reveals_update_the_seed() {
+	new_test_ext().execute_with(|| {
+		register(&[1, 2]);
+		let (s1, s2) = (H256::repeat_byte(1), H256::repeat_byte(2));
+		assert_noop!(Beacon::register(Origin::signed(1), 3), sp_runtime::DispatchError::BadOrigin);
+		assert_noop!(Beacon::commit(Origin::signed(3), commitment(3, s1)), Error::<Test>::NotParticipant);
+
+		assert_ok!(Beacon::commit(Origin::signed(1), commitment(1, s1)));
+		assert_ok!(Beacon::commit(Origin::signed(2), commitment(2, s2)));
+		assert_noop!(Beacon::commit(Origin::signed(1), commitment(1, s2)), Error::<Test>::AlreadyCommitted);
+		assert_noop!(Beacon::reveal(Origin::signed(1), s1), Error::<Test>::NotRevealPhase);
+
+		run_to_block(3);
+		assert_eq!(Beacon::phase(3), (0, true));
+		assert_noop!(Beacon::commit(Origin::signed(1), commitment(1, s1)), Error::<Test>::NotCommitPhase);
+		// Another participant's secret does not open this participant's commitment.
+		assert_noop!(Beacon::reveal(Origin::signed(1), s2), Error::<Test>::BadReveal);
+		assert_ok!(Beacon::reveal(Origin::signed(1), s1));
+		assert_ok!(Beacon::reveal(Origin::signed(2), s2));
+		assert_noop!(Beacon::reveal(Origin::signed(2), s2), Error::<Test>::NoCommitment);
+
+		let before = Beacon::random(b"dna");
+		run_to_block(5);
+		let seed = BlakeTwo256::hash_of(&(H256::zero(), H256::repeat_byte(1 ^ 2)));
+		System::assert_last_event(Event::Beacon(BeaconEvent::SeedUpdated(0, seed)));
+		assert_eq!(Beacon::seed(), (seed, 4));
+		assert_eq!(Beacon::random(b"dna"), (BlakeTwo256::hash_of(&(seed, &b"dna"[..])), 4));
+		assert_ne!(Beacon::random(b"dna"), before);
+		assert_ne!(Beacon::random(b"dna").0, Beacon::random(b"gender").0);
+	});
+}
+
+#[test]
+fn missing_reveals_are_penalised_and_keep_the_seed() {
+	new_test_ext().execute_with(|| {
+		register(&[1, 2]);
+		let secret = H256::repeat_byte(7);
+
+		// Round 0: 2 reveals, 1 withholds.
+		assert_ok!(Beacon::commit(Origin::signed(1), commitment(1, secret)));
+		assert_ok!(Beacon::commit(Origin::signed(2), commitment(2, secret)));
+		run_to_block(3);
+		assert_ok!(Beacon::reveal(Origin::signed(2), secret));
+		run_to_block(5);
+		System::assert_has_event(Event::Beacon(BeaconEvent::RevealMissed(0, 1)));
+		assert_eq!(Beacon::participants(1), Some(1));
+		assert_eq!(Beacon::participants(2), Some(0));
+		let seed = Beacon::seed();
+		assert_eq!(seed.1, 4);
+
+		// Round 1: nobody reveals, so the seed and its block stay as they were.
+		assert_ok!(Beacon::commit(Origin::signed(1), commitment(1, secret)));
+		assert_ok!(Beacon::commit(Origin::signed(2), commitment(2, secret)));
+		run_to_block(10);
+		System::assert_last_event(Event::Beacon(BeaconEvent::NoReveals(1)));
+		assert_eq!(Beacon::seed(), seed);
+
+		// 1 missed twice in a row and is removed, 2 missed once.
+		System::assert_has_event(Event::Beacon(BeaconEvent::ParticipantRemoved(1)));
+		assert_eq!(Beacon::participants(1), None);
+		assert_eq!(Beacon::participants(2), Some(1));
+		assert_eq!(Beacon::participant_count(), 1);
+		assert_noop!(Beacon::commit(Origin::signed(1), commitment(1, secret)), Error::<Test>::NotParticipant);
+
+		// Revealing resets the count of missed reveals.
+		assert_ok!(Beacon::commit(Origin::signed(2), commitment(2, secret)));
+		run_to_block(13);
+		assert_ok!(Beacon::reveal(Origin::signed(2), secret));
+		assert_eq!(Beacon::participants(2), Some(0));
+		run_to_block(15);
+		assert_ne!(Beacon::seed(), seed);
+	});
+}
+
+#[test]
+fn participants_are_bounded() {
+	new_test_ext().execute_with(|| {
+		register(&[1, 2, 3]);
+		assert_noop!(Beacon::register(Origin::root(), 1), Error::<Test>::AlreadyParticipant);
+		assert_noop!(Beacon::register(Origin::root(), 4), Error::<Test>::TooManyParticipants);
+
+		assert_ok!(Beacon::commit(Origin::signed(3), commitment(3, H256::zero())));
+		assert_ok!(Beacon::deregister(Origin::root(), 3));
+		assert_eq!(Beacon::commitments(0, 3), None);
+		assert_noop!(Beacon::deregister(Origin::root(), 3), Error::<Test>::NotParticipant);
+		register(&[4]);
+	});
+}
+
+#[test]
+fn genesis_participants_take_part_from_the_first_round() {
+	new_test_ext_with_participants(vec![1, 2]).execute_with(|| {
+		assert_eq!(Beacon::participant_count(), 2);
+		assert_noop!(Beacon::register(Origin::root(), 2), Error::<Test>::AlreadyParticipant);
+		register(&[3]);
+		assert_noop!(Beacon::register(Origin::root(), 4), Error::<Test>::TooManyParticipants);
+
+		assert_ok!(Beacon::commit(Origin::signed(1), commitment(1, H256::repeat_byte(1))));
+		run_to_block(3);
+		assert_ok!(Beacon::reveal(Origin::signed(1), H256::repeat_byte(1)));
+		run_to_block(5);
+		assert_ne!(Beacon::seed().0, H256::zero());
+	});
+}
@@ -1,94 +1,118 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+//! Simple crowdfunding.
+//!
+//! Anyone can `create` a fund for a beneficiary, with a goal and an end block, by paying the
+//! `SubmissionDeposit`. Until the end, anyone can `contribute` at least `MinContribution`. After
+//! the end, a fund that reached its goal can be `dispense`d to the beneficiary, while the
+//! contributors of a fund that did not can `withdraw`. Once `RetirementPeriod` has passed after
+//! the end, anyone can `dissolve` the fund and collect what is left in it, after the beneficiary
+//! of a successful fund has been paid.
+//!
+//! Every fund holds its balance in its own sub-account of `PalletId`, and keeps its
+//! contributions in a child trie, so a fund can be cleared at once.
+
 pub use pallet::*;
 
 pub use orml_nft;
 
-
-//use crate::Balance;
-
-
+mod mocks;
+mod tests;
 
 #[frame_support::pallet]
 pub mod pallet {
     use frame_support::{pallet_prelude::*, PalletId, ensure, storage::child,
-	//	traits::{Currency, ExistenceRequirement, Get, ReservableCurrency, WithdrawReasons},
-    traits::{	Currency, LockIdentifier, LockableCurrency, WithdrawReasons },
+		traits::{Currency, ExistenceRequirement, Get},
 		sp_runtime::{traits::{AccountIdConversion, Saturating, Zero, Hash},
          }
 	};
-	 use frame_system::{pallet_prelude::*, ensure_signed};
-
-	//use frame_support::pallet_prelude::*;
-	//use frame_system::pallet_prelude::*;
-	use sp_std::vec::Vec; // Step 3.1 will include this in `Cargo.toml`
-    const EXAMPLE_ID: LockIdentifier = *b"example ";
-
-
-	#[pallet::config]  // <-- Step 2. code block will replace this.
-	pub trait Config: frame_system::Config    {
+	use frame_system::{pallet_prelude::*, ensure_signed};
+	use sp_std::vec::Vec;
+
+	/// Index of a fund.
+	pub type FundIndex = u32;
+	type AccountIdOf<T> = <T as frame_system::Config>::AccountId;
+	pub(crate) type BalanceOf<T> = <<T as Config>::Currency as Currency<AccountIdOf<T>>>::Balance;
+	pub(crate) type FundInfoOf<T> =
+		FundInfo<AccountIdOf<T>, BalanceOf<T>, <T as frame_system::Config>::BlockNumber>;
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
 		/// The overarching event type.
 		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
-        type StakeCurrency: LockableCurrency<Self::AccountId, Moment = Self::BlockNumber>;
-    //    type Currency: ReservableCurrency<Self::AccountId>;
-    //type SubmissionDeposit: Get<BalanceOf<Self>>;
-    //type MinContribution: Get<BalanceOf<Self>>;
-    //type RetirementPeriod: Get<Self::BlockNumber>;
-
-	}
 
-/*    type BalanceOf<T> =
-        <<T as Config>::StakeCurrency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
-*/
+		/// The currency funds are raised in.
+		type Currency: Currency<Self::AccountId>;
 
-	#[pallet::event]   // <-- Step 3. code block will replace this.
-	#[pallet::generate_deposit(pub(super) fn deposit_event)]
-	pub enum Event<T: Config> {
-		/// Event emitted when a proof has been claimed. [who, claim]
-		ClaimCreated(T::AccountId, Vec<u8>),
-		/// Event emitted when a claim is revoked by the owner. [who, claim]
-		ClaimRevoked(T::AccountId, Vec<u8>),
+		/// The crowdfunding pallet's id, used to derive the sub-account of each fund.
+		#[pallet::constant]
+		type PalletId: Get<PalletId>;
 
-		UpdateCreated(T::AccountId, Vec<u8>),
+		/// The amount paid into a new fund by its creator, paid to whoever dispenses or
+		/// dissolves it. Must be at least the existential deposit, as it opens the fund's account.
+		#[pallet::constant]
+		type SubmissionDeposit: Get<BalanceOf<Self>>;
 
-		ClaimLocked(T::AccountId, Vec<u8>),
+		/// The smallest amount that can be contributed.
+		#[pallet::constant]
+		type MinContribution: Get<BalanceOf<Self>>;
 
-        PshAddressCreated(T::AccountId, Vec<u8>),
+		/// Blocks after a fund's end before it can be dissolved.
+		#[pallet::constant]
+		type RetirementPeriod: Get<Self::BlockNumber>;
+	}
 
-        SomethingStored(u32, T::AccountId),
-		TokenIssuedBy(T::AccountId),
-		TokenMinted(T::AccountId),
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// A fund was created. \[fund_index, creator, end\]
+		Created(FundIndex, T::AccountId, T::BlockNumber),
+		/// A contribution was made. \[contributor, fund_index, amount\]
+		Contributed(T::AccountId, FundIndex, BalanceOf<T>),
+		/// A contributor took back their contribution to a failed fund. \[contributor, fund_index, amount\]
+		Withdrew(T::AccountId, FundIndex, BalanceOf<T>),
+		/// A successful fund was paid to its beneficiary. \[fund_index, beneficiary, amount, caller\]
+		Dispensed(FundIndex, T::AccountId, BalanceOf<T>, T::AccountId),
+		/// A retired fund was dissolved and what was left paid to the caller. \[fund_index, caller\]
+		Dissolved(FundIndex, T::AccountId),
 	}
 
-	#[pallet::error]   // <-- Step 4. code block will replace this.
+	#[pallet::error]
 	pub enum Error<T> {
-		/// The proof has already been claimed.
-		ProofAlreadyClaimed,
-		/// The proof does not exist, so it cannot be revoked.
-		NoSuchProof,
-		/// The proof is claimed by another account, so caller can't revoke it.
-		NotProofOwner,
-
-		ProofAlreadyLocked,
-
-		ProofNotClaimed,
-
-		InvalidLock,
+		/// A fund must end after the current block.
+		EndTooEarly,
+		/// The contribution is below `MinContribution`.
+		ContributionTooSmall,
+		/// There is no fund with this index.
+		InvalidIndex,
+		/// The fund has ended and takes no more contributions.
+		ContributionPeriodOver,
+		/// The fund has not ended yet.
+		FundStillActive,
+		/// The fund reached its goal, so contributions cannot be withdrawn.
+		FundSucceeded,
+		/// The fund did not reach its goal, so it cannot be dispensed.
+		UnsuccessfulFund,
+		/// The account has no contribution to withdraw.
+		NoContribution,
+		/// The retirement period has not passed yet.
+		FundNotRetired,
+		/// Ran out of fund indexes.
+		FundIndexOverflow,
 	}
 
-    #[derive(Encode, Decode, Default, PartialEq, Eq)]
-#[cfg_attr(feature = "std", derive(Debug))]
+    #[derive(Clone, Encode, Decode, Default, PartialEq, Eq, RuntimeDebug, TypeInfo)]
 pub struct FundInfo<AccountId, Balance, BlockNumber> {
     /// The account that will recieve the funds if the campaign is successful.
-    beneficiary: AccountId,
+    pub beneficiary: AccountId,
     /// The amount of deposit placed.
-    deposit: Balance,
+    pub deposit: Balance,
     /// The total amount raised.
-    raised: Balance,
+    pub raised: Balance,
     /// Block number after which funding must have succeeded.
-    end: BlockNumber,
-    /// Upper bound on `raised`.
-    goal: Balance,
+    pub end: BlockNumber,
+    /// The amount `raised` must reach for the campaign to succeed.
+    pub goal: Balance,
 }
 
     #[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, Default)]
@@ -98,237 +122,174 @@ pub struct MetaData<AccountId, Balance> {
     burner: AccountId,
     }
 
-/*
-pub type FundIndex = u32;
-type AccountIdOf<T> = <T as frame_system::Config>::AccountId;
-type BalanceOf<T> = <<T as Config>::Currency as Currency<AccountIdOf<T>>>::Balance;
-type FundInfoOf<T> = FundInfo<AccountIdOf<T>, BalanceOf<T>, <T as frame_system::Config>::BlockNumber>;
-
-*/
 	#[pallet::pallet]
 	#[pallet::generate_store(pub(super) trait Store)]
 	pub struct Pallet<T>(_);
 
-    /*
-    
-    #[pallet::storage]
-    #[pallet::getter(fn meta_data)]
-pub(super) type MetaDataStore<T: Config> = StorageValue<_, MetaData<T::AccountId, T::Balance>, ValueQuery>; 
-*/
-
-	#[pallet::storage] // <-- Step 5. code block will replace this.
-	pub(super) type Proofs<T: Config> = StorageMap<_, Blake2_128Concat, Vec<u8>, (T::AccountId, T::BlockNumber), ValueQuery>;
-
-	#[pallet::storage] // <-- Step 5. code block will replace this.
-	pub(super) type P2shaddress<T: Config> = StorageMap<_, Blake2_128Concat, Vec<u8>, (T::AccountId, T::BlockNumber), ValueQuery>;
+	#[pallet::storage]
+	#[pallet::getter(fn funds)]
+	/// Funds, by index.
+	pub(super) type Funds<T: Config> = StorageMap<_, Blake2_128Concat, FundIndex, FundInfoOf<T>>;
 
-	#[pallet::storage] // <-- Step 5. code block will replace this.
-	pub(super) type Prooflocks<T: Config> = StorageMap<_, Blake2_128Concat, Vec<u8>, (T::AccountId, Vec<u8> ), ValueQuery>;
-
-    /*
-	#[pallet::storage] // <-- Step 5. code block will replace this.
-	pub(super) type NftClassId<T: Config> = StorageMap<_, Blake2_128Concat, Vec<u8>, (T::AccountId, Vec<u8> ), ValueQuery>;
-
-	#[pallet::storage] // <-- Step 5. code block will replace this.
-	pub(super) type NftTokenMetaData<T: Config> = StorageMap<_, Blake2_128Concat, Vec<u8>, (T::AccountId, Vec<u8>   ), ValueQuery>;
-*/
-		// Learn more about declaring storage items:
-		// https://substrate.dev/docs/en/knowledgebase/runtime/storage#declaring-storage-items
-   
+	#[pallet::storage]
+	#[pallet::getter(fn fund_count)]
+	/// The number of funds ever created, which is also the next fund's index.
+	pub(super) type FundCount<T: Config> = StorageValue<_, FundIndex, ValueQuery>;
 
 	#[pallet::hooks]
 	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {}
 
-	#[pallet::call]   // <-- Step 6. code block will replace this.
+	#[pallet::call]
 	impl<T: Config> Pallet<T> {
-		#[pallet::weight(1_000)]
-		pub fn create_claim(
+		/// Create a fund for `beneficiary` that succeeds if it raises `goal` by block `end`.
+		/// The `SubmissionDeposit` is paid into the fund.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(3, 4))]
+		pub fn create(
 			origin: OriginFor<T>,
-			proof: Vec<u8>,
+			beneficiary: T::AccountId,
+			goal: BalanceOf<T>,
+			end: T::BlockNumber,
 		) -> DispatchResult {
-			// Check that the extrinsic was signed and get the signer.
-			// This function will return an error if the extrinsic is not signed.
-			// https://docs.substrate.io/v3/runtime/origins
-			let sender = ensure_signed(origin)?;
-
-
+			let creator = ensure_signed(origin)?;
+			ensure!(end > <frame_system::Pallet<T>>::block_number(), Error::<T>::EndTooEarly);
 
-            // Verify that the specified proof has not already been claimed.
-        ensure!(!Proofs::<T>::contains_key(&proof), Error::<T>::ProofAlreadyClaimed);
+			let index = Self::fund_count();
+			let next = index.checked_add(1).ok_or(Error::<T>::FundIndexOverflow)?;
+			let deposit = T::SubmissionDeposit::get();
+			T::Currency::transfer(&creator, &Self::fund_account_id(index), deposit, ExistenceRequirement::AllowDeath)?;
 
-        // Get the block number from the FRAME System pallet.
-        let current_block = <frame_system::Pallet<T>>::block_number();
-
-        // Store the proof with the sender and block number.
-        Proofs::<T>::insert(&proof, (&sender, current_block));
-
-
-
-			// Emit an event that the claim was created.
-			Self::deposit_event(Event::ClaimCreated(sender, proof));
+			<FundCount<T>>::put(next);
+			<Funds<T>>::insert(index, FundInfo { beneficiary, deposit, raised: Zero::zero(), end, goal });
 
+			Self::deposit_event(Event::Created(index, creator, end));
 			Ok(())
 		}
 
-		#[pallet::weight(1_000)]
-		pub fn update_claim(
-			origin: OriginFor<T>,
-			proof: Vec<u8>,
-			sentlock: Vec<u8>,
-		) -> DispatchResult {
-			// Check that the extrinsic was signed and get the signer.
-			// This function will return an error if the extrinsic is not signed.
-			// https://docs.substrate.io/v3/runtime/origins
-			let sender = ensure_signed(origin)?;
-
-			// Verify that the specified proof has not already been claimed.
-			if Proofs::<T>::contains_key(&proof) {
-
-			  // Get owner of the claim.
-			  let (owner, current_block) = Proofs::<T>::get(&proof);
-
-			  let (_, lock) = Prooflocks::<T>::get(&proof);
-
-			ensure!(sentlock == lock, Error::<T>::InvalidLock);
-
-			  if sender != owner {
-//			    Proofs::<T>::remove(&proof);
-			    Proofs::<T>::insert(&proof, (&sender, current_block));
-//			    Prooflocks::<T>::remove(&proof);
-		        Prooflocks::<T>::insert(&proof, (&sender, &lock));
-              }
-
-            }
-            else {
-            //
-			// Get the block number from the FRAME System module.
-			let current_block = <frame_system::Pallet<T>>::block_number();
+		/// Contribute `value` to a fund that has not ended.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(4, 4))]
+		pub fn contribute(origin: OriginFor<T>, index: FundIndex, value: BalanceOf<T>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(value >= T::MinContribution::get(), Error::<T>::ContributionTooSmall);
 
-			// Store the proof with the sender and block number.
-			Proofs::<T>::insert(&proof, (&sender, current_block));
+			let mut fund = Self::funds(index).ok_or(Error::<T>::InvalidIndex)?;
+			ensure!(<frame_system::Pallet<T>>::block_number() < fund.end, Error::<T>::ContributionPeriodOver);
 
-            }
+			T::Currency::transfer(&who, &Self::fund_account_id(index), value, ExistenceRequirement::AllowDeath)?;
+			fund.raised = fund.raised.saturating_add(value);
+			<Funds<T>>::insert(index, &fund);
 
-			// Emit an event that the claim was created.
-			Self::deposit_event(Event::UpdateCreated(sender, proof));
+			let balance = Self::contribution_get(index, &who);
+			Self::contribution_put(index, &who, &balance.saturating_add(value));
 
+			Self::deposit_event(Event::Contributed(who, index, value));
 			Ok(())
 		}
 
-		#[pallet::weight(1_000)]
-		pub fn lock_claim(
-			origin: OriginFor<T>,
-			proof: Vec<u8>,
-			lock: Vec<u8>,
-		) -> DispatchResult {
-			// Check that the extrinsic was signed and get the signer.
-			// This function will return an error if the extrinsic is not signed.
-			// https://docs.substrate.io/v3/runtime/origins
-			let sender = ensure_signed(origin)?;
-
-        ensure!(!Prooflocks::<T>::contains_key(&proof), Error::<T>::ProofAlreadyLocked);
-        ensure!(Proofs::<T>::contains_key(&proof), Error::<T>::ProofNotClaimed);
-
-			let (owner, _) = Proofs::<T>::get(&proof);
+		/// Take back all of the sender's contributions to a fund that ended below its goal.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(4, 4))]
+		pub fn withdraw(origin: OriginFor<T>, index: FundIndex) -> DispatchResult {
+			let who = ensure_signed(origin)?;
 
-			// Verify that sender of the current call is the claim owner.
-			ensure!(sender == owner, Error::<T>::NotProofOwner);
+			let mut fund = Self::funds(index).ok_or(Error::<T>::InvalidIndex)?;
+			ensure!(<frame_system::Pallet<T>>::block_number() >= fund.end, Error::<T>::FundStillActive);
+			ensure!(fund.raised < fund.goal, Error::<T>::FundSucceeded);
 
-		    Prooflocks::<T>::insert(&proof, (&sender, &lock));
-                
+			let balance = Self::contribution_get(index, &who);
+			ensure!(!balance.is_zero(), Error::<T>::NoContribution);
 
-			// Emit an event that the claim was created.
-			Self::deposit_event(Event::ClaimLocked(sender, lock));
+			T::Currency::transfer(&Self::fund_account_id(index), &who, balance, ExistenceRequirement::AllowDeath)?;
+			Self::contribution_kill(index, &who);
+			fund.raised = fund.raised.saturating_sub(balance);
+			<Funds<T>>::insert(index, &fund);
 
+			Self::deposit_event(Event::Withdrew(who, index, balance));
 			Ok(())
 		}
 
-		#[pallet::weight(1_000)]
-		pub fn create_pshaddress(
-			origin: OriginFor<T>,
-			pshaddress: Vec<u8>,
-		) -> DispatchResult {
-			// Check that the extrinsic was signed and get the signer.
-			// This function will return an error if the extrinsic is not signed.
-			// https://docs.substrate.io/v3/runtime/origins
-			let sender = ensure_signed(origin)?;
-
-			// Verify that the specified proof has not already been claimed.
-			ensure!(!P2shaddress::<T>::contains_key(&pshaddress), Error::<T>::ProofAlreadyClaimed);
+		/// Pay a fund that reached its goal to its beneficiary. The caller collects the
+		/// deposit and the fund is removed.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(4, 5))]
+		pub fn dispense(origin: OriginFor<T>, index: FundIndex) -> DispatchResult {
+			let caller = ensure_signed(origin)?;
 
-			// Get the block number from the FRAME System module.
-			let current_block = <frame_system::Pallet<T>>::block_number();
+			let fund = Self::funds(index).ok_or(Error::<T>::InvalidIndex)?;
+			ensure!(<frame_system::Pallet<T>>::block_number() >= fund.end, Error::<T>::FundStillActive);
+			ensure!(fund.raised >= fund.goal, Error::<T>::UnsuccessfulFund);
 
-			// Store the proof with the sender and block number.
-			P2shaddress::<T>::insert(&pshaddress, (&sender, current_block));
+			let account = Self::fund_account_id(index);
+			T::Currency::transfer(&account, &fund.beneficiary, fund.raised, ExistenceRequirement::AllowDeath)?;
+			// The deposit and anything sent to the fund directly go to the caller.
+			let rest = T::Currency::free_balance(&account);
+			T::Currency::transfer(&account, &caller, rest, ExistenceRequirement::AllowDeath)?;
 
-			// Emit an event that the claim was created.
-			Self::deposit_event(Event::PshAddressCreated(sender, pshaddress));
+			Self::crowdfund_kill(index);
+			<Funds<T>>::remove(index);
 
+			Self::deposit_event(Event::Dispensed(index, fund.beneficiary, fund.raised, caller));
 			Ok(())
 		}
 
-		#[pallet::weight(10_000)]
-		pub fn revoke_claim(
-			origin: OriginFor<T>,
-			proof: Vec<u8>,
-		) -> DispatchResult {
-			// Check that the extrinsic was signed and get the signer.
-			// This function will return an error if the extrinsic is not signed.
-			// https://docs.substrate.io/v3/runtime/origins
-			let sender = ensure_signed(origin)?;
-
-			// Verify that the specified proof has been claimed.
-			ensure!(Proofs::<T>::contains_key(&proof), Error::<T>::NoSuchProof);
-
-			// Get owner of the claim.
-			let (owner, _) = Proofs::<T>::get(&proof);
-
-			// Verify that sender of the current call is the claim owner.
-			ensure!(sender == owner, Error::<T>::NotProofOwner);
-
-			// Remove claim from storage.
-			Proofs::<T>::remove(&proof);
-
-			// Emit an event that the claim was erased.
-			Self::deposit_event(Event::ClaimRevoked(sender, proof));
-
+		/// Remove a fund `RetirementPeriod` blocks after its end. A fund that reached its goal
+		/// but was never dispensed still pays what it raised to the beneficiary. Whatever else is
+		/// left in it, such as the deposit and contributions nobody withdrew, goes to the caller.
+		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(5, 5))]
+		pub fn dissolve(origin: OriginFor<T>, index: FundIndex) -> DispatchResult {
+			let caller = ensure_signed(origin)?;
+
+			let fund = Self::funds(index).ok_or(Error::<T>::InvalidIndex)?;
+			let now = <frame_system::Pallet<T>>::block_number();
+			ensure!(now >= fund.end.saturating_add(T::RetirementPeriod::get()), Error::<T>::FundNotRetired);
+
+			let account = Self::fund_account_id(index);
+			if fund.raised >= fund.goal {
+				T::Currency::transfer(&account, &fund.beneficiary, fund.raised, ExistenceRequirement::AllowDeath)?;
+				Self::deposit_event(Event::Dispensed(index, fund.beneficiary, fund.raised, caller.clone()));
+			}
+			let rest = T::Currency::free_balance(&account);
+			T::Currency::transfer(&account, &caller, rest, ExistenceRequirement::AllowDeath)?;
+
+			Self::crowdfund_kill(index);
+			<Funds<T>>::remove(index);
+
+			Self::deposit_event(Event::Dissolved(index, caller));
 			Ok(())
 		}
+	}
 
-/*
-		#[pallet::weight(20_000)]
-		pub fn create_nft(origin  : OriginFor<T>, 
-                    metadata: Vec<u8> , 
-                    data: () ) -> DispatchResult{
-
-			let who = ensure_signed(origin)?;
-			let res = <orml_nft::Pallet<T>>::create_class(&who,metadata.clone(),data);
-			//NftClassId::<T>::insert(&who, (&who, &metadata ));
-			//NftTokenMetaData::<T>::insert(&who, (&who, metadata.clone()));
-			// <NftClassId<T>>::insert(&who,res.unwrap());
-			// <NftTokenMetaData<T>>::insert(&who,metadata);
-			Self::deposit_event(Event::TokenIssuedBy(who));
-			Ok(())
+	impl<T: Config> Pallet<T> {
+		/// The account holding the balance of fund `index`.
+		pub fn fund_account_id(index: FundIndex) -> T::AccountId {
+			T::PalletId::get().into_sub_account(index)
 		}
-*/
 
-/*
-#[weight = 10_000 + T::DbWeight::get().writes(1)]
-		pub fn mint_nft(origin, data: <T as orml_nft::Trait>::TokenData) -> dispatch::DispatchResult{
-			let who = ensure_signed(origin)?;
-			let metadata = <NftTokenMetaData<T>>::get(&who);
-			let classid = <NftClassId<T>>::get(&who);
-			
-			let _res = <orml_nft::Module<T>>::mint(&who,classid,metadata,data);
-			
-			Self::deposit_event(RawEvent::TokenMinted(who));
-			Ok(())
+		/// The child trie holding the contributions to fund `index`.
+		pub fn id_from_index(index: FundIndex) -> child::ChildInfo {
+			let mut buf = Vec::new();
+			buf.extend_from_slice(b"crowdfnd");
+			buf.extend_from_slice(&index.to_le_bytes()[..]);
+			child::ChildInfo::new_default(T::Hashing::hash(&buf[..]).as_ref())
 		}
 
-*/
+		/// How much `who` has contributed to fund `index`.
+		pub fn contribution_get(index: FundIndex, who: &T::AccountId) -> BalanceOf<T> {
+			let id = Self::id_from_index(index);
+			who.using_encoded(|b| child::get_or_default::<BalanceOf<T>>(&id, b))
+		}
 
+		fn contribution_put(index: FundIndex, who: &T::AccountId, balance: &BalanceOf<T>) {
+			let id = Self::id_from_index(index);
+			who.using_encoded(|b| child::put(&id, b, balance));
+		}
 
+		fn contribution_kill(index: FundIndex, who: &T::AccountId) {
+			let id = Self::id_from_index(index);
+			who.using_encoded(|b| child::kill(&id, b));
+		}
 
+		fn crowdfund_kill(index: FundIndex) {
+			let id = Self::id_from_index(index);
+			// The number of contributors is not bounded, so remove them all.
+			let _ = child::kill_storage(&id, None);
+		}
 	}
 }
@@ -1,29 +1,88 @@
+#![cfg(test)]
+
 use super::*;
+use frame_support::{assert_noop, assert_ok};
+use mocks::*;
 
 #[test]
-fn error_works(){
+fn successful_fund_is_dispensed_to_beneficiary() {
     new_test_ext().execute_with(|| {
-        assert_err!(
-            TestingPallet::add_value(Origin::signed(1), 51),
-            "value must be <= maximum add amount constant"
-        );
+        assert_noop!(Crowd::create(Origin::signed(1), 9, 50, 1), Error::<Test>::EndTooEarly);
+        assert_ok!(Crowd::create(Origin::signed(1), 9, 50, 10));
+        let account = Crowd::fund_account_id(0);
+        assert_eq!(Balances::free_balance(account), 10);
+        assert_eq!(Crowd::fund_count(), 1);
+
+        assert_noop!(Crowd::contribute(Origin::signed(2), 0, 4), Error::<Test>::ContributionTooSmall);
+        assert_noop!(Crowd::contribute(Origin::signed(2), 1, 5), Error::<Test>::InvalidIndex);
+        assert_ok!(Crowd::contribute(Origin::signed(2), 0, 30));
+        assert_ok!(Crowd::contribute(Origin::signed(3), 0, 20));
+        assert_eq!(Crowd::contribution_get(0, &2), 30);
+        assert_eq!(Crowd::funds(0).unwrap().raised, 50);
+        assert_noop!(Crowd::dispense(Origin::signed(3), 0), Error::<Test>::FundStillActive);
+
+        System::set_block_number(10);
+        assert_noop!(Crowd::contribute(Origin::signed(2), 0, 5), Error::<Test>::ContributionPeriodOver);
+        assert_noop!(Crowd::withdraw(Origin::signed(2), 0), Error::<Test>::FundSucceeded);
+        assert_ok!(Crowd::dispense(Origin::signed(3), 0));
+        System::assert_last_event(mocks::Event::Crowd(crate::Event::Dispensed(0, 9, 50, 3)));
+
+        assert_eq!(Balances::free_balance(9), 50);
+        // The caller collects the deposit.
+        assert_eq!(Balances::free_balance(3), 90);
+        assert_eq!(Balances::free_balance(account), 0);
+        assert_eq!(Crowd::funds(0), None);
+        assert_eq!(Crowd::contribution_get(0, &2), 0);
     })
 }
 
 #[test]
-fn test_should_work() {
+fn failed_fund_is_withdrawn_then_dissolved() {
     new_test_ext().execute_with(|| {
-        assert_ok!(
-            TestingPallet::add_value(Origin::signed(1), 10)
-        );
+        assert_ok!(Crowd::create(Origin::signed(1), 9, 100, 5));
+        assert_ok!(Crowd::contribute(Origin::signed(2), 0, 20));
+        assert_ok!(Crowd::contribute(Origin::signed(3), 0, 10));
+        assert_ok!(Crowd::contribute(Origin::signed(3), 0, 10));
+        assert_eq!(Crowd::contribution_get(0, &3), 20);
+        assert_noop!(Crowd::withdraw(Origin::signed(2), 0), Error::<Test>::FundStillActive);
+
+        System::set_block_number(5);
+        assert_noop!(Crowd::dispense(Origin::signed(2), 0), Error::<Test>::UnsuccessfulFund);
+        assert_ok!(Crowd::withdraw(Origin::signed(2), 0));
+        System::assert_last_event(mocks::Event::Crowd(crate::Event::Withdrew(2, 0, 20)));
+        assert_eq!(Balances::free_balance(2), 100);
+        assert_eq!(Crowd::funds(0).unwrap().raised, 20);
+        assert_noop!(Crowd::withdraw(Origin::signed(2), 0), Error::<Test>::NoContribution);
+
+        assert_noop!(Crowd::dissolve(Origin::signed(1), 0), Error::<Test>::FundNotRetired);
+        System::set_block_number(10);
+        // What 3 never withdrew goes to the caller along with the deposit.
+        assert_ok!(Crowd::dissolve(Origin::signed(1), 0));
+        System::assert_last_event(mocks::Event::Crowd(crate::Event::Dissolved(0, 1)));
+        assert_eq!(Balances::free_balance(1), 120);
+        assert_eq!(Crowd::funds(0), None);
+        assert_eq!(Crowd::contribution_get(0, &3), 0);
+        assert_noop!(Crowd::withdraw(Origin::signed(3), 0), Error::<Test>::InvalidIndex);
     })
 }
 
 #[test]
-fn test_should_fail() {
+fn successful_fund_pays_beneficiary_when_dissolved() {
     new_test_ext().execute_with(|| {
-        assert_ok!(
-            TestingPallet::add_value(Origin::signed(1), 100)
-        );
+        assert_ok!(Crowd::create(Origin::signed(1), 9, 50, 5));
+        assert_ok!(Crowd::contribute(Origin::signed(2), 0, 30));
+        assert_ok!(Crowd::contribute(Origin::signed(3), 0, 20));
+
+        // Nobody dispenses the fund, so it is dissolved once retired.
+        System::set_block_number(10);
+        assert_ok!(Crowd::dissolve(Origin::signed(2), 0));
+        System::assert_has_event(mocks::Event::Crowd(crate::Event::Dispensed(0, 9, 50, 2)));
+        System::assert_last_event(mocks::Event::Crowd(crate::Event::Dissolved(0, 2)));
+
+        assert_eq!(Balances::free_balance(9), 50);
+        // The caller only collects the deposit.
+        assert_eq!(Balances::free_balance(2), 80);
+        assert_eq!(Balances::free_balance(Crowd::fund_account_id(0)), 0);
+        assert_eq!(Crowd::funds(0), None);
     })
 }
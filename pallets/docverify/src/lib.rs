@@ -16,15 +16,23 @@ pub mod pallet {
 //use sp_core::{Pair, Public};
 use sp_runtime::traits::{IdentifyAccount, SignedExtension, Verify };
 use sp_runtime::{ MultiSignature };
-//	use pallet_studentid::*;
+	use pallet_studentid_primitives::IdentityProvider;
 	use sp_std::vec::Vec; // Step 3.1 will include this in `Cargo.toml`
 
 	#[pallet::config]  // <-- Step 2. code block will replace this.
-	pub trait Config: frame_system::Config + pallet_balances::Config + pallet_studentid::Config  {
+	pub trait Config: frame_system::Config + pallet_balances::Config  {
 
 		/// The overarching event type.
 		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
 
+		/// The origin which may verify or reject submitted documents, e.g.
+		/// `pallet_studentid::EnsureStudentRole<Runtime, roles::Faculty>`.
+		type VerifierOrigin: EnsureOrigin<Self::Origin, Success = Self::AccountId>;
+
+		/// Where student identities and their service access come from, usually
+		/// `pallet_studentid::Pallet<Runtime>`.
+		type Identity: IdentityProvider<Self::AccountId>;
+
 
 	}
 
@@ -34,6 +42,11 @@ use sp_runtime::{ MultiSignature };
      pub type DocumentStatus = Vec<u8>;
      pub type DocumentLink = Vec<u8>;
 
+     /// The `DocumentStatus` of a document a verifier has accepted.
+     pub const DOCUMENT_VERIFIED: &[u8] = b"verified";
+     /// The `DocumentStatus` of a document a verifier has rejected.
+     pub const DOCUMENT_REJECTED: &[u8] = b"rejected";
+
 pub type Signature = MultiSignature;
 pub type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::AccountId;
 	#[pallet::event]   // <-- Step 3. code block will replace this.
@@ -53,6 +66,10 @@ pub type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::Account
         SomethingStored(u32, T::AccountId),
 		TokenIssuedBy(T::AccountId),
 		TokenMinted(T::AccountId),
+		/// A submitted document was verified. [verifier, document]
+		DocumentVerified(T::AccountId, Vec<u8>),
+		/// A submitted document was rejected. [verifier, document]
+		DocumentRejected(T::AccountId, Vec<u8>),
 	}
 
 	#[pallet::error]   // <-- Step 4. code block will replace this.
@@ -69,6 +86,8 @@ pub type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::Account
 		ProofNotClaimed,
 
 		InvalidLock,
+		/// The caller's identity has not been granted access to the service.
+		NoServiceAccess,
 	}
 
 	#[pallet::pallet]
@@ -82,6 +101,9 @@ pub type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::Account
 	pub(super) type P2shaddress<T: Config> = StorageMap<_, Blake2_128Concat, Vec<u8>, (T::AccountId, T::BlockNumber), ValueQuery>;
 
 	#[pallet::storage] // <-- Step 5. code block will replace this.
+	#[pallet::getter(fn document)]
+	/// Submitter, verifier, link and status of each document a verifier has decided on. The
+	/// credential RPC only lists documents whose status is `DOCUMENT_VERIFIED`.
 	pub(super) type Documentverify<T: Config> = StorageMap<_, Blake2_128Concat, DocumentHash, (Submitter<T> , Verifier<T>, DocumentLink, DocumentStatus ), ValueQuery>;
 
     /*
@@ -144,7 +166,11 @@ pub type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::Account
              * Note:- Document link can be accessed outside this function.
              * Some random person cannot verify
              * */
+			let verifier = T::VerifierOrigin::ensure_origin(verifier)?;
 
+			Self::set_document_status(&verifier, &dochash, DOCUMENT_VERIFIED)?;
+
+			Self::deposit_event(Event::DocumentVerified(verifier, dochash));
 			Ok(())
 		}
 
@@ -162,7 +188,11 @@ pub type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::Account
              * Note:- Document link can be accessed outside this function.
              *
              * */
+			let verifier = T::VerifierOrigin::ensure_origin(verifier)?;
+
+			Self::set_document_status(&verifier, &dochash, DOCUMENT_REJECTED)?;
 
+			Self::deposit_event(Event::DocumentRejected(verifier, dochash));
 			Ok(())
 		}
 
@@ -301,16 +331,12 @@ pub type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::Account
 		pub fn create_nft(origin  : OriginFor<T>, 
                     metadata: Vec<u8> , 
                     data: () ) -> DispatchResult{
-            let service: Vec<u8> = b"moodle".to_vec();
-			let id = pallet_studentid::Pallet::<T>::check_web3access_sel18(origin.clone(), service);
-
-			let who = ensure_signed(origin.clone())?;
+			let who = ensure_signed(origin)?;
+			ensure!(T::Identity::has_access(&who, b"moodle"), Error::<T>::NoServiceAccess);
 			//let id = pallet_studentid::Pallet::<T>::set_account_id(origin, 1, who.clone());
 			// let res = orml_nft::Pallet::<T>::create_class(&who,vec![1],() as <T as orml_nft::Config>::ClassData);
 //             let xx : BoundedVec<_,  pallet_studentid::Pallet::<T>::Config::MaxEmailsize> = metadata.clone().try_into().unwrap();
 
-			let id = pallet_studentid::Pallet::<T>::identity(who.clone());
-
  //           id.accountId =  who.clone();
 //            <pallet_studentid::Pallet::<T> as pallet_studentid::Pallet::<T>::Config>::Identity1Of::insert(&who, id);
 
@@ -325,4 +351,20 @@ pub type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::Account
 
 
   }
+
+	impl<T: Config> Pallet<T> {
+		/// Record `verifier`'s decision on a submitted document: `DOCUMENT_VERIFIED` or
+		/// `DOCUMENT_REJECTED`. Only the calls gated by `VerifierOrigin` should reach this.
+		pub fn set_document_status(verifier: &T::AccountId, dochash: &Vec<u8>, status: &[u8]) -> DispatchResult {
+			ensure!(Proofs::<T>::contains_key(dochash), Error::<T>::NoSuchProof);
+			let (submitter, _) = Proofs::<T>::get(dochash);
+
+			Documentverify::<T>::mutate(dochash, |(doc_submitter, doc_verifier, _link, doc_status)| {
+				*doc_submitter = submitter;
+				*doc_verifier = verifier.clone();
+				*doc_status = status.to_vec();
+			});
+			Ok(())
+		}
+	}
 }
@@ -26,7 +26,6 @@ frame_support::construct_runtime!(
 	{
 		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
 		TestingPallet: pallet_docverify::{Pallet, Call, Storage, Event<T>},
-        Identity: pallet_studentid::{Pallet, Call, Storage, Event<T>},
         Balances: pallet_balances::{Pallet, Call, Storage, Event<T>},
 
 	}
@@ -56,46 +55,9 @@ impl pallet_balances::Config for Test {
 }
 
 
-parameter_types! {
-    pub const BasicDeposit: u64 = 10;
-    pub const FieldDeposit: u64 = 10;
-    pub const SubAccountDeposit: u64 = 10;
-    pub const MaxSubAccounts: u32 = 2;
-    pub const MaxUseridentities: u32 = 2;
-    pub const MaxAdditionalFields: u32 = 2;
-    pub const MaxRegistrars: u32 = 20;
-    pub const MaxEmailsize: u32 = 30;
-    pub const MaxTokenid: u32 = 30;
-
-}
 ord_parameter_types! {
-    pub const One: u64 = 1;
-    pub const Two: u64 = 2;
-    pub const MaxAccessTokenMetadata: u32 = 1;
-
+    pub const Faculty: u64 = 11;
 }
-//type EnsureOneOrRoot = EnsureOneOf<EnsureRoot<u64>, EnsureSignedBy<One, u64>>;
-//type EnsureTwoOrRoot = EnsureOneOf<EnsureRoot<u64>, EnsureSignedBy<Two, u64>>;
-impl pallet_studentid::Config for Test {
-    type Event = Event;
-    type Currency = Balances;
-    type Slashed = ();
-    type BasicDeposit = BasicDeposit;
-    type FieldDeposit = FieldDeposit;
-    type SubAccountDeposit = SubAccountDeposit;
-    type MaxSubAccounts = MaxSubAccounts;
-    type MaxUseridentities = MaxUseridentities;
-    type MaxAdditionalFields = MaxAdditionalFields;
-    type MaxRegistrars = MaxRegistrars;
-    type MaxEmailsize = MaxEmailsize;
-    type MaxAccessTokenMetadata = MaxAccessTokenMetadata;
-    type MaxTokenid = MaxTokenid;
-
-    type RegistrarOrigin = frame_system::EnsureRoot<Self::AccountId> ;
-    type ForceOrigin = frame_system::EnsureRoot<Self::AccountId>  ;
-    type WeightInfo = ();
-}
-
 
 parameter_types! {
 	pub const BlockHashCount: u64 = 250;
@@ -140,6 +102,8 @@ parameter_types! {
 
 impl pallet_docverify::Config for Test {
     type Event = Event;
+    type VerifierOrigin = frame_system::EnsureSignedBy<Faculty, u64>;
+    type Identity = ();
 }
 
 // Build genesis storage according to the mock runtime.
@@ -16,3 +16,40 @@ fn doc_submit_test() {
 
     });
 }
+
+#[test]
+fn doc_verify_requires_verifier_origin() {
+    new_test_ext().execute_with(|| {
+        let dochash: Vec<u8> = b"x4ydu6788".to_vec();
+        let manager = 11;
+        let staff = 10;
+
+        assert_noop!(
+            TestingPallet::document_verify_sel32(Origin::signed(staff), dochash.clone()),
+            frame_support::error::BadOrigin
+        );
+        assert_noop!(
+            TestingPallet::document_verify_sel32(Origin::signed(manager), dochash.clone()),
+            Error::<Test>::NoSuchProof
+        );
+
+        assert_ok!(TestingPallet::document_submit_sel31(Origin::signed(staff), dochash.clone()));
+        assert_ok!(TestingPallet::document_verify_sel32(Origin::signed(manager), dochash.clone()));
+        let (submitter, verifier, _, status) = Documentverify::<Test>::get(&dochash);
+        assert_eq!((submitter, verifier), (staff, manager));
+        assert_eq!(status, DOCUMENT_VERIFIED.to_vec());
+
+        assert_ok!(TestingPallet::document_reject_sel33(Origin::signed(manager), dochash.clone()));
+        assert_eq!(Documentverify::<Test>::get(&dochash).3, DOCUMENT_REJECTED.to_vec());
+    });
+}
+
+#[test]
+fn create_nft_requires_service_access() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            TestingPallet::create_nft(Origin::signed(10), b"meta".to_vec(), ()),
+            Error::<Test>::NoServiceAccess
+        );
+    });
+}
@@ -0,0 +1,70 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2019-2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Types and traits shared between the student identity pallet and the pallets that use it.
+//!
+//! Pallets that need to know who a caller is take an [`IdentityProvider`] in their `Config`
+//! instead of depending on `pallet_studentid` directly.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+use sp_runtime::RuntimeDebug;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+
+/// The key an identity is stored under: a domain-separated hash of the normalised email address.
+pub type EmailHash = [u8; 32];
+
+/// What an identity is to its institution. Assigned by institution admins, or `Student` when an
+/// institution enrolls the identity; a self-registered identity has no role.
+#[derive(Encode, Decode, Clone, Copy, Eq, PartialEq, MaxEncodedLen, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum Role {
+	Student,
+	Faculty,
+	Staff,
+	Admin,
+}
+
+/// Read access to student identities for other pallets.
+pub trait IdentityProvider<AccountId> {
+	/// The identity `who` is linked to, if any.
+	fn identity_of(who: &AccountId) -> Option<EmailHash>;
+
+	/// The role of the identity `who` is linked to, if it is linked and has been given one.
+	fn role_of(who: &AccountId) -> Option<Role>;
+
+	/// Whether `who` is linked to an identity that has been granted access to `service`.
+	fn has_access(who: &AccountId, service: &[u8]) -> bool;
+}
+
+/// No identities: nobody is linked and nobody has access. Useful in tests.
+impl<AccountId> IdentityProvider<AccountId> for () {
+	fn identity_of(_: &AccountId) -> Option<EmailHash> {
+		None
+	}
+
+	fn role_of(_: &AccountId) -> Option<Role> {
+		None
+	}
+
+	fn has_access(_: &AccountId, _: &[u8]) -> bool {
+		false
+	}
+}
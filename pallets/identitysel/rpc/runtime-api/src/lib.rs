@@ -0,0 +1,43 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2019-2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runtime API definition for the student identity pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use sp_std::prelude::*;
+
+pub use pallet_studentid::{DidDetails, DidKeyType, DidVerificationMethod, EmailHash, IdentitySummary};
+
+sp_api::decl_runtime_apis! {
+	/// Read access to student identities without decoding raw storage.
+	pub trait StudentIdApi<AccountId> where
+		AccountId: Codec,
+	{
+		/// The identity `who` is linked to.
+		fn identity_of_account(who: AccountId) -> Option<IdentitySummary<AccountId>>;
+		/// The identity stored under `email_hash`.
+		fn identity_by_email_hash(email_hash: EmailHash) -> Option<IdentitySummary<AccountId>>;
+		/// Whether `who` is linked to an identity that has been granted access to `service`.
+		fn has_access(who: AccountId, service: Vec<u8>) -> bool;
+		/// Whether `token_id` is a known access token owned by `who`.
+		fn validate_token(token_id: Vec<u8>, who: AccountId) -> bool;
+		/// What the DID document of the identity under `email_hash` is rendered from.
+		fn did_details(email_hash: EmailHash) -> Option<DidDetails<AccountId>>;
+	}
+}
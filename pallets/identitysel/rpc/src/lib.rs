@@ -0,0 +1,338 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2019-2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! RPC interface for the student identity pallet.
+
+use std::sync::Arc;
+
+use codec::{Codec, Decode, Encode};
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+pub use pallet_studentid_rpc_runtime_api::StudentIdApi as StudentIdRuntimeApi;
+use pallet_studentid_rpc_runtime_api::{DidDetails, DidKeyType, IdentitySummary};
+use serde_json::{json, Value};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::{bytes::from_hex, hexdisplay::HexDisplay, Bytes, H256};
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+/// Student identity RPC methods.
+#[rpc]
+pub trait StudentIdApi<BlockHash, AccountId> {
+	/// The identity `who` is linked to.
+	#[rpc(name = "studentid_identityOfAccount")]
+	fn identity_of_account(
+		&self,
+		who: AccountId,
+		at: Option<BlockHash>,
+	) -> Result<Option<IdentitySummary<AccountId>>>;
+
+	/// The identity stored under `email_hash`.
+	#[rpc(name = "studentid_identityByEmailHash")]
+	fn identity_by_email_hash(
+		&self,
+		email_hash: H256,
+		at: Option<BlockHash>,
+	) -> Result<Option<IdentitySummary<AccountId>>>;
+
+	/// Whether `who` is linked to an identity that has been granted access to `service`.
+	#[rpc(name = "studentid_hasAccess")]
+	fn has_access(&self, who: AccountId, service: Bytes, at: Option<BlockHash>) -> Result<bool>;
+
+	/// Whether `token_id` is a known access token owned by `who`.
+	#[rpc(name = "studentid_validateToken")]
+	fn validate_token(&self, token_id: Bytes, who: AccountId, at: Option<BlockHash>)
+		-> Result<bool>;
+
+	/// Resolve a `did:studentid:0x<email hash>` identity DID into a W3C DID document, or `None`
+	/// if no identity is stored under the hash. A `did:studentid:account:0x<account>` account
+	/// DID always resolves.
+	#[rpc(name = "studentid_resolveDid")]
+	fn resolve_did(&self, did: String, at: Option<BlockHash>) -> Result<Option<Value>>;
+}
+
+/// The DID method name.
+pub const DID_METHOD: &str = "studentid";
+
+/// Parse the email hash out of a `did:studentid:0x<email hash>` DID.
+pub fn parse_did(did: &str) -> Option<[u8; 32]> {
+	let id = did.strip_prefix("did:")?.strip_prefix(DID_METHOD)?.strip_prefix(':')?;
+	from_hex(id).ok()?.try_into().ok()
+}
+
+/// The DID naming the identity under `email_hash`.
+pub fn did_of(email_hash: &[u8; 32]) -> String {
+	format!("did:{}:0x{}", DID_METHOD, HexDisplay::from(email_hash))
+}
+
+/// Parse the account out of a `did:studentid:account:0x<encoded account>` DID.
+pub fn parse_account_did<AccountId: Decode>(did: &str) -> Option<AccountId> {
+	let id = did
+		.strip_prefix("did:")?
+		.strip_prefix(DID_METHOD)?
+		.strip_prefix(":account:")?;
+	let encoded = from_hex(id).ok()?;
+	let mut input = &encoded[..];
+	let account = AccountId::decode(&mut input).ok()?;
+	input.is_empty().then(|| account)
+}
+
+/// The DID naming `account`. Every account, linked to an identity or not, has its own DID.
+pub fn account_did_of<AccountId: Encode>(account: &AccountId) -> String {
+	format!("did:{}:account:0x{}", DID_METHOD, HexDisplay::from(&account.encode()))
+}
+
+/// Render `details` as a DID document (<https://www.w3.org/TR/did-core/>).
+///
+/// Keys from the on-chain document are the verification methods, as `#key-N`; they can
+/// authenticate and make assertions. The document's controller and the linked accounts are
+/// named by their account DIDs, the latter under `alsoKnownAs`. Services are listed as
+/// `#service-N` with the granted service name as endpoint.
+pub fn did_document<AccountId: Codec>(details: &DidDetails<AccountId>) -> Value {
+	let did = did_of(&details.email_hash);
+	let methods: Vec<Value> = details
+		.verification_methods
+		.iter()
+		.enumerate()
+		.map(|(i, m)| {
+			let key_type = match m.key_type {
+				DidKeyType::Sr25519 => "Sr25519VerificationKey2020",
+				DidKeyType::Ed25519 => "Ed25519VerificationKey2018",
+			};
+			json!({
+				"id": format!("{}#key-{}", did, i),
+				"type": key_type,
+				"controller": did,
+				"publicKeyHex": format!("{}", HexDisplay::from(&m.public_key)),
+			})
+		})
+		.collect();
+	let method_ids: Vec<Value> = methods.iter().map(|m| m["id"].clone()).collect();
+	let services: Vec<Value> = details
+		.services
+		.iter()
+		.enumerate()
+		.map(|(i, service)| {
+			json!({
+				"id": format!("{}#service-{}", did, i),
+				"type": "StudentIdService",
+				"serviceEndpoint": String::from_utf8_lossy(service),
+			})
+		})
+		.collect();
+
+	let mut document = json!({
+		"@context": ["https://www.w3.org/ns/did/v1"],
+		"id": did,
+		"alsoKnownAs": details.linked_accounts.iter().map(account_did_of).collect::<Vec<_>>(),
+		"verificationMethod": methods,
+		"authentication": method_ids,
+		"assertionMethod": method_ids,
+		"service": services,
+	});
+	if let Some(controller) = &details.controller {
+		document["controller"] = json!(account_did_of(controller));
+	}
+	document
+}
+
+/// Render the DID document of `account`, linked to the identity in `summary` if any.
+///
+/// An account id does not tell which kind of key it belongs to, so the document lists no
+/// verification methods; it only points at the identity the account is linked to.
+pub fn account_did_document<AccountId: Codec>(
+	account: &AccountId,
+	summary: Option<&IdentitySummary<AccountId>>,
+) -> Value {
+	json!({
+		"@context": ["https://www.w3.org/ns/did/v1"],
+		"id": account_did_of(account),
+		"alsoKnownAs": summary.map(|summary| did_of(&summary.email_hash)).into_iter().collect::<Vec<_>>(),
+	})
+}
+
+/// Implements the [`StudentIdApi`] RPC trait by calling into the runtime.
+pub struct StudentId<C, B> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> StudentId<C, B> {
+	/// Create new `StudentId` with the given reference to the client.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: Default::default() }
+	}
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+	/// The call to runtime failed.
+	RuntimeError,
+	/// The DID is not a `did:studentid` DID.
+	InvalidDid,
+}
+
+impl From<Error> for i64 {
+	fn from(e: Error) -> i64 {
+		match e {
+			Error::RuntimeError => 1,
+			Error::InvalidDid => 2,
+		}
+	}
+}
+
+fn runtime_error(e: impl std::fmt::Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(Error::RuntimeError.into()),
+		message: "Unable to query student identity.".into(),
+		data: Some(format!("{:?}", e).into()),
+	}
+}
+
+impl<C, Block, AccountId> StudentIdApi<<Block as BlockT>::Hash, AccountId> for StudentId<C, Block>
+where
+	Block: BlockT,
+	C: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: StudentIdRuntimeApi<Block, AccountId>,
+	AccountId: Codec + Clone,
+{
+	fn identity_of_account(
+		&self,
+		who: AccountId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<IdentitySummary<AccountId>>> {
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		self.client.runtime_api().identity_of_account(&at, who).map_err(runtime_error)
+	}
+
+	fn identity_by_email_hash(
+		&self,
+		email_hash: H256,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<IdentitySummary<AccountId>>> {
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		self.client
+			.runtime_api()
+			.identity_by_email_hash(&at, email_hash.into())
+			.map_err(runtime_error)
+	}
+
+	fn has_access(
+		&self,
+		who: AccountId,
+		service: Bytes,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<bool> {
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		self.client.runtime_api().has_access(&at, who, service.to_vec()).map_err(runtime_error)
+	}
+
+	fn validate_token(
+		&self,
+		token_id: Bytes,
+		who: AccountId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<bool> {
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		self.client
+			.runtime_api()
+			.validate_token(&at, token_id.to_vec(), who)
+			.map_err(runtime_error)
+	}
+
+	fn resolve_did(&self, did: String, at: Option<<Block as BlockT>::Hash>) -> Result<Option<Value>> {
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		if let Some(email_hash) = parse_did(&did) {
+			let details =
+				self.client.runtime_api().did_details(&at, email_hash).map_err(runtime_error)?;
+			return Ok(details.as_ref().map(did_document))
+		}
+		if let Some(account) = parse_account_did::<AccountId>(&did) {
+			let summary = self
+				.client
+				.runtime_api()
+				.identity_of_account(&at, account.clone())
+				.map_err(runtime_error)?;
+			return Ok(Some(account_did_document(&account, summary.as_ref())))
+		}
+		Err(RpcError {
+			code: ErrorCode::ServerError(Error::InvalidDid.into()),
+			message: "Not a did:studentid DID.".into(),
+			data: Some(did.into()),
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn did_round_trips() {
+		let did = did_of(&[0xab; 32]);
+		assert_eq!(did, format!("did:studentid:0x{}", "ab".repeat(32)));
+		assert_eq!(parse_did(&did), Some([0xab; 32]));
+		assert_eq!(parse_did("did:example:0xab"), None);
+		assert_eq!(parse_did("did:studentid:0xabab"), None);
+	}
+
+	#[test]
+	fn account_did_round_trips() {
+		let did = account_did_of(&[0xcd_u8; 32]);
+		assert_eq!(did, format!("did:studentid:account:0x{}", "cd".repeat(32)));
+		assert_eq!(parse_account_did::<[u8; 32]>(&did), Some([0xcd; 32]));
+		assert_eq!(parse_did(&did), None);
+		assert_eq!(parse_account_did::<[u8; 32]>(&did_of(&[0xcd; 32])), None);
+		assert_eq!(parse_account_did::<[u8; 32]>("did:studentid:account:0xcdcd"), None);
+	}
+
+	#[test]
+	fn did_document_lists_keys_accounts_and_services() {
+		let details = DidDetails {
+			email_hash: [1; 32],
+			controller: Some([2u8; 32]),
+			verification_methods: vec![pallet_studentid_rpc_runtime_api::DidVerificationMethod {
+				key_type: DidKeyType::Ed25519,
+				public_key: [3; 32],
+			}],
+			linked_accounts: vec![[2u8; 32], [4u8; 32]],
+			services: vec![b"moodle".to_vec()],
+		};
+		let doc = did_document(&details);
+		let did = did_of(&[1; 32]);
+		assert_eq!(doc["id"], json!(did));
+		assert_eq!(doc["controller"], json!(account_did_of(&[2u8; 32])));
+		assert!(doc.get("studentIdController").is_none());
+		assert_eq!(doc["verificationMethod"].as_array().unwrap().len(), 1);
+		assert_eq!(doc["verificationMethod"][0]["type"], json!("Ed25519VerificationKey2018"));
+		assert_eq!(doc["authentication"], json!([format!("{}#key-0", did)]));
+		assert_eq!(doc["alsoKnownAs"], json!([account_did_of(&[2u8; 32]), account_did_of(&[4u8; 32])]));
+		assert_eq!(doc["service"][0]["serviceEndpoint"], json!("moodle"));
+
+		let summary = IdentitySummary {
+			email_hash: [1; 32],
+			owner: [2u8; 32],
+			linked_accounts: vec![[4u8; 32]],
+			service: None,
+			recovery_configured: false,
+		};
+		let doc = account_did_document(&[4u8; 32], Some(&summary));
+		assert_eq!(doc["id"], json!(account_did_of(&[4u8; 32])));
+		assert_eq!(doc["alsoKnownAs"], json!([did]));
+		assert_eq!(account_did_document(&[5u8; 32], None)["alsoKnownAs"], json!([]));
+	}
+}
@@ -15,17 +15,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-//! Identity pallet benchmarking.
+//! Student identity pallet benchmarking.
 
 #![cfg(feature = "runtime-benchmarks")]
 
 use super::*;
 
 use crate::Pallet as Identity;
-use frame_benchmarking::{account, benchmarks, whitelisted_caller};
+use frame_benchmarking::{account, benchmarks, impl_benchmark_test_suite, whitelisted_caller};
 use frame_support::{ensure, traits::Get};
 use frame_system::RawOrigin;
-use sp_runtime::traits::Bounded;
 
 const SEED: u32 = 0;
 
@@ -33,383 +32,121 @@ fn assert_last_event<T: Config>(generic_event: <T as Config>::Event) {
 	frame_system::Pallet::<T>::assert_last_event(generic_event.into());
 }
 
-// Adds `r` registrars to the Identity Pallet. These registrars will have set fees and fields.
-fn add_registrars<T: Config>(r: u32) -> Result<(), &'static str> {
-	for i in 0..r {
-		let registrar: T::AccountId = account("registrar", i, SEED);
-		let _ = T::Currency::make_free_balance_be(&registrar, BalanceOf::<T>::max_value());
-		Identity::<T>::add_registrar(RawOrigin::Root.into(), registrar.clone())?;
-		Identity::<T>::set_fee(
-			RawOrigin::Signed(registrar.clone()).into(),
-			i.into(),
-			10u32.into(),
-		)?;
-		let fields =
-			IdentityFields(
-				IdentityField::Display |
-					IdentityField::Legal | IdentityField::Web |
-					IdentityField::Riot | IdentityField::Email |
-					IdentityField::PgpFingerprint |
-					IdentityField::Image | IdentityField::Twitter,
-			);
-		Identity::<T>::set_fields(RawOrigin::Signed(registrar.clone()).into(), i.into(), fields)?;
-	}
+// Registers the identity of `email`, owned by `owner`, with the password `password`.
+fn register<T: Config>(owner: &T::AccountId, email: &[u8]) -> Result<EmailHash, &'static str> {
+	let email_hash = Identity::<T>::email_hash(email);
+	Identity::<T>::request_registration_sel11(
+		RawOrigin::Signed(owner.clone()).into(),
+		email_hash,
+		b"password".to_vec(),
+	)?;
+	Ok(email_hash)
+}
 
-	assert_eq!(Registrars::<T>::get().len(), r as usize);
+// Links `who` to the identity of `email_hash`, which `owner` may set the referal of.
+fn link<T: Config>(owner: &T::AccountId, who: &T::AccountId, email_hash: EmailHash) -> Result<(), &'static str> {
+	Identity::<T>::set_referal_sel12(RawOrigin::Signed(owner.clone()).into(), email_hash, b"link".to_vec())?;
+	Identity::<T>::create_web3link_sel15(
+		RawOrigin::Signed(who.clone()).into(),
+		email_hash,
+		who.clone(),
+		b"link".to_vec(),
+	)?;
 	Ok(())
 }
 
-// Create `s` sub-accounts for the identity of `who` and return them.
-// Each will have 32 bytes of raw data added to it.
-fn create_sub_accounts<T: Config>(
-	who: &T::AccountId,
-	s: u32,
-) -> Result<Vec<(T::AccountId, Data)>, &'static str> {
-	let mut subs = Vec::new();
-	let who_origin = RawOrigin::Signed(who.clone());
-	let data = Data::Raw(vec![0; 32].try_into().unwrap());
-
-	for i in 0..s {
-		let sub_account = account("sub", i, SEED);
-		subs.push((sub_account, data.clone()));
-	}
-
-	// Set identity so `set_subs` does not fail.
-	let _ = T::Currency::make_free_balance_be(&who, BalanceOf::<T>::max_value());
-	let info = create_identity_info::<T>(1);
-	Identity::<T>::set_identity(who_origin.clone().into(), Box::new(info))?;
-
-	Ok(subs)
+// Registers an identity for `referred` from an account linked to another identity, so that
+// setting its referal records a pending referrer.
+fn referred_identity<T: Config>() -> Result<(T::AccountId, EmailHash), &'static str> {
+	let referrer: T::AccountId = account("referrer", 0, SEED);
+	let referrer_hash = register::<T>(&referrer, b"referrer@example.edu")?;
+	link::<T>(&referrer, &referrer, referrer_hash)?;
+	let email_hash = register::<T>(&referrer, b"referred@example.edu")?;
+	Ok((referrer, email_hash))
 }
 
-// Adds `s` sub-accounts to the identity of `who`. Each will have 32 bytes of raw data added to it.
-// This additionally returns the vector of sub-accounts so it can be modified if needed.
-fn add_sub_accounts<T: Config>(
-	who: &T::AccountId,
-	s: u32,
-) -> Result<Vec<(T::AccountId, Data)>, &'static str> {
-	let who_origin = RawOrigin::Signed(who.clone());
-	let subs = create_sub_accounts::<T>(who, s)?;
-
-	Identity::<T>::set_subs(who_origin.into(), subs.clone())?;
-
-	Ok(subs)
-}
-
-// This creates an `IdentityInfo` object with `num_fields` extra fields.
-// All data is pre-populated with some arbitrary bytes.
-fn create_identity_info<T: Config>(num_fields: u32) -> IdentityInfo<T::MaxAdditionalFields> {
-	let data = Data::Raw(vec![0; 32].try_into().unwrap());
-
-	let info = IdentityInfo {
-		additional: vec![(data.clone(), data.clone()); num_fields as usize].try_into().unwrap(),
-		display: data.clone(),
-		legal: data.clone(),
-		web: data.clone(),
-		riot: data.clone(),
-		email: data.clone(),
-		pgp_fingerprint: Some([0; 20]),
-		image: data.clone(),
-		twitter: data.clone(),
-	};
-
-	return info
+fn enrollments<T: Config>(n: u32) -> Vec<EnrollmentOf<T>> {
+	let additional: BoundedVec<(Data, Data), T::MaxAdditionalFields> = (0..T::MaxAdditionalFields::get())
+		.map(|i| {
+			let key = Data::Raw(i.encode().try_into().unwrap());
+			(key, Data::Raw(vec![0u8; 32].try_into().unwrap()))
+		})
+		.collect::<Vec<_>>()
+		.try_into()
+		.unwrap();
+	(0..n)
+		.map(|i| (blake2_256(&i.encode()), sha2_256(&i.encode()), additional.clone()))
+		.collect()
 }
 
 benchmarks! {
-	add_registrar {
-		let r in 1 .. T::MaxRegistrars::get() - 1 => add_registrars::<T>(r)?;
-		ensure!(Registrars::<T>::get().len() as u32 == r, "Registrars not set up correctly.");
-	}: _(RawOrigin::Root, account("registrar", r + 1, SEED))
-	verify {
-		ensure!(Registrars::<T>::get().len() as u32 == r + 1, "Registrars not added.");
-	}
-
-	set_identity {
-		let r in 1 .. T::MaxRegistrars::get() => add_registrars::<T>(r)?;
-		let x in 1 .. T::MaxAdditionalFields::get();
-		let caller = {
-			// The target user
-			let caller: T::AccountId = whitelisted_caller();
-			let caller_lookup: <T::Lookup as StaticLookup>::Source = T::Lookup::unlookup(caller.clone());
-			let caller_origin: <T as frame_system::Config>::Origin = RawOrigin::Signed(caller.clone()).into();
-			let _ = T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value());
-
-			// Add an initial identity
-			let initial_info = create_identity_info::<T>(1);
-			Identity::<T>::set_identity(caller_origin.clone(), Box::new(initial_info))?;
-
-			// User requests judgement from all the registrars, and they approve
-			for i in 0..r {
-				Identity::<T>::request_judgement(caller_origin.clone(), i, 10u32.into())?;
-				Identity::<T>::provide_judgement(
-					RawOrigin::Signed(account("registrar", i, SEED)).into(),
-					i,
-					caller_lookup.clone(),
-					Judgement::Reasonable
-				)?;
-			}
-			caller
-		};
-	}: _(RawOrigin::Signed(caller.clone()), Box::new(create_identity_info::<T>(x)))
-	verify {
-		assert_last_event::<T>(Event::<T>::IdentitySet { who: caller }.into());
-	}
-
-	// We need to split `set_subs` into two benchmarks to accurately isolate the potential
-	// writes caused by new or old sub accounts. The actual weight should simply be
-	// the sum of these two weights.
-	set_subs_new {
+	request_registration_sel11 {
 		let caller: T::AccountId = whitelisted_caller();
-		// Create a new subs vec with s sub accounts
-		let s in 1 .. T::MaxSubAccounts::get() => ();
-		let subs = create_sub_accounts::<T>(&caller, s)?;
-		ensure!(SubsOf::<T>::get(&caller).1.len() == 0, "Caller already has subs");
-	}: set_subs(RawOrigin::Signed(caller.clone()), subs)
+		let email_hash = Identity::<T>::email_hash(b"student@example.edu");
+	}: _(RawOrigin::Signed(caller.clone()), email_hash, b"password".to_vec())
 	verify {
-		ensure!(SubsOf::<T>::get(&caller).1.len() as u32 == s, "Subs not added");
+		assert_last_event::<T>(Event::<T>::UserRegistered { email_hash, owner: caller }.into());
 	}
 
-	set_subs_old {
+	login_access_sel12 {
 		let caller: T::AccountId = whitelisted_caller();
-		// Give them p many previous sub accounts.
-		let p in 1 .. T::MaxSubAccounts::get() => {
-			let _ = add_sub_accounts::<T>(&caller, p)?;
-		};
-		// Remove all subs.
-		let subs = create_sub_accounts::<T>(&caller, 0)?;
-		ensure!(
-			SubsOf::<T>::get(&caller).1.len() as u32 == p,
-			"Caller does have subs",
-		);
-	}: set_subs(RawOrigin::Signed(caller.clone()), subs)
+		let email_hash = register::<T>(&caller, b"student@example.edu")?;
+	}: _(RawOrigin::Signed(caller.clone()), email_hash, b"password".to_vec())
 	verify {
-		ensure!(SubsOf::<T>::get(&caller).1.len() == 0, "Subs not removed");
+		assert_last_event::<T>(Event::<T>::UserLoginsuccess { email_hash, who: caller }.into());
 	}
 
-	clear_identity {
+	change_password_sel13 {
 		let caller: T::AccountId = whitelisted_caller();
-		let caller_origin = <T as frame_system::Config>::Origin::from(RawOrigin::Signed(caller.clone()));
-		let caller_lookup = <T::Lookup as StaticLookup>::unlookup(caller.clone());
-		let _ = T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value());
-
-		let r in 1 .. T::MaxRegistrars::get() => add_registrars::<T>(r)?;
-		let s in 1 .. T::MaxSubAccounts::get() => {
-			// Give them s many sub accounts
-			let caller: T::AccountId = whitelisted_caller();
-			let _ = add_sub_accounts::<T>(&caller, s)?;
-		};
-		let x in 1 .. T::MaxAdditionalFields::get() => {
-			// Create their main identity with x additional fields
-			let info = create_identity_info::<T>(x);
-			let caller: T::AccountId = whitelisted_caller();
-			let caller_origin = <T as frame_system::Config>::Origin::from(RawOrigin::Signed(caller));
-			Identity::<T>::set_identity(caller_origin, Box::new(info))?;
-		};
-
-		// User requests judgement from all the registrars, and they approve
-		for i in 0..r {
-			Identity::<T>::request_judgement(caller_origin.clone(), i, 10u32.into())?;
-			Identity::<T>::provide_judgement(
-				RawOrigin::Signed(account("registrar", i, SEED)).into(),
-				i,
-				caller_lookup.clone(),
-				Judgement::Reasonable
-			)?;
-		}
-		ensure!(IdentityOf::<T>::contains_key(&caller), "Identity does not exist.");
-	}: _(RawOrigin::Signed(caller.clone()))
-	verify {
-		ensure!(!IdentityOf::<T>::contains_key(&caller), "Identity not cleared.");
-	}
-
-	request_judgement {
-		let caller: T::AccountId = whitelisted_caller();
-		let _ = T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value());
-
-		let r in 1 .. T::MaxRegistrars::get() => add_registrars::<T>(r)?;
-		let x in 1 .. T::MaxAdditionalFields::get() => {
-			// Create their main identity with x additional fields
-			let info = create_identity_info::<T>(x);
-			let caller: T::AccountId = whitelisted_caller();
-			let caller_origin = <T as frame_system::Config>::Origin::from(RawOrigin::Signed(caller));
-			Identity::<T>::set_identity(caller_origin, Box::new(info))?;
-		};
-	}: _(RawOrigin::Signed(caller.clone()), r - 1, 10u32.into())
+		let email_hash = register::<T>(&caller, b"student@example.edu")?;
+	}: _(RawOrigin::Signed(caller.clone()), email_hash, b"new password".to_vec())
 	verify {
-		assert_last_event::<T>(Event::<T>::JudgementRequested { who: caller, registrar_index: r-1 }.into());
+		assert_last_event::<T>(Event::<T>::PasswordChanged { email_hash, who: caller }.into());
 	}
 
-	cancel_request {
-		let caller: T::AccountId = whitelisted_caller();
-		let caller_origin = <T as frame_system::Config>::Origin::from(RawOrigin::Signed(caller.clone()));
-		let _ = T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value());
-
-		let r in 1 .. T::MaxRegistrars::get() => add_registrars::<T>(r)?;
-		let x in 1 .. T::MaxAdditionalFields::get() => {
-			// Create their main identity with x additional fields
-			let info = create_identity_info::<T>(x);
-			let caller: T::AccountId = whitelisted_caller();
-			let caller_origin = <T as frame_system::Config>::Origin::from(RawOrigin::Signed(caller));
-			Identity::<T>::set_identity(caller_origin, Box::new(info))?;
-		};
-
-		Identity::<T>::request_judgement(caller_origin, r - 1, 10u32.into())?;
-	}: _(RawOrigin::Signed(caller.clone()), r - 1)
+	// The worst case: the sender is linked to another identity, so a referrer is recorded.
+	set_referal_sel12 {
+		let (referrer, email_hash) = referred_identity::<T>()?;
+	}: _(RawOrigin::Signed(referrer.clone()), email_hash, b"referal".to_vec())
 	verify {
-		assert_last_event::<T>(Event::<T>::JudgementUnrequested { who: caller, registrar_index: r-1 }.into());
+		ensure!(PendingReferrer::<T>::contains_key(&email_hash), "Referrer not recorded");
 	}
 
-	set_fee {
+	// The worst case: linking completes a pending referral.
+	create_web3link_sel15 {
 		let caller: T::AccountId = whitelisted_caller();
-
-		let r in 1 .. T::MaxRegistrars::get() - 1 => add_registrars::<T>(r)?;
-
-		Identity::<T>::add_registrar(RawOrigin::Root.into(), caller.clone())?;
-		let registrars = Registrars::<T>::get();
-		ensure!(registrars[r as usize].as_ref().unwrap().fee == 0u32.into(), "Fee already set.");
-	}: _(RawOrigin::Signed(caller), r, 100u32.into())
+		let (referrer, email_hash) = referred_identity::<T>()?;
+		Identity::<T>::set_referal_sel12(RawOrigin::Signed(referrer).into(), email_hash, b"referal".to_vec())?;
+	}: _(RawOrigin::Signed(caller.clone()), email_hash, caller.clone(), b"referal".to_vec())
 	verify {
-		let registrars = Registrars::<T>::get();
-		ensure!(registrars[r as usize].as_ref().unwrap().fee == 100u32.into(), "Fee not changed.");
+		ensure!(Identity::<T>::is_linked(&email_hash, &caller), "Account not linked");
+		ensure!(ReferredBy::<T>::contains_key(&email_hash), "Referral not recorded");
 	}
 
-	set_account_id {
-		let caller: T::AccountId = whitelisted_caller();
-		let _ = T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value());
-
-		let r in 1 .. T::MaxRegistrars::get() - 1 => add_registrars::<T>(r)?;
-
-		Identity::<T>::add_registrar(RawOrigin::Root.into(), caller.clone())?;
-		let registrars = Registrars::<T>::get();
-		ensure!(registrars[r as usize].as_ref().unwrap().account == caller.clone(), "id not set.");
-	}: _(RawOrigin::Signed(caller), r, account("new", 0, SEED))
-	verify {
-		let registrars = Registrars::<T>::get();
-		ensure!(registrars[r as usize].as_ref().unwrap().account == account("new", 0, SEED), "id not changed.");
-	}
-
-	set_fields {
-		let caller: T::AccountId = whitelisted_caller();
-		let _ = T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value());
-
-		let r in 1 .. T::MaxRegistrars::get() - 1 => add_registrars::<T>(r)?;
-
-		Identity::<T>::add_registrar(RawOrigin::Root.into(), caller.clone())?;
-		let fields = IdentityFields(
-			IdentityField::Display | IdentityField::Legal | IdentityField::Web | IdentityField::Riot
-			| IdentityField::Email | IdentityField::PgpFingerprint | IdentityField::Image | IdentityField::Twitter
-		);
-		let registrars = Registrars::<T>::get();
-		ensure!(registrars[r as usize].as_ref().unwrap().fields == Default::default(), "fields already set.");
-	}: _(RawOrigin::Signed(caller), r, fields)
-	verify {
-		let registrars = Registrars::<T>::get();
-		ensure!(registrars[r as usize].as_ref().unwrap().fields != Default::default(), "fields not set.");
-	}
-
-	provide_judgement {
-		// The user
-		let user: T::AccountId = account("user", r, SEED);
-		let user_origin = <T as frame_system::Config>::Origin::from(RawOrigin::Signed(user.clone()));
-		let user_lookup = <T::Lookup as StaticLookup>::unlookup(user.clone());
-		let _ = T::Currency::make_free_balance_be(&user, BalanceOf::<T>::max_value());
-
-		let caller: T::AccountId = whitelisted_caller();
-		let _ = T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value());
-
-		let r in 1 .. T::MaxRegistrars::get() - 1 => add_registrars::<T>(r)?;
-		let x in 1 .. T::MaxAdditionalFields::get() => {
-			let info = create_identity_info::<T>(x);
-			Identity::<T>::set_identity(user_origin.clone(), Box::new(info))?;
-		};
-
-		Identity::<T>::add_registrar(RawOrigin::Root.into(), caller.clone())?;
-		Identity::<T>::request_judgement(user_origin.clone(), r, 10u32.into())?;
-	}: _(RawOrigin::Signed(caller), r, user_lookup, Judgement::Reasonable)
-	verify {
-		assert_last_event::<T>(Event::<T>::JudgementGiven { target: user, registrar_index: r }.into())
-	}
-
-	kill_identity {
-		let r in 1 .. T::MaxRegistrars::get() => add_registrars::<T>(r)?;
-		let s in 1 .. T::MaxSubAccounts::get();
-		let x in 1 .. T::MaxAdditionalFields::get();
-
-		let target: T::AccountId = account("target", 0, SEED);
-		let target_origin: <T as frame_system::Config>::Origin = RawOrigin::Signed(target.clone()).into();
-		let target_lookup: <T::Lookup as StaticLookup>::Source = T::Lookup::unlookup(target.clone());
-		let _ = T::Currency::make_free_balance_be(&target, BalanceOf::<T>::max_value());
-
-		let info = create_identity_info::<T>(x);
-		Identity::<T>::set_identity(target_origin.clone(), Box::new(info))?;
-		let _ = add_sub_accounts::<T>(&target, s)?;
-
-		// User requests judgement from all the registrars, and they approve
-		for i in 0..r {
-			Identity::<T>::request_judgement(target_origin.clone(), i, 10u32.into())?;
-			Identity::<T>::provide_judgement(
-				RawOrigin::Signed(account("registrar", i, SEED)).into(),
-				i,
-				target_lookup.clone(),
-				Judgement::Reasonable
-			)?;
+	// The worst case: the sender is the last of the most accounts linked to the identity.
+	login_web3_sel16 {
+		let owner: T::AccountId = account("owner", 0, SEED);
+		let email_hash = register::<T>(&owner, b"student@example.edu")?;
+		for i in 1 .. T::MaxLinkedAccounts::get() {
+			link::<T>(&owner, &account("linked", i, SEED), email_hash)?;
 		}
-		ensure!(IdentityOf::<T>::contains_key(&target), "Identity not set");
-	}: _(RawOrigin::Root, target_lookup)
-	verify {
-		ensure!(!IdentityOf::<T>::contains_key(&target), "Identity not removed");
-	}
-
-	add_sub {
-		let s in 1 .. T::MaxSubAccounts::get() - 1;
-
-		let caller: T::AccountId = whitelisted_caller();
-		let _ = add_sub_accounts::<T>(&caller, s)?;
-		let sub = account("new_sub", 0, SEED);
-		let data = Data::Raw(vec![0; 32].try_into().unwrap());
-		ensure!(SubsOf::<T>::get(&caller).1.len() as u32 == s, "Subs not set.");
-	}: _(RawOrigin::Signed(caller.clone()), T::Lookup::unlookup(sub), data)
-	verify {
-		ensure!(SubsOf::<T>::get(&caller).1.len() as u32 == s + 1, "Subs not added.");
-	}
-
-	rename_sub {
-		let s in 1 .. T::MaxSubAccounts::get();
-
 		let caller: T::AccountId = whitelisted_caller();
-		let (sub, _) = add_sub_accounts::<T>(&caller, s)?.remove(0);
-		let data = Data::Raw(vec![1; 32].try_into().unwrap());
-		ensure!(SuperOf::<T>::get(&sub).unwrap().1 != data, "data already set");
-	}: _(RawOrigin::Signed(caller), T::Lookup::unlookup(sub.clone()), data.clone())
+		link::<T>(&owner, &caller, email_hash)?;
+	}: _(RawOrigin::Signed(caller.clone()), email_hash)
 	verify {
-		ensure!(SuperOf::<T>::get(&sub).unwrap().1 == data, "data not set");
+		assert_last_event::<T>(Event::<T>::UserWeb3loginsuccess { email_hash, account: caller }.into());
 	}
 
-	remove_sub {
-		let s in 1 .. T::MaxSubAccounts::get();
-
-		let caller: T::AccountId = whitelisted_caller();
-		let (sub, _) = add_sub_accounts::<T>(&caller, s)?.remove(0);
-		ensure!(SuperOf::<T>::contains_key(&sub), "Sub doesn't exists");
-	}: _(RawOrigin::Signed(caller), T::Lookup::unlookup(sub.clone()))
+	enroll_batch {
+		let n in 1 .. T::MaxEnrollmentBatch::get();
+		let institution: T::AccountId = whitelisted_caller();
+		<Registrars<T>>::put(BoundedVec::try_from(vec![institution.clone()]).unwrap());
+		let entries = enrollments::<T>(n);
+		let last = entries[n as usize - 1].0;
+	}: enroll_batch_sel33(RawOrigin::Signed(institution), entries)
 	verify {
-		ensure!(!SuperOf::<T>::contains_key(&sub), "Sub not removed");
+		assert!(<Identity1Of<T>>::contains_key(last));
 	}
-
-	quit_sub {
-		let s in 1 .. T::MaxSubAccounts::get() - 1;
-
-		let caller: T::AccountId = whitelisted_caller();
-		let sup = account("super", 0, SEED);
-		let _ = add_sub_accounts::<T>(&sup, s)?;
-		let sup_origin = RawOrigin::Signed(sup).into();
-		Identity::<T>::add_sub(sup_origin, T::Lookup::unlookup(caller.clone()), Data::Raw(vec![0; 32].try_into().unwrap()))?;
-		ensure!(SuperOf::<T>::contains_key(&caller), "Sub doesn't exists");
-	}: _(RawOrigin::Signed(caller.clone()))
-	verify {
-		ensure!(!SuperOf::<T>::contains_key(&caller), "Sub not removed");
-	}
-
-	impl_benchmark_test_suite!(Identity, crate::tests::new_test_ext(), crate::tests::Test);
 }
+
+impl_benchmark_test_suite!(Identity, crate::tests::new_test_ext(), crate::tests::Test);
@@ -0,0 +1,110 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2019-2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Bookkeeping for failed identity calls.
+//!
+//! A call that fails, e.g. `login_access_sel12` with a wrong password, cannot keep anything it
+//! writes or deposits. [`RecordFailures`] goes in a runtime's `SignedExtra` and, once such a
+//! call has failed, counts failed password logins towards a lockout (see
+//! [`Pallet::note_failed_login`]) and deposits the matching failure event. Without it failed
+//! logins are never counted and nobody is locked out.
+
+use super::*;
+use frame_support::traits::IsSubType;
+use sp_runtime::{
+	traits::{DispatchInfoOf, PostDispatchInfoOf, SignedExtension},
+	transaction_validity::TransactionValidityError,
+};
+
+/// Record failed registrations, links and logins after dispatch.
+#[derive(Encode, Decode, Clone, Eq, PartialEq, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+pub struct RecordFailures<T: Config>(sp_std::marker::PhantomData<T>);
+
+impl<T: Config> RecordFailures<T> {
+	pub fn new() -> Self {
+		Self(Default::default())
+	}
+}
+
+impl<T: Config> Default for RecordFailures<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T: Config> sp_std::fmt::Debug for RecordFailures<T> {
+	fn fmt(&self, f: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+		write!(f, "RecordFailures")
+	}
+}
+
+/// The identity call a transaction makes, as far as [`RecordFailures`] cares.
+#[derive(Clone, Copy, Eq, PartialEq, RuntimeDebug)]
+pub enum Watched {
+	Registration,
+	Web3Link,
+	Login,
+	Web3Login,
+}
+
+impl<T> SignedExtension for RecordFailures<T>
+where
+	T: Config + Send + Sync,
+	<T as frame_system::Config>::Call: IsSubType<Call<T>>,
+{
+	const IDENTIFIER: &'static str = "RecordFailures";
+	type AccountId = T::AccountId;
+	type Call = <T as frame_system::Config>::Call;
+	type AdditionalSigned = ();
+	/// The caller, the identity and which call it was.
+	type Pre = Option<(T::AccountId, EmailHash, Watched)>;
+
+	fn additional_signed(&self) -> sp_std::result::Result<(), TransactionValidityError> {
+		Ok(())
+	}
+
+	fn pre_dispatch(
+		self,
+		who: &Self::AccountId,
+		call: &Self::Call,
+		_info: &DispatchInfoOf<Self::Call>,
+		_len: usize,
+	) -> Result<Self::Pre, TransactionValidityError> {
+		let watched = match call.is_sub_type() {
+			Some(Call::request_registration_sel11 { email_hash, .. }) => (*email_hash, Watched::Registration),
+			Some(Call::create_web3link_sel15 { email_hash, .. }) => (*email_hash, Watched::Web3Link),
+			Some(Call::login_access_sel12 { email_hash, .. }) => (*email_hash, Watched::Login),
+			Some(Call::login_web3_sel16 { email_hash }) => (*email_hash, Watched::Web3Login),
+			_ => return Ok(None),
+		};
+		Ok(Some((who.clone(), watched.0, watched.1)))
+	}
+
+	fn post_dispatch(
+		pre: Self::Pre,
+		_info: &DispatchInfoOf<Self::Call>,
+		_post_info: &PostDispatchInfoOf<Self::Call>,
+		_len: usize,
+		result: &DispatchResult,
+	) -> Result<(), TransactionValidityError> {
+		if let (Some((who, email_hash, watched)), Err(error)) = (pre, result) {
+			Pallet::<T>::note_failure(watched, email_hash, who, *error);
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,137 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2019-2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fee-less transactions for students.
+//!
+//! [`ChargeOrWaiveForStudents`] replaces `pallet_transaction_payment::ChargeTransactionPayment`
+//! in a runtime's `SignedExtra`. Calls accepted by the filter `F` are free when the signer is
+//! linked to an identity that was enrolled or given a role by an institution, and has not used up
+//! its `FeelessTxPerEra` quota; everything else is charged as usual.
+//!
+//! A fee-less transaction claims the next slot of its signer's quota already when it enters the
+//! transaction pool, through a `provides` tag. Transactions claiming the same slot replace each
+//! other, so a signer keeps at most one free transaction in the pool at a time instead of
+//! filling it with transactions that cost nothing.
+
+use super::*;
+use frame_support::{
+	traits::Contains,
+	weights::{DispatchInfo, PostDispatchInfo},
+};
+use pallet_transaction_payment::{ChargeTransactionPayment, OnChargeTransaction};
+use sp_runtime::{
+	traits::{DispatchInfoOf, Dispatchable, One, PostDispatchInfoOf, SignedExtension, UniqueSaturatedInto},
+	transaction_validity::{TransactionValidity, TransactionValidityError, ValidTransaction},
+	FixedPointOperand,
+};
+
+type PaymentBalanceOf<T> = <<T as pallet_transaction_payment::Config>::OnChargeTransaction as OnChargeTransaction<T>>::Balance;
+
+/// Charge the transaction fee, unless the call is accepted by `F` and the signer is a student
+/// with fee-less transactions left this era.
+#[derive(Encode, Decode, Clone, Eq, PartialEq, TypeInfo)]
+#[scale_info(skip_type_params(T, F))]
+pub struct ChargeOrWaiveForStudents<T: Config + pallet_transaction_payment::Config, F>(
+	ChargeTransactionPayment<T>,
+	sp_std::marker::PhantomData<F>,
+);
+
+impl<T: Config + pallet_transaction_payment::Config, F> ChargeOrWaiveForStudents<T, F> {
+	/// Utility constructor, `tip` is paid whenever the fee is charged.
+	pub fn from(tip: PaymentBalanceOf<T>) -> Self {
+		Self(ChargeTransactionPayment::from(tip), Default::default())
+	}
+}
+
+impl<T: Config + pallet_transaction_payment::Config, F> sp_std::fmt::Debug for ChargeOrWaiveForStudents<T, F> {
+	#[cfg(feature = "std")]
+	fn fmt(&self, f: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+		write!(f, "ChargeOrWaiveForStudents<{:?}>", self.0)
+	}
+	#[cfg(not(feature = "std"))]
+	fn fmt(&self, _: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+		Ok(())
+	}
+}
+
+impl<T, F> SignedExtension for ChargeOrWaiveForStudents<T, F>
+where
+	T: Config + pallet_transaction_payment::Config + Send + Sync,
+	PaymentBalanceOf<T>: Send + Sync + From<u64> + FixedPointOperand,
+	<T as frame_system::Config>::Call: Dispatchable<Info = DispatchInfo, PostInfo = PostDispatchInfo>,
+	F: Contains<<T as frame_system::Config>::Call> + Send + Sync + 'static,
+{
+	const IDENTIFIER: &'static str = "ChargeOrWaiveForStudents";
+	type AccountId = T::AccountId;
+	type Call = <T as frame_system::Config>::Call;
+	type AdditionalSigned = ();
+	/// `None` when the fee was waived.
+	type Pre = Option<<ChargeTransactionPayment<T> as SignedExtension>::Pre>;
+
+	fn additional_signed(&self) -> sp_std::result::Result<(), TransactionValidityError> {
+		Ok(())
+	}
+
+	fn validate(
+		&self,
+		who: &Self::AccountId,
+		call: &Self::Call,
+		info: &DispatchInfoOf<Self::Call>,
+		len: usize,
+	) -> TransactionValidity {
+		if F::contains(call) {
+			if let Some((era, used)) = Pallet::<T>::next_feeless_slot(who) {
+				// The quota is counted again when the era ends, so there is no point keeping the
+				// transaction around for longer.
+				let era_end = era.saturating_add(One::one()).saturating_mul(T::FeelessEraLength::get());
+				let now = <frame_system::Pallet<T>>::block_number();
+				return Ok(ValidTransaction {
+					provides: vec![(Self::IDENTIFIER, who, era, used).encode()],
+					longevity: era_end.saturating_sub(now).unique_saturated_into(),
+					..Default::default()
+				})
+			}
+		}
+		self.0.validate(who, call, info, len)
+	}
+
+	fn pre_dispatch(
+		self,
+		who: &Self::AccountId,
+		call: &Self::Call,
+		info: &DispatchInfoOf<Self::Call>,
+		len: usize,
+	) -> Result<Self::Pre, TransactionValidityError> {
+		if F::contains(call) && Pallet::<T>::use_feeless_tx(who, true) {
+			return Ok(None)
+		}
+		self.0.pre_dispatch(who, call, info, len).map(Some)
+	}
+
+	fn post_dispatch(
+		pre: Self::Pre,
+		info: &DispatchInfoOf<Self::Call>,
+		post_info: &PostDispatchInfoOf<Self::Call>,
+		len: usize,
+		result: &DispatchResult,
+	) -> Result<(), TransactionValidityError> {
+		match pre {
+			Some(pre) => ChargeTransactionPayment::<T>::post_dispatch(pre, info, post_info, len, result),
+			None => Ok(()),
+		}
+	}
+}
@@ -51,15 +51,22 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 mod benchmarking;
+pub mod fees;
+pub mod failures;
+pub mod migrations;
 #[cfg(test)]
 mod tests;
 mod types;
+pub mod weights;
 
 use codec::{Decode, Encode, MaxEncodedLen};
 
 use sp_io::hashing::{sha2_256, blake2_128, blake2_256, twox_128, twox_256, twox_64};
 
-use frame_support::traits::{BalanceStatus, Currency, OnUnbalanced, ReservableCurrency};
+use frame_support::traits::{
+	BalanceStatus, Currency, EnsureOrigin, ExistenceRequirement, Get, OnUnbalanced, ReservableCurrency,
+};
+use frame_support::PalletId;
 use sp_runtime::traits::{AppendZerosInput, Saturating,  AtLeast32BitUnsigned,
     AccountIdConversion,
         AtLeast32Bit,
@@ -78,9 +85,11 @@ use sp_std::{
 
 pub use pallet::*;
 pub use types::{
-	Data, IdentityField, IdentityFields,  IdentityInfoSel,  RegistrarIndex, 
-	 RegistrationSel
+	ActiveRecovery, Data, DidDetails, DidDocument, DidKeyType, DidVerificationMethod,
+	EncryptedPayload, IdentityField, IdentityFields,  IdentityInfoSel, IdentitySummary,
+	ProfileField, RecoveryConfig, RegistrarIndex, RegistrationSel
 };
+pub use pallet_studentid_primitives::{EmailHash, IdentityProvider, Role};
 
 type BalanceOf<T> =
 	<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
@@ -90,6 +99,15 @@ type NegativeImbalanceOf<T> = <<T as Config>::Currency as Currency<
 
 pub type UseridentityIndex = u32;
 
+/// Domain separator mixed into every email hash so the keys cannot be matched against plain
+/// `blake2_256(email)` tables built for other systems.
+pub const EMAIL_HASH_DOMAIN: &[u8] = b"studentid/email";
+
+pub use weights::WeightInfo;
+
+/// Name of a service identities can be granted access to, e.g. `moodle`.
+pub type ServiceName = BoundedVec<u8, frame_support::traits::ConstU32<32>>;
+
 /// Token info
 #[derive(Encode, Decode, Clone, Eq, PartialEq, MaxEncodedLen, RuntimeDebug, TypeInfo)]
 pub struct TokenInfo<AccountId, Data, TokenMetadataOf> {
@@ -104,7 +122,7 @@ pub struct TokenInfo<AccountId, Data, TokenMetadataOf> {
 #[frame_support::pallet]
 pub mod pallet {
 	use super::*;
-	use frame_support::pallet_prelude::*;
+	use frame_support::{pallet_prelude::*, traits::StorageVersion};
 	use frame_system::pallet_prelude::*;
 
 	#[pallet::config]
@@ -151,12 +169,85 @@ pub mod pallet {
 		type MaxTokenid: Get<u32>;
 
 		type MaxUseridentities: Get<u32>;
+
+		/// Maximum number of web3 accounts that may be linked to one identity at a time.
+		#[pallet::constant]
+		type MaxLinkedAccounts: Get<u32>;
+
+		/// Maximum number of guardians in an identity's recovery configuration.
+		#[pallet::constant]
+		type MaxGuardians: Get<u32>;
+
+		/// How long a recovery must wait after being initiated before it can be claimed. The
+		/// identity's linked accounts can cancel it during this time.
+		#[pallet::constant]
+		type RecoveryDelay: Get<Self::BlockNumber>;
+
+		/// Reserved from the new key when it initiates a recovery. Returned once the recovery is
+		/// claimed, or paid to whoever cancels it on the identity's behalf.
+		#[pallet::constant]
+		type RecoveryDeposit: Get<BalanceOf<Self>>;
+
+		/// Maximum number of identities one identity may refer.
+		#[pallet::constant]
+		type MaxReferralsPerReferrer: Get<u32>;
+
+		/// Paid to the referring account once an account is linked to the referred identity.
+		/// Zero disables referral rewards.
+		#[pallet::constant]
+		type ReferralReward: Get<BalanceOf<Self>>;
+
+		/// The pallet's id. Referral rewards are paid from its account, see
+		/// [`Pallet::referral_pot`], which a campaign has to fund up front.
+		#[pallet::constant]
+		type PalletId: Get<PalletId>;
+
+		/// Maximum number of verification methods in a DID document.
+		#[pallet::constant]
+		type MaxDidKeys: Get<u32>;
+
 		/// What to do with slashed funds.
 		type Slashed: OnUnbalanced<NegativeImbalanceOf<Self>>;
 
 		/// The origin which may forcibly set or remove a name. Root can always do this.
 		type ForceOrigin: EnsureOrigin<Self::Origin>;
 
+		/// The origin which may enroll students in bulk, yielding the institution account that
+		/// will own the new identities. Usually [`EnsureRegistrar`].
+		type InstitutionOrigin: EnsureOrigin<Self::Origin, Success = Self::AccountId>;
+
+		/// Maximum number of students in one `enroll_batch_sel33` call.
+		#[pallet::constant]
+		type MaxEnrollmentBatch: Get<u32>;
+
+		/// Maximum size of an encrypted profile field.
+		#[pallet::constant]
+		type MaxCiphertextLen: Get<u32>;
+
+		/// Maximum number of viewers one encrypted profile field can be disclosed to.
+		#[pallet::constant]
+		type MaxFieldGrants: Get<u32>;
+
+		/// Number of fee-less transactions a student may send per era, see [`fees`].
+		#[pallet::constant]
+		type FeelessTxPerEra: Get<u32>;
+
+		/// Length of a fee-less transaction era, in blocks.
+		#[pallet::constant]
+		type FeelessEraLength: Get<Self::BlockNumber>;
+
+		/// Failed logins an identity may have before it is locked out.
+		#[pallet::constant]
+		type MaxLoginAttempts: Get<u32>;
+
+		/// Length of the first login lockout, in blocks. Each further failure after a lockout
+		/// doubles it.
+		#[pallet::constant]
+		type LoginLockoutBase: Get<Self::BlockNumber>;
+
+		/// Weight information for extrinsics in this pallet.
+		type WeightInfo: WeightInfo;
+
 		/// The origin which may add or remove registrars. Root can always do this.
 		type RegistrarOrigin: EnsureOrigin<Self::Origin>;
 
@@ -176,12 +267,60 @@ pub mod pallet {
 	pub type TokenInfoOf<T> =
 		TokenInfo<<T as frame_system::Config>::AccountId,  Data, TokenMetadataOf<T>>;
 
+	pub type RecoveryConfigOf<T> =
+		RecoveryConfig<<T as frame_system::Config>::AccountId, <T as Config>::MaxGuardians>;
+	pub type ActiveRecoveryOf<T> = ActiveRecovery<
+		BalanceOf<T>,
+		<T as frame_system::Config>::AccountId,
+		<T as frame_system::Config>::BlockNumber,
+		<T as Config>::MaxGuardians,
+	>;
+
+	/// One student to enroll: email hash, SHA2-256 of the initial referal and additional fields.
+	pub type EnrollmentOf<T> =
+		(EmailHash, [u8; 32], BoundedVec<(Data, Data), <T as Config>::MaxAdditionalFields>);
+
+	pub type EncryptedPayloadOf<T> = EncryptedPayload<<T as Config>::MaxCiphertextLen>;
+
+	pub type DidDocumentOf<T> = DidDocument<
+		<T as frame_system::Config>::AccountId,
+		<T as frame_system::Config>::BlockNumber,
+		<T as Config>::MaxDidKeys,
+	>;
+
+	pub type RegistrationOf<T> = RegistrationSel<
+		BalanceOf<T>,
+		<T as frame_system::Config>::AccountId,
+		<T as Config>::MaxAdditionalFields,
+	>;
+
+
+	/// The current storage version, see [`crate::migrations`].
+	pub const STORAGE_VERSION: StorageVersion = StorageVersion::new(6);
 
 	#[pallet::pallet]
 	#[pallet::generate_store(pub(super) trait Store)]
     #[pallet::generate_storage_info]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_runtime_upgrade() -> Weight {
+			migrations::migrate::<T>()
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<(), &'static str> {
+			migrations::pre_upgrade::<T>()
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade() -> Result<(), &'static str> {
+			migrations::post_upgrade::<T>()
+		}
+	}
+
 
 
 	/// testing
@@ -192,11 +331,197 @@ pub mod pallet {
 	pub type Tokens<T: Config> =
 		StorageMap<_, Twox64Concat,  TokenId<T>, TokenInfoOf<T>>;
 
+	/// The email hash of the identity an account has been linked to.
 	#[pallet::storage]
 	#[pallet::getter(fn emailid)]
 	pub type EmailId<T: Config> =
-		StorageMap<_, Twox64Concat,  T::AccountId, Email<T>>;
+		StorageMap<_, Twox64Concat,  T::AccountId, EmailHash>;
+
+
+	/// The web3 accounts linked to an identity. Every account in here has a matching `EmailId`
+	/// entry pointing back at the identity.
+	#[pallet::storage]
+	#[pallet::getter(fn linked_accounts)]
+	pub type LinkedAccounts<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		EmailHash,
+		BoundedVec<T::AccountId, T::MaxLinkedAccounts>,
+		ValueQuery,
+	>;
+
+	/// The referrer identity and account behind the referal currently set on an identity.
+	/// Only present when the referal was set from a linked account.
+	#[pallet::storage]
+	pub type PendingReferrer<T: Config> =
+		StorageMap<_, Twox64Concat, EmailHash, (EmailHash, T::AccountId)>;
+
+	/// Referral edges: referrer identity => referred identity => block the link happened in.
+	#[pallet::storage]
+	#[pallet::getter(fn referrals)]
+	pub type Referrals<T: Config> = StorageDoubleMap<
+		_,
+		Twox64Concat,
+		EmailHash,
+		Twox64Concat,
+		EmailHash,
+		T::BlockNumber,
+	>;
+
+	/// Who referred an identity. Every identity is referred at most once.
+	#[pallet::storage]
+	#[pallet::getter(fn referred_by)]
+	pub type ReferredBy<T: Config> = StorageMap<_, Twox64Concat, EmailHash, EmailHash>;
+
+	/// Referral rewards not paid yet because the referral pot could not cover them: referred
+	/// identity => account the reward goes to. Paid the next time an account is linked to the
+	/// referred identity.
+	#[pallet::storage]
+	#[pallet::getter(fn unpaid_referral_reward)]
+	pub type UnpaidReferralRewards<T: Config> = StorageMap<_, Twox64Concat, EmailHash, T::AccountId>;
+
+	/// Number of identities each identity has referred.
+	#[pallet::storage]
+	#[pallet::getter(fn referral_count)]
+	pub type ReferralCount<T: Config> = StorageMap<_, Twox64Concat, EmailHash, u32, ValueQuery>;
+
+	/// The guardians that can recover an identity onto a new key.
+	#[pallet::storage]
+	#[pallet::getter(fn recovery_config)]
+	pub type RecoveryConfigs<T: Config> =
+		StorageMap<_, Twox64Concat, EmailHash, RecoveryConfigOf<T>>;
+
+	/// Recoveries that have been initiated and not yet claimed or cancelled, by identity and
+	/// new key.
+	#[pallet::storage]
+	#[pallet::getter(fn active_recovery)]
+	pub type ActiveRecoveries<T: Config> = StorageDoubleMap<
+		_,
+		Twox64Concat,
+		EmailHash,
+		Twox64Concat,
+		T::AccountId,
+		ActiveRecoveryOf<T>,
+	>;
+
+	/// Accounts trusted to vouch for identities on behalf of an institution.
+	#[pallet::storage]
+	#[pallet::getter(fn registrars)]
+	pub type Registrars<T: Config> =
+		StorageValue<_, BoundedVec<T::AccountId, T::MaxRegistrars>, ValueQuery>;
+
+	/// Known services and the account operating each.
+	#[pallet::storage]
+	#[pallet::getter(fn service_provider)]
+	pub type Services<T: Config> = StorageMap<_, Twox64Concat, ServiceName, T::AccountId>;
+
+	#[pallet::genesis_config]
+	pub struct GenesisConfig<T: Config> {
+		/// Identities to register: plaintext email, owning account and password.
+		pub identities: Vec<(Vec<u8>, T::AccountId, Vec<u8>)>,
+		/// Accounts to link to the identities above, by plaintext email.
+		pub email_links: Vec<(Vec<u8>, T::AccountId)>,
+		/// Services and the account operating each.
+		pub services: Vec<(Vec<u8>, T::AccountId)>,
+		/// Initial registrars.
+		pub registrars: Vec<T::AccountId>,
+	}
+
+	#[cfg(feature = "std")]
+	impl<T: Config> Default for GenesisConfig<T> {
+		fn default() -> Self {
+			Self {
+				identities: Default::default(),
+				email_links: Default::default(),
+				services: Default::default(),
+				registrars: Default::default(),
+			}
+		}
+	}
 
+	#[pallet::genesis_build]
+	impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
+		fn build(&self) {
+			for (email, owner, password) in &self.identities {
+				let email_hash = Pallet::<T>::email_hash(email);
+				assert!(!<Identity1Of<T>>::contains_key(&email_hash), "Identity registered twice");
+				<Identity1Of<T>>::insert(email_hash, Pallet::<T>::new_registration(owner.clone(), password));
+			}
+
+			for (email, account) in &self.email_links {
+				let email_hash = Pallet::<T>::email_hash(email);
+				assert!(<Identity1Of<T>>::contains_key(&email_hash), "Link to an unknown identity");
+				assert!(!<EmailId<T>>::contains_key(account), "Account linked twice");
+				<LinkedAccounts<T>>::try_mutate(&email_hash, |linked| linked.try_push(account.clone()))
+					.expect("Too many linked accounts");
+				<EmailId<T>>::insert(account, email_hash);
+			}
+
+			for (name, provider) in &self.services {
+				let name: ServiceName = name.clone().try_into().expect("Service name too long");
+				<Services<T>>::insert(name, provider);
+			}
+
+			let registrars: BoundedVec<_, T::MaxRegistrars> =
+				self.registrars.clone().try_into().expect("Too many registrars");
+			<Registrars<T>>::put(registrars);
+		}
+	}
+
+	/// The role of each identity that was enrolled or assigned one, see [`EnsureStudentRole`].
+	/// Identities without an entry have no role and pass no role check.
+	#[pallet::storage]
+	#[pallet::getter(fn role)]
+	pub type Roles<T: Config> = StorageMap<_, Twox64Concat, EmailHash, Role>;
+
+	/// Profile fields an identity keeps encrypted to its own key.
+	#[pallet::storage]
+	#[pallet::getter(fn encrypted_field)]
+	pub type EncryptedFields<T: Config> = StorageDoubleMap<
+		_,
+		Twox64Concat,
+		EmailHash,
+		Twox64Concat,
+		ProfileField,
+		EncryptedPayloadOf<T>,
+	>;
+
+	/// Copies of encrypted profile fields re-encrypted to the key of a viewer the identity chose
+	/// to disclose them to.
+	#[pallet::storage]
+	#[pallet::getter(fn field_grant)]
+	pub type FieldGrants<T: Config> = StorageNMap<
+		_,
+		(
+			NMapKey<Twox64Concat, EmailHash>,
+			NMapKey<Twox64Concat, ProfileField>,
+			NMapKey<Twox64Concat, T::AccountId>,
+		),
+		EncryptedPayloadOf<T>,
+	>;
+
+	/// Number of viewers each encrypted profile field has been disclosed to.
+	#[pallet::storage]
+	pub type FieldGrantCount<T: Config> =
+		StorageDoubleMap<_, Twox64Concat, EmailHash, Twox64Concat, ProfileField, u32, ValueQuery>;
+
+	/// Fee-less transactions each account has sent: the era they were counted in and how many.
+	#[pallet::storage]
+	#[pallet::getter(fn feeless_usage)]
+	pub type FeelessUsage<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, (T::BlockNumber, u32), ValueQuery>;
+
+	/// Failed logins per identity since its last successful one, whichever account sent them,
+	/// and the block logins to the identity are locked until. Written by [`failures`].
+	#[pallet::storage]
+	#[pallet::getter(fn login_attempts)]
+	pub type LoginAttempts<T: Config> =
+		StorageMap<_, Twox64Concat, EmailHash, (u32, T::BlockNumber), ValueQuery>;
+
+	/// The on-chain part of each identity's DID document, see [`Pallet::did_details`].
+	#[pallet::storage]
+	#[pallet::getter(fn did_document)]
+	pub type DidDocuments<T: Config> = StorageMap<_, Twox64Concat, EmailHash, DidDocumentOf<T>>;
 
     /// Information that is pertinent to identify the entity behind an account.
 	///
@@ -212,12 +537,16 @@ pub mod pallet {
 	>;
     
 
+	/// Student identities, keyed by the hash of their email address. The plaintext email is
+	/// never stored.
+	///
+	/// TWOX-NOTE: OK ― `EmailHash` is a secure hash.
 	#[pallet::storage]
 	#[pallet::getter(fn identity1)]
 	pub(super) type Identity1Of<T: Config> = StorageMap<
 		_,
 		Twox64Concat,
-		BoundedVec<u8, T::MaxEmailsize> ,
+		EmailHash,
 		RegistrationSel<BalanceOf<T>, T::AccountId,  T::MaxAdditionalFields>,
 		OptionQuery,
 	>;
@@ -242,6 +571,51 @@ pub mod pallet {
 
         ServiceAccessFailed,
 
+		/// The account is already linked to an identity.
+		AlreadyLinked,
+		/// The account is not linked to this identity.
+		NotLinked,
+		/// The identity already has `MaxLinkedAccounts` linked accounts.
+		TooManyLinkedAccounts,
+		/// Only a linked account or the identity owner may do this.
+		NotLinkedOrOwner,
+		/// The threshold must be at least one and at most the number of guardians.
+		InvalidThreshold,
+		/// Too many guardians.
+		TooManyGuardians,
+		/// A guardian was listed twice.
+		DuplicateGuardian,
+		/// The identity has no recovery configuration.
+		NoRecoveryConfig,
+		/// A recovery is already in progress for this identity.
+		RecoveryAlreadyActive,
+		/// There is no recovery in progress for this identity.
+		NoActiveRecovery,
+		/// The account is not a guardian of this identity.
+		NotGuardian,
+		/// The guardian has already vouched for this recovery.
+		AlreadyVouched,
+		/// The recovery is for a different account.
+		NotRecoveryAccount,
+		/// Not enough guardians have vouched yet.
+		ThresholdNotReached,
+		/// The recovery delay has not passed yet.
+		RecoveryDelayNotPassed,
+		/// The identity already has a DID document.
+		DidAlreadyExists,
+		/// The identity has no DID document.
+		NoDid,
+		/// Only the DID controller may change the document.
+		NotDidController,
+		/// The key is already a verification method of the document.
+		DuplicateDidKey,
+		/// The key is not a verification method of the document.
+		DidKeyNotFound,
+		/// The document already has `MaxDidKeys` verification methods.
+		TooManyDidKeys,
+		/// The referrer has already referred `MaxReferralsPerReferrer` identities.
+		ReferralCapReached,
+
 		/// Too many subs-accounts.
 		TooManySubAccounts,
 
@@ -264,46 +638,150 @@ pub mod pallet {
 		TooManyFields,
 		/// Maximum amount of registrars reached. Cannot add any more.
 		AlreadyClaimed,
+		/// Maximum amount of registrars reached. Cannot add any more.
+		TooManyRegistrars,
+		/// The account is already a registrar.
+		AlreadyRegistrar,
+		/// Service names are at most 32 bytes.
+		ServiceNameTooLong,
+		/// More entries than `MaxEnrollmentBatch`.
+		BatchTooLarge,
+		/// The identity has no encrypted value for this field.
+		NoEncryptedField,
+		/// The field has not been disclosed to this viewer.
+		NoFieldGrant,
+		/// The field has already been disclosed to `MaxFieldGrants` viewers.
+		TooManyFieldGrants,
+		/// The ciphertext is longer than `MaxCiphertextLen`.
+		CiphertextTooLong,
+		/// Too many failed logins; try again once the lockout has passed.
+		LoginLockedOut,
+		/// The identity has not set a PGP fingerprint to encrypt its fields to.
+		NoPgpFingerprint,
+		/// The value is encrypted to another key than the identity's PGP fingerprint.
+		FingerprintMismatch,
+		/// The identity has no role.
+		NoRole,
 	}
 
 	#[pallet::event]
 	#[pallet::generate_deposit(pub(super) fn deposit_event)]
 	pub enum Event<T: Config> {
-		/// A name was set or reset (which will remove all judgements).
-		UserRegistered { who: Vec<u8>      },
-		UserExists { who: Vec<u8>      },
-		UserInvalid { who: Vec<u8>      },
-		UserRegisterfailed { who: Vec<u8>      },
-
-		UserWeb3registered { who: Vec<u8>      },
-		UserWeb3registerfailed { who: Vec<u8>      },
-
-		UserLoginsuccess { who: Vec<u8>, blocksession: Vec<u8>      },
-		UserLoginfailed { who: Vec<u8>      },
-		UserDoesnotexist { who: Vec<u8>      },
-
-		UserWeb3loginsuccess { who: Vec<u8>, blocksession: Vec<u8>      },
-		UserWeb3loginfailed { who: Vec<u8>      },
-		UserWeb3doesnotexist { who: Vec<u8>      },
-
+		// The first twenty variants keep the order they were originally declared in, so event
+		// indices seen by indexers do not shift. New variants go at the end.
+
+		/// An identity was registered, owned by `owner`.
+		UserRegistered { email_hash: EmailHash, owner: T::AccountId },
+		/// `who` tried to register or enroll an identity that already exists.
+		UserExists { email_hash: EmailHash, who: T::AccountId },
+		/// Not deposited by any call; kept so later variants keep their index.
+		UserInvalid { email_hash: EmailHash },
+		/// A registration failed for another reason than the identity existing already.
+		UserRegisterfailed { email_hash: EmailHash, who: T::AccountId, error: DispatchError },
+
+		/// A web3 account was linked to an identity.
+		UserWeb3registered { email_hash: EmailHash, account: T::AccountId },
+		/// Linking a web3 account to an identity failed.
+		UserWeb3registerfailed { email_hash: EmailHash, account: T::AccountId, error: DispatchError },
+
+		/// A password login succeeded.
+		UserLoginsuccess { email_hash: EmailHash, who: T::AccountId },
+		/// A password login failed; the attempt counts towards a lockout.
+		UserLoginfailed { email_hash: EmailHash, who: T::AccountId },
+		/// A password login named an identity that does not exist.
+		UserDoesnotexist { email_hash: EmailHash, who: T::AccountId },
+
+		/// A linked web3 account logged in.
+		UserWeb3loginsuccess { email_hash: EmailHash, account: T::AccountId },
+		/// A web3 login failed because the account is not linked to the identity.
+		UserWeb3loginfailed { email_hash: EmailHash, account: T::AccountId },
+		/// A web3 login named an identity that does not exist.
+		UserWeb3doesnotexist { email_hash: EmailHash, account: T::AccountId },
+
+		/// Not deposited by any call; kept so later variants keep their index.
 		IdentitySet { who: T::AccountId },
-		/// A name was cleared, and the given balance returned.
+		/// Not deposited by any call; kept so later variants keep their index.
 		IdentityCleared { who: T::AccountId, deposit: BalanceOf<T> },
-		/// A name was removed and the given balance slashed.
+		/// Not deposited by any call; kept so later variants keep their index.
 		IdentityKilled { who: T::AccountId, deposit: BalanceOf<T> },
-        
-		/// A useridentity was added.
+
+		/// Not deposited by any call; kept so later variants keep their index.
 		UseridentityAdded { useridentity_index: UseridentityIndex },
 
 		/// A registrar was added.
 		RegistrarAdded { registrar_index: RegistrarIndex },
-		/// A sub-identity was added to an identity and the deposit paid.
+		/// Not deposited by any call; kept so later variants keep their index.
 		SubIdentityAdded { sub: T::AccountId, main: T::AccountId, deposit: BalanceOf<T> },
-		/// A sub-identity was removed from an identity and the deposit freed.
+		/// Not deposited by any call; kept so later variants keep their index.
 		SubIdentityRemoved { sub: T::AccountId, main: T::AccountId, deposit: BalanceOf<T> },
-		/// A sub-identity was cleared, and the given deposit repatriated from the
-		/// main identity account to the sub-identity account.
+		/// Not deposited by any call; kept so later variants keep their index.
 		SubIdentityRevoked { sub: T::AccountId, main: T::AccountId, deposit: BalanceOf<T> },
+
+		/// The password of an identity was changed.
+		PasswordChanged { email_hash: EmailHash, who: T::AccountId },
+		/// A referal was set for an identity, to be used by the next account that links to it.
+		ReferalSet { email_hash: EmailHash, by: T::AccountId },
+		/// An identity was granted access to a service.
+		ServiceAccessSet { email_hash: EmailHash, service: Vec<u8>, by: T::AccountId },
+		/// A linked account's access to a service was checked and confirmed.
+		ServiceAccessChecked { email_hash: EmailHash, account: T::AccountId, service: Vec<u8> },
+
+		/// A web3 account was unlinked from an identity.
+		Web3AccountUnlinked { email_hash: EmailHash, account: T::AccountId },
+
+		/// A referral edge was recorded. The reward is paid once the referred identity is
+		/// verified.
+		ReferralRecorded { referrer: EmailHash, referred: EmailHash },
+		/// A referral reward was paid from the referral pot.
+		ReferralRewardPaid { referred: EmailHash, account: T::AccountId, reward: BalanceOf<T> },
+
+		/// Recovery guardians were set for an identity.
+		RecoveryConfigured { email_hash: EmailHash },
+		/// Recovery guardians were removed from an identity.
+		RecoveryRemoved { email_hash: EmailHash },
+		/// A new key started recovering an identity.
+		RecoveryInitiated { email_hash: EmailHash, new_account: T::AccountId },
+		/// A guardian vouched for a recovery.
+		RecoveryVouched { email_hash: EmailHash, new_account: T::AccountId, guardian: T::AccountId },
+		/// A recovery was cancelled by the identity.
+		RecoveryCancelled { email_hash: EmailHash, new_account: T::AccountId },
+		/// A recovery was claimed: the new key replaced every linked account.
+		IdentityRecovered { email_hash: EmailHash, new_account: T::AccountId },
+		/// A DID document was created for an identity.
+		DidCreated { email_hash: EmailHash, controller: T::AccountId },
+		/// A verification method was added to a DID document.
+		DidKeyAdded { email_hash: EmailHash, public_key: [u8; 32] },
+		/// A verification method of a DID document was replaced by a new key.
+		DidKeyRotated { email_hash: EmailHash, old_key: [u8; 32], new_key: [u8; 32] },
+		/// A verification method was removed from a DID document.
+		DidKeyRemoved { email_hash: EmailHash, public_key: [u8; 32] },
+		/// A DID document got a new controller.
+		DidControllerChanged { email_hash: EmailHash, controller: T::AccountId },
+
+		/// An institution or admin assigned a role to an identity; `by` is `None` for root.
+		RoleAssigned { email_hash: EmailHash, role: Role, by: Option<T::AccountId> },
+		/// Too many failed logins; logins to the identity are locked until block `until`.
+		LoginLockedOut { email_hash: EmailHash, until: T::BlockNumber },
+		/// An admin lifted a login lockout.
+		LoginUnlocked { email_hash: EmailHash },
+		/// An encrypted profile field was set; any plaintext copy was cleared.
+		EncryptedFieldSet { email_hash: EmailHash, field: ProfileField, recipient_fingerprint: [u8; 20] },
+		/// An encrypted profile field and all of its grants were removed.
+		EncryptedFieldCleared { email_hash: EmailHash, field: ProfileField },
+		/// An encrypted profile field was disclosed to a viewer.
+		FieldGranted { email_hash: EmailHash, field: ProfileField, viewer: T::AccountId },
+		/// A viewer's copy of an encrypted profile field was removed.
+		FieldGrantRevoked { email_hash: EmailHash, field: ProfileField, viewer: T::AccountId },
+		/// A student was enrolled by an institution.
+		StudentEnrolled { email_hash: EmailHash, institution: T::AccountId },
+		/// A batch enrollment finished.
+		BatchEnrolled { institution: T::AccountId, enrolled: u32, failed: u32 },
+		/// A service was added to the registry, or got a new provider.
+		ServiceRegistered { name: Vec<u8>, provider: T::AccountId },
+		/// The PGP fingerprint of an identity was set or, with `None`, removed.
+		PgpFingerprintSet { email_hash: EmailHash, fingerprint: Option<[u8; 20]> },
+		/// The role of an identity was removed; `by` is `None` for root.
+		RoleCleared { email_hash: EmailHash, by: Option<T::AccountId> },
 	}
 
 	#[pallet::call]
@@ -314,53 +792,18 @@ pub mod pallet {
         #[pallet::weight(1_000)]
 		pub fn request_registration_sel11(
 			origin: OriginFor<T>,
-			email: Vec<u8>,
+			email_hash: EmailHash,
 			password: Vec<u8>,
 		) -> DispatchResult {
 			let sender = ensure_signed(origin)?;
 
-            let emailx : BoundedVec<_, T::MaxEmailsize> = email.clone().try_into().unwrap();
-        
-            ensure!(!Identity1Of::<T>::contains_key(&emailx), Error::<T>::IdentityAlreadyClaimed);
-
-
-
-            let add: BoundedVec<_, T::MaxAdditionalFields> = vec![
-                    (
-                        Data::Raw(b"number".to_vec().try_into().unwrap()),
-                        Data::Raw(10u32.encode().try_into().unwrap())
-                    ),
-                    (
-                        Data::Raw(b"text".to_vec().try_into().unwrap()),
-                        Data::Raw(b"10".to_vec().try_into().unwrap())
-                    ),
-                ]
-                .try_into()
-                .unwrap();
-
-
-        let info =  IdentityInfoSel {
-        display: Data::Raw(b"ten".to_vec().try_into().unwrap()),
-        legal: Data::Raw(b"The Right Ordinal Ten, Esq.".to_vec().try_into().unwrap()),
-        image: Data::Raw(b"The Right Ordinal Ten, Esq.".to_vec().try_into().unwrap()),
-        web: Data::Raw(b"The Right Ordinal Ten, Esq.".to_vec().try_into().unwrap()),
-        referalhash: Data::Raw(b"The Right Ordinal Ten, Esq.".to_vec().try_into().unwrap()),
-        email: Data::Raw(email.clone().try_into().unwrap()),
-        passwordhash: Data::BlakeTwo256(blake2_256(&password.clone())),
-        pgp_fingerprint: None,
-        account: Data::Raw(b"The Right Ordinal Ten, Esq.".to_vec().try_into().unwrap()),
-        additional: add
-        };
-
-
-        let reg = RegistrationSel {
-                    accountId:sender, 
-                    info: info,
-                    deposit: Zero::zero(),
-        };
+            ensure!(!Identity1Of::<T>::contains_key(&email_hash), Error::<T>::IdentityAlreadyClaimed);
 
-            
-			<Identity1Of<T>>::insert(emailx, reg);
+            let reg = Self::new_registration(sender.clone(), &password);
+
+			<Identity1Of<T>>::insert(email_hash, reg);
+
+			Self::deposit_event(Event::UserRegistered { email_hash, owner: sender });
 
 			Ok(())
 		}
@@ -370,21 +813,27 @@ pub mod pallet {
         #[pallet::weight(1_000)]
 		pub fn login_access_sel12(
 			origin: OriginFor<T>,
-			email: Vec<u8>,
+			email_hash: EmailHash,
 			password: Vec<u8>,
 		) -> DispatchResult {
 			let sender = ensure_signed(origin)?;
 
-            let emailx : BoundedVec<_, T::MaxEmailsize> = email.clone().try_into().unwrap();
+			let id = <Identity1Of<T>>::get(&email_hash).ok_or(Error::<T>::NoIdentity)?;
 
-			let id = <Identity1Of<T>>::get(&emailx).ok_or(Error::<T>::NoIdentity)?;
+            let now = <frame_system::Pallet<T>>::block_number();
+            let (_, locked_until) = <LoginAttempts<T>>::get(&email_hash);
+            ensure!(now >= locked_until, Error::<T>::LoginLockedOut);
 
             let info = id.info;
         
 
             let passtocheck = Data::BlakeTwo256(blake2_256(&password.clone()));
 
-            ensure!(info.passwordhash == passtocheck , Error::<T>::LoginFailed);
+            // A failure is counted by `failures::RecordFailures` once the call has failed.
+            ensure!(info.password_hash == passtocheck, Error::<T>::LoginFailed);
+
+            <LoginAttempts<T>>::remove(&email_hash);
+            Self::deposit_event(Event::UserLoginsuccess { email_hash, who: sender });
 			Ok(())
 		}
 
@@ -392,32 +841,32 @@ pub mod pallet {
         #[pallet::weight(1_000)]
 		pub fn change_password_sel13(
 			origin: OriginFor<T>,
-			email: Vec<u8>,
+			email_hash: EmailHash,
 			password: Vec<u8>,
 		) -> DispatchResult {
 
 			let sender = ensure_signed(origin)?;
 
-            let emailx : BoundedVec<_, T::MaxEmailsize> = email.clone().try_into().unwrap();
-
-			let id = <Identity1Of<T>>::get(&emailx).ok_or(Error::<T>::NoIdentity)?;
+			let id = <Identity1Of<T>>::get(&email_hash).ok_or(Error::<T>::NoIdentity)?;
+            Self::ensure_linked_or_owner(&email_hash, &sender)?;
 
             let mut info = id.info;
 
             let newpassword = Data::BlakeTwo256(blake2_256(&password.clone()));
 
-            info.passwordhash  = newpassword;
-            info.account  =  Data::Raw(b"The Right Ordinal Ten, Esq.".to_vec().try_into().unwrap());
+            info.password_hash  = newpassword;
 
             let reg = RegistrationSel {
-                    accountId: sender,
+                    owner: id.owner,
                     info: info,
-                    deposit: Zero::zero(),
+                    deposit: id.deposit,
 
             };
 
             
-			<Identity1Of<T>>::insert(emailx, reg);
+			<Identity1Of<T>>::insert(email_hash, reg);
+
+			Self::deposit_event(Event::PasswordChanged { email_hash, who: sender });
 
 			Ok(())
 		}
@@ -425,31 +874,46 @@ pub mod pallet {
         #[pallet::weight(1_000)]
 		pub fn set_referal_sel12(
 			origin: OriginFor<T>,
-			email: Vec<u8>,
+			email_hash: EmailHash,
 			referal: Vec<u8>,
 		) -> DispatchResult {
             
 			let sender = ensure_signed(origin)?;
 
-            let emailx : BoundedVec<_, T::MaxEmailsize> = email.clone().try_into().unwrap();
-
-			let id = <Identity1Of<T>>::get(&emailx).ok_or(Error::<T>::NoIdentity)?;
+			let id = <Identity1Of<T>>::get(&email_hash).ok_or(Error::<T>::NoIdentity)?;
+            Self::ensure_linked_or_owner(&email_hash, &sender)?;
+
+            // A referal set by an owner that is linked to another identity, e.g. a student who
+            // registered a friend's identity, is attributed to that other identity. A referal
+            // an identity hands out to itself, e.g. to link a second device, refers nobody.
+            match <EmailId<T>>::get(&sender).filter(|referrer| *referrer != email_hash) {
+                Some(referrer) => {
+                    ensure!(
+                        Self::referral_count(&referrer) < T::MaxReferralsPerReferrer::get(),
+                        Error::<T>::ReferralCapReached
+                    );
+                    <PendingReferrer<T>>::insert(&email_hash, (referrer, sender.clone()));
+                },
+                None => <PendingReferrer<T>>::remove(&email_hash),
+            }
 
             let hashtoset = Data::Sha256(sha2_256(&referal.clone()));
 
             let mut info = id.info;
 
-            info.referalhash  =  hashtoset;
+            info.referral_hash  =  hashtoset;
 
 
             let reg = RegistrationSel {
-                    accountId: sender,
+                    owner: id.owner,
                     info: info,
-                    deposit: Zero::zero(),
+                    deposit: id.deposit,
             };
 
             
-			<Identity1Of<T>>::insert(emailx, reg);
+			<Identity1Of<T>>::insert(email_hash, reg);
+
+			Self::deposit_event(Event::ReferalSet { email_hash, by: sender });
 
 			Ok(())
 		}
@@ -458,45 +922,54 @@ pub mod pallet {
         #[pallet::weight(1_000)]
 		pub fn create_web3link_sel15(
 			origin: OriginFor<T>,
-			email: Vec<u8>,
+			email_hash: EmailHash,
 			idtolink: T::AccountId,
 			referal: Vec<u8>,
 		) -> DispatchResult {
             // We check if the signer is same as in email-id
-            // Then update account-id of that record
+            // Then add the account to the linked accounts of that record
             // That user would have received referal key, onlythen he can link 
             // After use referal is removed
-            // No more linking possible     
+            // Linking another account needs a fresh referal
             // We check if the person signing is same as origin
             
 			let sender = ensure_signed(origin)?;
 
             ensure!(sender == idtolink , Error::<T>::SignerNotmatching);
+            ensure!(!<EmailId<T>>::contains_key(&sender), Error::<T>::AlreadyLinked);
 
-            let emailx : BoundedVec<_, T::MaxEmailsize> = email.clone().try_into().unwrap();
-
-			let id = <Identity1Of<T>>::get(&emailx).ok_or(Error::<T>::NoIdentity)?;
+			let id = <Identity1Of<T>>::get(&email_hash).ok_or(Error::<T>::NoIdentity)?;
 
             let hashtocheck = Data::Sha256(sha2_256(&referal.clone()));
 
             let mut info = id.info;
 
-            ensure!(info.referalhash == hashtocheck , Error::<T>::ReferalFailed);
+            ensure!(info.referral_hash == hashtocheck , Error::<T>::ReferalFailed);
 
+            <LinkedAccounts<T>>::try_mutate(&email_hash, |linked| {
+                linked.try_push(sender.clone())
+            }).map_err(|_| Error::<T>::TooManyLinkedAccounts)?;
 
             // Remove referal 
-            info.referalhash  =  Data::Raw(b"null".to_vec().try_into().unwrap());
+            info.referral_hash  =  Data::Raw(b"null".to_vec().try_into().unwrap());
 
 
             let reg = RegistrationSel {
-                    accountId: sender.clone(),
+                    owner: id.owner,
                     info: info,
-                    deposit: Zero::zero(),
+                    deposit: id.deposit,
             };
 
             
-			<Identity1Of<T>>::insert(emailx.clone(), reg);
-            <EmailId<T>>::insert(sender, emailx);
+			<Identity1Of<T>>::insert(email_hash, reg);
+            <EmailId<T>>::insert(&sender, email_hash);
+
+            Self::deposit_event(Event::UserWeb3registered { email_hash, account: sender });
+
+            if let Some((referrer, referrer_account)) = <PendingReferrer<T>>::take(&email_hash) {
+                Self::record_referral(referrer, referrer_account, email_hash);
+            }
+            Self::pay_referral_reward(&email_hash);
 
 			Ok(())
 		}
@@ -504,20 +977,16 @@ pub mod pallet {
         #[pallet::weight(1_000)]
 		pub fn login_web3_sel16(
 			origin: OriginFor<T>,
-			email: Vec<u8>,
+			email_hash: EmailHash,
 		) -> DispatchResult{
             // let key = Origin::signed(1);
             
 			let sender = ensure_signed(origin)?;
 
+            ensure!(<Identity1Of<T>>::contains_key(&email_hash), Error::<T>::NoIdentity);
+            ensure!(Self::is_linked(&email_hash, &sender), Error::<T>::LoginFailed);
 
-            let emailx : BoundedVec<_, T::MaxEmailsize> = email.clone().try_into().unwrap();
-
-			let id = <Identity1Of<T>>::get(&emailx).ok_or(Error::<T>::NoIdentity)?;
-            ensure!(sender == id.accountId , Error::<T>::LoginFailed);
-
-            let tokenid : TokenId<T> =   email.clone().try_into().unwrap();
-
+			Self::deposit_event(Event::UserWeb3loginsuccess { email_hash, account: sender });
 
 			Ok(())
 		}
@@ -532,13 +1001,11 @@ pub mod pallet {
 
             let sender = ensure_signed(origin)?;
 
-            let email = <EmailId<T>>::get(idtoaccess.clone()).ok_or(Error::<T>::NoIdentity)?;
-
-            let emailx : BoundedVec<_, T::MaxEmailsize> = email.clone().try_into().unwrap();
+            let email_hash = <EmailId<T>>::get(idtoaccess.clone()).ok_or(Error::<T>::NoIdentity)?;
 
-            let id = <Identity1Of<T>>::get(&emailx).ok_or(Error::<T>::NoIdentity)?;
+            let id = <Identity1Of<T>>::get(&email_hash).ok_or(Error::<T>::NoIdentity)?;
 
-            let web = Data::Raw(service.try_into().unwrap());
+            let web = Data::Raw(service.clone().try_into().unwrap());
 
             let mut info = id.info;
 
@@ -546,13 +1013,15 @@ pub mod pallet {
 
 
             let reg = RegistrationSel {
-                    accountId: id.accountId,
+                    owner: id.owner,
                     info: info,
-                    deposit: Zero::zero(),
+                    deposit: id.deposit,
             };
 
 
-            <Identity1Of<T>>::insert(emailx, reg);
+            <Identity1Of<T>>::insert(email_hash, reg);
+
+            Self::deposit_event(Event::ServiceAccessSet { email_hash, service, by: sender });
 
             Ok(())
         }
@@ -566,20 +1035,1036 @@ pub mod pallet {
 
             let sender = ensure_signed(origin)?;
 
-            let email = <EmailId<T>>::get(sender.clone()).ok_or(Error::<T>::NoIdentity)?;
-
-            let emailx : BoundedVec<_, T::MaxEmailsize> = email.clone().try_into().unwrap();
+            let email_hash = <EmailId<T>>::get(sender.clone()).ok_or(Error::<T>::NoIdentity)?;
 
-            let id = <Identity1Of<T>>::get(&emailx).ok_or(Error::<T>::NoIdentity)?;
+            let id = <Identity1Of<T>>::get(&email_hash).ok_or(Error::<T>::NoIdentity)?;
           
-            let servicetocheck = Data::Raw(service.try_into().unwrap());
+            let servicetocheck = Data::Raw(service.clone().try_into().unwrap());
             let info = id.info;
             
             ensure!(servicetocheck == info.web , Error::<T>::ServiceAccessFailed);
 
+            Self::deposit_event(Event::ServiceAccessChecked { email_hash, account: sender, service });
+
+            Ok(())
+        }
+
+        /// Unlink `account` from the identity. May be called by any account linked to the
+        /// identity (including `account` itself, e.g. from a second device after a key is
+        /// compromised) or by the identity owner. Linking the account again needs a fresh
+        /// referal.
+        #[pallet::weight(1_000)]
+        pub fn unlink_web3_sel19(
+            origin: OriginFor<T>,
+            email_hash: EmailHash,
+            account: T::AccountId,
+        ) -> DispatchResult {
+
+            let sender = ensure_signed(origin)?;
+
+            Self::ensure_linked_or_owner(&email_hash, &sender)?;
+
+            <LinkedAccounts<T>>::try_mutate(&email_hash, |linked| {
+                let pos = linked.iter().position(|a| *a == account).ok_or(Error::<T>::NotLinked)?;
+                linked.remove(pos);
+                Ok::<_, Error<T>>(())
+            })?;
+            <EmailId<T>>::remove(&account);
+
+            Self::deposit_event(Event::Web3AccountUnlinked { email_hash, account });
+
+            Ok(())
+        }
+
+        /// Set the guardians that can recover the identity onto a new key, and how many of them
+        /// must vouch. Called by a linked account or the identity owner, and not while a
+        /// recovery is in progress.
+        #[pallet::weight(1_000)]
+        pub fn set_recovery_sel20(
+            origin: OriginFor<T>,
+            email_hash: EmailHash,
+            guardians: Vec<T::AccountId>,
+            threshold: u16,
+        ) -> DispatchResult {
+
+            let sender = ensure_signed(origin)?;
+
+            Self::ensure_linked_or_owner(&email_hash, &sender)?;
+            ensure!(!Self::recovery_in_progress(&email_hash), Error::<T>::RecoveryAlreadyActive);
+
+            let mut guardians = guardians;
+            let count = guardians.len();
+            guardians.sort();
+            guardians.dedup();
+            ensure!(guardians.len() == count, Error::<T>::DuplicateGuardian);
+            ensure!(threshold >= 1 && threshold as usize <= count, Error::<T>::InvalidThreshold);
+
+            let guardians: BoundedVec<_, T::MaxGuardians> =
+                guardians.try_into().map_err(|_| Error::<T>::TooManyGuardians)?;
+
+            <RecoveryConfigs<T>>::insert(&email_hash, RecoveryConfig { guardians, threshold });
+
+            Self::deposit_event(Event::RecoveryConfigured { email_hash });
+
+            Ok(())
+        }
+
+        /// Remove the recovery guardians of the identity.
+        #[pallet::weight(1_000)]
+        pub fn remove_recovery_sel21(
+            origin: OriginFor<T>,
+            email_hash: EmailHash,
+        ) -> DispatchResult {
+
+            let sender = ensure_signed(origin)?;
+
+            Self::ensure_linked_or_owner(&email_hash, &sender)?;
+            ensure!(!Self::recovery_in_progress(&email_hash), Error::<T>::RecoveryAlreadyActive);
+            ensure!(<RecoveryConfigs<T>>::contains_key(&email_hash), Error::<T>::NoRecoveryConfig);
+
+            <RecoveryConfigs<T>>::remove(&email_hash);
+
+            Self::deposit_event(Event::RecoveryRemoved { email_hash });
+
+            Ok(())
+        }
+
+        /// Start recovering the identity onto the signing key, reserving `RecoveryDeposit`.
+        /// The key must not be linked to any identity yet. Recoveries onto different keys run
+        /// side by side, so one key cannot hold up the others.
+        #[pallet::weight(1_000)]
+        pub fn initiate_recovery_sel22(
+            origin: OriginFor<T>,
+            email_hash: EmailHash,
+        ) -> DispatchResult {
+
+            let sender = ensure_signed(origin)?;
+
+            ensure!(<Identity1Of<T>>::contains_key(&email_hash), Error::<T>::NoIdentity);
+            ensure!(<RecoveryConfigs<T>>::contains_key(&email_hash), Error::<T>::NoRecoveryConfig);
+            ensure!(!<ActiveRecoveries<T>>::contains_key(&email_hash, &sender), Error::<T>::RecoveryAlreadyActive);
+            ensure!(!<EmailId<T>>::contains_key(&sender), Error::<T>::AlreadyLinked);
+
+            let deposit = T::RecoveryDeposit::get();
+            T::Currency::reserve(&sender, deposit)?;
+
+            let recovery = ActiveRecovery {
+                started: <frame_system::Pallet<T>>::block_number(),
+                deposit,
+                vouchers: Default::default(),
+            };
+            <ActiveRecoveries<T>>::insert(&email_hash, &sender, recovery);
+
+            Self::deposit_event(Event::RecoveryInitiated { email_hash, new_account: sender });
+
+            Ok(())
+        }
+
+        /// Vouch, as a guardian, that `new_account` should take over the identity.
+        #[pallet::weight(1_000)]
+        pub fn vouch_recovery_sel23(
+            origin: OriginFor<T>,
+            email_hash: EmailHash,
+            new_account: T::AccountId,
+        ) -> DispatchResult {
+
+            let sender = ensure_signed(origin)?;
+
+            let config = <RecoveryConfigs<T>>::get(&email_hash).ok_or(Error::<T>::NoRecoveryConfig)?;
+            ensure!(config.guardians.binary_search(&sender).is_ok(), Error::<T>::NotGuardian);
+
+            <ActiveRecoveries<T>>::try_mutate(&email_hash, &new_account, |maybe_recovery| {
+                let recovery = maybe_recovery.as_mut().ok_or(Error::<T>::NoActiveRecovery)?;
+                ensure!(!recovery.vouchers.contains(&sender), Error::<T>::AlreadyVouched);
+                recovery.vouchers.try_push(sender.clone()).map_err(|_| Error::<T>::TooManyGuardians)
+            })?;
+
+            Self::deposit_event(Event::RecoveryVouched { email_hash, new_account, guardian: sender });
+
+            Ok(())
+        }
+
+        /// Claim the identity once enough guardians have vouched and `RecoveryDelay` has
+        /// passed. Every previously linked account is unlinked and the signing key becomes the
+        /// only linked account and the owner. Other recoveries of the identity are dropped and
+        /// every recovery deposit is returned.
+        #[pallet::weight(1_000)]
+        pub fn claim_recovery_sel24(
+            origin: OriginFor<T>,
+            email_hash: EmailHash,
+        ) -> DispatchResult {
+
+            let sender = ensure_signed(origin)?;
+
+            let recovery = <ActiveRecoveries<T>>::get(&email_hash, &sender).ok_or(Error::<T>::NoActiveRecovery)?;
+
+            let config = <RecoveryConfigs<T>>::get(&email_hash).ok_or(Error::<T>::NoRecoveryConfig)?;
+            ensure!(recovery.vouchers.len() >= config.threshold as usize, Error::<T>::ThresholdNotReached);
+
+            let now = <frame_system::Pallet<T>>::block_number();
+            ensure!(now >= recovery.started.saturating_add(T::RecoveryDelay::get()), Error::<T>::RecoveryDelayNotPassed);
+
+            // The key may have been linked elsewhere since the recovery started.
+            ensure!(!<EmailId<T>>::contains_key(&sender), Error::<T>::AlreadyLinked);
+
+            let mut id = <Identity1Of<T>>::get(&email_hash).ok_or(Error::<T>::NoIdentity)?;
+
+            for old in <LinkedAccounts<T>>::take(&email_hash) {
+                <EmailId<T>>::remove(&old);
+            }
+            let linked: BoundedVec<_, T::MaxLinkedAccounts> =
+                vec![sender.clone()].try_into().map_err(|_| Error::<T>::TooManyLinkedAccounts)?;
+            <LinkedAccounts<T>>::insert(&email_hash, linked);
+            <EmailId<T>>::insert(&sender, email_hash);
+
+            id.owner = sender.clone();
+            <Identity1Of<T>>::insert(&email_hash, id);
+            for (rescuer, recovery) in <ActiveRecoveries<T>>::drain_prefix(&email_hash) {
+                T::Currency::unreserve(&rescuer, recovery.deposit);
+            }
+            <DidDocuments<T>>::mutate(&email_hash, |doc| {
+                if let Some(doc) = doc {
+                    doc.controller = sender.clone();
+                    doc.updated = now;
+                }
+            });
+
+            Self::deposit_event(Event::IdentityRecovered { email_hash, new_account: sender });
+
+            Ok(())
+        }
+
+        /// Cancel the recovery onto `new_account`. Called by a linked account or the identity
+        /// owner, e.g. when the old key is not lost after all; the recovery deposit is paid to
+        /// the caller.
+        #[pallet::weight(1_000)]
+        pub fn cancel_recovery_sel25(
+            origin: OriginFor<T>,
+            email_hash: EmailHash,
+            new_account: T::AccountId,
+        ) -> DispatchResult {
+
+            let sender = ensure_signed(origin)?;
+
+            Self::ensure_linked_or_owner(&email_hash, &sender)?;
+            let recovery = <ActiveRecoveries<T>>::take(&email_hash, &new_account).ok_or(Error::<T>::NoActiveRecovery)?;
+            let _ = T::Currency::repatriate_reserved(&new_account, &sender, recovery.deposit, BalanceStatus::Free);
+
+            Self::deposit_event(Event::RecoveryCancelled { email_hash, new_account });
+
+            Ok(())
+        }
+
+        /// Create the DID document of an identity, with the caller as controller. The caller
+        /// must be linked to the identity or own it.
+        #[pallet::weight(1_000)]
+        pub fn create_did_sel26(
+            origin: OriginFor<T>,
+            email_hash: EmailHash,
+            verification_methods: Vec<DidVerificationMethod>,
+        ) -> DispatchResult {
+
+            let sender = ensure_signed(origin)?;
+
+            Self::ensure_linked_or_owner(&email_hash, &sender)?;
+            ensure!(!<DidDocuments<T>>::contains_key(&email_hash), Error::<T>::DidAlreadyExists);
+
+            let mut keys: Vec<[u8; 32]> = verification_methods.iter().map(|m| m.public_key).collect();
+            keys.sort();
+            keys.dedup();
+            ensure!(keys.len() == verification_methods.len(), Error::<T>::DuplicateDidKey);
+
+            let doc = DidDocument {
+                controller: sender.clone(),
+                verification_methods: verification_methods.try_into().map_err(|_| Error::<T>::TooManyDidKeys)?,
+                updated: <frame_system::Pallet<T>>::block_number(),
+            };
+            <DidDocuments<T>>::insert(&email_hash, doc);
+
+            Self::deposit_event(Event::DidCreated { email_hash, controller: sender });
+
+            Ok(())
+        }
+
+        /// Add a verification method. Controller only.
+        #[pallet::weight(1_000)]
+        pub fn add_did_key_sel27(
+            origin: OriginFor<T>,
+            email_hash: EmailHash,
+            method: DidVerificationMethod,
+        ) -> DispatchResult {
+
+            let sender = ensure_signed(origin)?;
+
+            Self::mutate_did_as_controller(&email_hash, &sender, |doc| {
+                ensure!(
+                    !doc.verification_methods.iter().any(|m| m.public_key == method.public_key),
+                    Error::<T>::DuplicateDidKey
+                );
+                doc.verification_methods.try_push(method).map_err(|_| Error::<T>::TooManyDidKeys)?;
+                Ok(())
+            })?;
+
+            Self::deposit_event(Event::DidKeyAdded { email_hash, public_key: method.public_key });
+
+            Ok(())
+        }
+
+        /// Replace the verification method with key `old_key` by `new_method`, keeping its
+        /// position. Controller only.
+        #[pallet::weight(1_000)]
+        pub fn rotate_did_key_sel28(
+            origin: OriginFor<T>,
+            email_hash: EmailHash,
+            old_key: [u8; 32],
+            new_method: DidVerificationMethod,
+        ) -> DispatchResult {
+
+            let sender = ensure_signed(origin)?;
+
+            Self::mutate_did_as_controller(&email_hash, &sender, |doc| {
+                ensure!(
+                    !doc.verification_methods.iter().any(|m| m.public_key == new_method.public_key),
+                    Error::<T>::DuplicateDidKey
+                );
+                let slot = doc
+                    .verification_methods
+                    .iter_mut()
+                    .find(|m| m.public_key == old_key)
+                    .ok_or(Error::<T>::DidKeyNotFound)?;
+                *slot = new_method;
+                Ok(())
+            })?;
+
+            Self::deposit_event(Event::DidKeyRotated { email_hash, old_key, new_key: new_method.public_key });
+
+            Ok(())
+        }
+
+        /// Remove a verification method. Controller only.
+        #[pallet::weight(1_000)]
+        pub fn remove_did_key_sel29(
+            origin: OriginFor<T>,
+            email_hash: EmailHash,
+            public_key: [u8; 32],
+        ) -> DispatchResult {
+
+            let sender = ensure_signed(origin)?;
+
+            Self::mutate_did_as_controller(&email_hash, &sender, |doc| {
+                let before = doc.verification_methods.len();
+                doc.verification_methods.retain(|m| m.public_key != public_key);
+                ensure!(doc.verification_methods.len() < before, Error::<T>::DidKeyNotFound);
+                Ok(())
+            })?;
+
+            Self::deposit_event(Event::DidKeyRemoved { email_hash, public_key });
+
+            Ok(())
+        }
+
+        /// Hand the DID document to another account linked to the identity. Called by the
+        /// controller, or by the identity owner when the controller has been unlinked.
+        #[pallet::weight(1_000)]
+        pub fn set_did_controller_sel30(
+            origin: OriginFor<T>,
+            email_hash: EmailHash,
+            controller: T::AccountId,
+        ) -> DispatchResult {
+
+            let sender = ensure_signed(origin)?;
+
+            Self::ensure_linked_or_owner(&email_hash, &controller)?;
+            let mut doc = <DidDocuments<T>>::get(&email_hash).ok_or(Error::<T>::NoDid)?;
+            let is_owner = <Identity1Of<T>>::get(&email_hash).map_or(false, |id| id.owner == sender);
+            ensure!(
+                doc.controller == sender || (is_owner && !Self::is_linked(&email_hash, &doc.controller)),
+                Error::<T>::NotDidController
+            );
+
+            doc.controller = controller.clone();
+            doc.updated = <frame_system::Pallet<T>>::block_number();
+            <DidDocuments<T>>::insert(&email_hash, doc);
+
+            Self::deposit_event(Event::DidControllerChanged { email_hash, controller });
+
+            Ok(())
+        }
+
+        /// Add a registrar. Must be called by `RegistrarOrigin`.
+        #[pallet::weight(1_000)]
+        pub fn add_registrar_sel31(
+            origin: OriginFor<T>,
+            account: T::AccountId,
+        ) -> DispatchResult {
+
+            T::RegistrarOrigin::ensure_origin(origin)?;
+
+            let registrar_index = <Registrars<T>>::try_mutate(|registrars| -> Result<RegistrarIndex, DispatchError> {
+                ensure!(!registrars.contains(&account), Error::<T>::AlreadyRegistrar);
+                registrars.try_push(account).map_err(|_| Error::<T>::TooManyRegistrars)?;
+                Ok((registrars.len() - 1) as RegistrarIndex)
+            })?;
+
+            Self::deposit_event(Event::RegistrarAdded { registrar_index });
+
+            Ok(())
+        }
+
+        /// Add a service to the registry, or change its provider. Must be called by
+        /// `RegistrarOrigin`.
+        #[pallet::weight(1_000)]
+        pub fn register_service_sel32(
+            origin: OriginFor<T>,
+            name: Vec<u8>,
+            provider: T::AccountId,
+        ) -> DispatchResult {
+
+            T::RegistrarOrigin::ensure_origin(origin)?;
+
+            let service: ServiceName = name.clone().try_into().map_err(|_| Error::<T>::ServiceNameTooLong)?;
+            <Services<T>>::insert(service, &provider);
+
+            Self::deposit_event(Event::ServiceRegistered { name, provider });
+
+            Ok(())
+        }
+
+        /// Register a batch of students owned by the calling institution. Each entry gets the
+        /// given referal hash, so the student can link an account with the referal handed out
+        /// by the institution, and no password until one is set.
+        ///
+        /// Entries whose email hash is already registered, including duplicates within the
+        /// batch, are reported with `UserExists` and skipped.
+        #[pallet::weight(T::WeightInfo::enroll_batch(entries.len() as u32))]
+        pub fn enroll_batch_sel33(
+            origin: OriginFor<T>,
+            entries: Vec<EnrollmentOf<T>>,
+        ) -> DispatchResult {
+
+            let institution = T::InstitutionOrigin::ensure_origin(origin)?;
+            ensure!(entries.len() as u32 <= T::MaxEnrollmentBatch::get(), Error::<T>::BatchTooLarge);
+
+            let (mut enrolled, mut failed) = (0u32, 0u32);
+            for (email_hash, referral_hash, additional) in entries {
+                if <Identity1Of<T>>::contains_key(&email_hash) {
+                    failed += 1;
+                    Self::deposit_event(Event::UserExists { email_hash, who: institution.clone() });
+                    continue
+                }
+
+                let mut reg = Self::new_registration(institution.clone(), &[]);
+                reg.info.password_hash = Data::None;
+                reg.info.referral_hash = Data::Sha256(referral_hash);
+                reg.info.additional = additional;
+                <Identity1Of<T>>::insert(&email_hash, reg);
+                <Roles<T>>::insert(&email_hash, Role::Student);
+
+                enrolled += 1;
+                Self::deposit_event(Event::StudentEnrolled { email_hash, institution: institution.clone() });
+            }
+
+            Self::deposit_event(Event::BatchEnrolled { institution, enrolled, failed });
+
+            Ok(())
+        }
+
+        /// Store `field` encrypted to the identity's own key, whose fingerprint must be passed
+        /// as `recipient_fingerprint` and match the one set with `set_pgp_fingerprint_sel40`,
+        /// and clear its plaintext copy. Existing grants are kept: viewers keep the value they
+        /// were given until it is granted again or revoked. Called by a linked account or the
+        /// identity owner.
+        #[pallet::weight(1_000)]
+        pub fn set_encrypted_field_sel34(
+            origin: OriginFor<T>,
+            email_hash: EmailHash,
+            field: ProfileField,
+            recipient_fingerprint: [u8; 20],
+            ciphertext: Vec<u8>,
+        ) -> DispatchResult {
+
+            let sender = ensure_signed(origin)?;
+
+            Self::ensure_linked_or_owner(&email_hash, &sender)?;
+            let own_fingerprint = <Identity1Of<T>>::get(&email_hash)
+                .and_then(|id| id.info.pgp_fingerprint)
+                .ok_or(Error::<T>::NoPgpFingerprint)?;
+            ensure!(recipient_fingerprint == own_fingerprint, Error::<T>::FingerprintMismatch);
+            let payload = Self::encrypted_payload(recipient_fingerprint, ciphertext)?;
+
+            <Identity1Of<T>>::mutate(&email_hash, |id| {
+                if let Some(id) = id {
+                    match field {
+                        ProfileField::Display => id.info.display = Data::None,
+                        ProfileField::Legal => id.info.legal = Data::None,
+                        ProfileField::Image => id.info.image = Data::None,
+                        ProfileField::Email => (),
+                    }
+                }
+            });
+            <EncryptedFields<T>>::insert(&email_hash, field, payload);
+
+            Self::deposit_event(Event::EncryptedFieldSet { email_hash, field, recipient_fingerprint });
+
+            Ok(())
+        }
+
+        /// Remove an encrypted profile field together with every copy granted to viewers.
+        /// Called by a linked account or the identity owner.
+        #[pallet::weight(1_000)]
+        pub fn clear_encrypted_field_sel35(
+            origin: OriginFor<T>,
+            email_hash: EmailHash,
+            field: ProfileField,
+        ) -> DispatchResult {
+
+            let sender = ensure_signed(origin)?;
+
+            Self::ensure_linked_or_owner(&email_hash, &sender)?;
+            <EncryptedFields<T>>::take(&email_hash, field).ok_or(Error::<T>::NoEncryptedField)?;
+            let _ = <FieldGrants<T>>::remove_prefix((email_hash, field), None);
+            <FieldGrantCount<T>>::remove(&email_hash, field);
+
+            Self::deposit_event(Event::EncryptedFieldCleared { email_hash, field });
+
+            Ok(())
+        }
+
+        /// Disclose an encrypted profile field to `viewer` by storing a copy re-encrypted
+        /// off-chain to the viewer's key. Granting again replaces the viewer's copy. Called by a
+        /// linked account or the identity owner.
+        #[pallet::weight(1_000)]
+        pub fn grant_field_sel36(
+            origin: OriginFor<T>,
+            email_hash: EmailHash,
+            field: ProfileField,
+            viewer: T::AccountId,
+            viewer_fingerprint: [u8; 20],
+            ciphertext: Vec<u8>,
+        ) -> DispatchResult {
+
+            let sender = ensure_signed(origin)?;
+
+            Self::ensure_linked_or_owner(&email_hash, &sender)?;
+            ensure!(<EncryptedFields<T>>::contains_key(&email_hash, field), Error::<T>::NoEncryptedField);
+            let payload = Self::encrypted_payload(viewer_fingerprint, ciphertext)?;
+
+            let key = (email_hash, field, viewer.clone());
+            if !<FieldGrants<T>>::contains_key(&key) {
+                <FieldGrantCount<T>>::try_mutate(&email_hash, field, |count| -> DispatchResult {
+                    ensure!(*count < T::MaxFieldGrants::get(), Error::<T>::TooManyFieldGrants);
+                    *count += 1;
+                    Ok(())
+                })?;
+            }
+            <FieldGrants<T>>::insert(&key, payload);
+
+            Self::deposit_event(Event::FieldGranted { email_hash, field, viewer });
+
+            Ok(())
+        }
+
+        /// Remove `viewer`'s copy of an encrypted profile field. Called by a linked account or
+        /// the identity owner.
+        #[pallet::weight(1_000)]
+        pub fn revoke_field_sel37(
+            origin: OriginFor<T>,
+            email_hash: EmailHash,
+            field: ProfileField,
+            viewer: T::AccountId,
+        ) -> DispatchResult {
+
+            let sender = ensure_signed(origin)?;
+
+            Self::ensure_linked_or_owner(&email_hash, &sender)?;
+            <FieldGrants<T>>::take((email_hash, field, viewer.clone())).ok_or(Error::<T>::NoFieldGrant)?;
+            <FieldGrantCount<T>>::mutate(&email_hash, field, |count| *count = count.saturating_sub(1));
+
+            Self::deposit_event(Event::FieldGrantRevoked { email_hash, field, viewer });
+
+            Ok(())
+        }
+
+        /// Assign `role` to an identity, replacing any role it had. Must be called by
+        /// `InstitutionOrigin`, root or an account acting in the `Admin` role; granting
+        /// `Admin`, or replacing it, needs root or an `Admin` account.
+        #[pallet::weight(1_000)]
+        pub fn set_role_sel38(
+            origin: OriginFor<T>,
+            email_hash: EmailHash,
+            role: Role,
+        ) -> DispatchResult {
+
+            ensure!(<Identity1Of<T>>::contains_key(&email_hash), Error::<T>::NoIdentity);
+            let admin_only = role == Role::Admin || <Roles<T>>::get(&email_hash) == Some(Role::Admin);
+            let by = Self::ensure_role_manager(origin, admin_only)?;
+            <Roles<T>>::insert(&email_hash, role);
+
+            Self::deposit_event(Event::RoleAssigned { email_hash, role, by });
+
+            Ok(())
+        }
+
+        /// Lift a login lockout and forget the failed attempts. Must be called by `ForceOrigin`.
+        #[pallet::weight(1_000)]
+        pub fn unlock_login_sel39(
+            origin: OriginFor<T>,
+            email_hash: EmailHash,
+        ) -> DispatchResult {
+
+            T::ForceOrigin::ensure_origin(origin)?;
+
+            ensure!(<Identity1Of<T>>::contains_key(&email_hash), Error::<T>::NoIdentity);
+            <LoginAttempts<T>>::remove(&email_hash);
+
+            Self::deposit_event(Event::LoginUnlocked { email_hash });
+
+            Ok(())
+        }
+
+        /// Set the fingerprint of the PGP key the identity's encrypted fields are encrypted to,
+        /// or remove it with `None`. Fields already encrypted keep their value until they are set
+        /// again. Called by a linked account or the identity owner.
+        #[pallet::weight(1_000)]
+        pub fn set_pgp_fingerprint_sel40(
+            origin: OriginFor<T>,
+            email_hash: EmailHash,
+            fingerprint: Option<[u8; 20]>,
+        ) -> DispatchResult {
+
+            let sender = ensure_signed(origin)?;
+
+            Self::ensure_linked_or_owner(&email_hash, &sender)?;
+            <Identity1Of<T>>::mutate(&email_hash, |id| {
+                if let Some(id) = id {
+                    id.info.pgp_fingerprint = fingerprint;
+                }
+            });
+
+            Self::deposit_event(Event::PgpFingerprintSet { email_hash, fingerprint });
+
+            Ok(())
+        }
+
+        /// Remove the role of an identity. Called by the same origins as `set_role_sel38`;
+        /// removing `Admin` needs root or an `Admin` account.
+        #[pallet::weight(1_000)]
+        pub fn clear_role_sel41(
+            origin: OriginFor<T>,
+            email_hash: EmailHash,
+        ) -> DispatchResult {
+
+            let role = <Roles<T>>::get(&email_hash).ok_or(Error::<T>::NoRole)?;
+            let by = Self::ensure_role_manager(origin, role == Role::Admin)?;
+            <Roles<T>>::remove(&email_hash);
+
+            Self::deposit_event(Event::RoleCleared { email_hash, by });
+
             Ok(())
         }
 
     }
+
+	impl<T: Config> Pallet<T> {
+		/// Hash an email address into the key its identity is stored under.
+		///
+		/// The address is normalised first (surrounding whitespace trimmed, ASCII lower-cased) so
+		/// `" A@B.com"` and `"a@b.com"` map to the same identity, then hashed together with
+		/// [`EMAIL_HASH_DOMAIN`]. Front ends should call this (or reimplement it) off-chain and
+		/// only ever submit the hash.
+		pub fn email_hash(email: &[u8]) -> EmailHash {
+			let is_space = |c: &u8| c.is_ascii_whitespace();
+			let start = email.iter().position(|c| !is_space(c)).unwrap_or(email.len());
+			let end = email.iter().rposition(|c| !is_space(c)).map_or(start, |i| i + 1);
+			let normalised: Vec<u8> =
+				email[start..end].iter().map(|c| c.to_ascii_lowercase()).collect();
+
+			blake2_256(&(EMAIL_HASH_DOMAIN, normalised).encode())
+		}
+
+		/// The identity registered for a plaintext email address, if any.
+		pub fn identity_by_email(email: &[u8]) -> Option<RegistrationOf<T>> {
+			Self::identity_by_email_hash(&Self::email_hash(email))
+		}
+
+		/// The identity registered under an email hash, if any.
+		pub fn identity_by_email_hash(email_hash: &EmailHash) -> Option<RegistrationOf<T>> {
+			<Identity1Of<T>>::get(email_hash)
+		}
+
+		/// Count a failed password login of `who` on the identity and lock every account out of
+		/// it for `LoginLockoutBase` blocks once it reaches `MaxLoginAttempts`, doubling with each
+		/// further failure.
+		pub(crate) fn note_failed_login(email_hash: EmailHash, who: T::AccountId) {
+			let now = <frame_system::Pallet<T>>::block_number();
+			let (failures, mut locked_until) = <LoginAttempts<T>>::get(&email_hash);
+			let failures = failures.saturating_add(1);
+			if failures >= T::MaxLoginAttempts::get() {
+				let doublings = (failures - T::MaxLoginAttempts::get()).min(16);
+				let lockout = T::LoginLockoutBase::get().saturating_mul((1u32 << doublings).into());
+				locked_until = now.saturating_add(lockout);
+				Self::deposit_event(Event::LoginLockedOut { email_hash, until: locked_until });
+			}
+			<LoginAttempts<T>>::insert(&email_hash, (failures, locked_until));
+			Self::deposit_event(Event::UserLoginfailed { email_hash, who });
+		}
+
+		/// Deposit the event for a failed identity call, see [`failures`].
+		pub(crate) fn note_failure(
+			watched: failures::Watched,
+			email_hash: EmailHash,
+			who: T::AccountId,
+			error: DispatchError,
+		) {
+			use failures::Watched;
+			let is = |e: Error<T>| error == DispatchError::from(e);
+
+			let event = match watched {
+				Watched::Registration if is(Error::<T>::IdentityAlreadyClaimed) =>
+					Event::UserExists { email_hash, who },
+				Watched::Registration => Event::UserRegisterfailed { email_hash, who, error },
+				Watched::Web3Link => Event::UserWeb3registerfailed { email_hash, account: who, error },
+				Watched::Login if is(Error::<T>::LoginFailed) =>
+					return Self::note_failed_login(email_hash, who),
+				Watched::Login if is(Error::<T>::NoIdentity) =>
+					Event::UserDoesnotexist { email_hash, who },
+				Watched::Web3Login if is(Error::<T>::LoginFailed) =>
+					Event::UserWeb3loginfailed { email_hash, account: who },
+				Watched::Web3Login if is(Error::<T>::NoIdentity) =>
+					Event::UserWeb3doesnotexist { email_hash, account: who },
+				// Lockouts and bad origins need no event of their own.
+				Watched::Login | Watched::Web3Login => return,
+			};
+			Self::deposit_event(event);
+		}
+
+		/// Whether any key is recovering the identity.
+		pub fn recovery_in_progress(email_hash: &EmailHash) -> bool {
+			<ActiveRecoveries<T>>::iter_key_prefix(email_hash).next().is_some()
+		}
+
+		/// Whether `who` is one of the web3 accounts linked to the identity.
+		pub fn is_linked(email_hash: &EmailHash, who: &T::AccountId) -> bool {
+			<LinkedAccounts<T>>::get(email_hash).contains(who)
+		}
+
+		/// A fresh registration owned by `owner`, before any account is linked.
+		pub(crate) fn new_registration(owner: T::AccountId, password: &[u8]) -> RegistrationOf<T> {
+			let additional: BoundedVec<_, T::MaxAdditionalFields> = vec![
+				(
+					Data::Raw(b"number".to_vec().try_into().unwrap()),
+					Data::Raw(10u32.encode().try_into().unwrap()),
+				),
+				(
+					Data::Raw(b"text".to_vec().try_into().unwrap()),
+					Data::Raw(b"10".to_vec().try_into().unwrap()),
+				),
+			]
+			.try_into()
+			.unwrap();
+
+			let info = IdentityInfoSel {
+				display: Data::Raw(b"ten".to_vec().try_into().unwrap()),
+				legal: Data::Raw(b"The Right Ordinal Ten, Esq.".to_vec().try_into().unwrap()),
+				image: Data::Raw(b"The Right Ordinal Ten, Esq.".to_vec().try_into().unwrap()),
+				web: Data::Raw(b"The Right Ordinal Ten, Esq.".to_vec().try_into().unwrap()),
+				referral_hash: Data::Raw(b"The Right Ordinal Ten, Esq.".to_vec().try_into().unwrap()),
+				email: Data::None,
+				password_hash: Data::BlakeTwo256(blake2_256(password)),
+				pgp_fingerprint: None,
+				additional,
+			};
+
+			RegistrationSel { owner, info, deposit: Zero::zero() }
+		}
+
+		/// Summary of the identity stored under `email_hash`, for the runtime API.
+		pub fn identity_summary(email_hash: EmailHash) -> Option<IdentitySummary<T::AccountId>> {
+			let id = <Identity1Of<T>>::get(&email_hash)?;
+			let service = match id.info.web {
+				Data::Raw(service) => Some(service.into_inner()),
+				_ => None,
+			};
+			Some(IdentitySummary {
+				email_hash,
+				owner: id.owner,
+				linked_accounts: Self::linked_accounts(&email_hash).into_inner(),
+				service,
+				recovery_configured: <RecoveryConfigs<T>>::contains_key(&email_hash),
+			})
+		}
+
+		/// Summary of the identity `who` is linked to, for the runtime API.
+		pub fn identity_summary_of_account(who: &T::AccountId) -> Option<IdentitySummary<T::AccountId>> {
+			Self::identity_summary(<EmailId<T>>::get(who)?)
+		}
+
+		/// Whether `who` is linked to an identity that has been granted access to `service`.
+		/// The read-only counterpart of `check_web3access_sel18`.
+		pub fn has_access(who: &T::AccountId, service: &[u8]) -> bool {
+			let granted = match Self::identity_of_account(who) {
+				Some((_, id)) => id.info.web,
+				None => return false,
+			};
+			match BoundedVec::try_from(service.to_vec()) {
+				Ok(service) => granted == Data::Raw(service),
+				Err(_) => false,
+			}
+		}
+
+		/// The fee-less transaction slot `who` would use next: the current era and how many
+		/// fee-less transactions `who` already sent in it. `None` unless `who` is linked to an
+		/// identity an institution enrolled or assigned a role to, and has fee-less transactions
+		/// left this era.
+		pub fn next_feeless_slot(who: &T::AccountId) -> Option<(T::BlockNumber, u32)> {
+			let email_hash = <EmailId<T>>::get(who)?;
+			if !<Roles<T>>::contains_key(&email_hash) {
+				return None
+			}
+			let era_length = T::FeelessEraLength::get();
+			if era_length.is_zero() {
+				return None
+			}
+			let era = <frame_system::Pallet<T>>::block_number() / era_length;
+			let (last_era, used) = <FeelessUsage<T>>::get(who);
+			let used = if last_era == era { used } else { 0 };
+			(used < T::FeelessTxPerEra::get()).then(|| (era, used))
+		}
+
+		/// Whether `who` may send a fee-less transaction now, see [`Self::next_feeless_slot`].
+		/// With `consume`, the slot is used up.
+		pub fn use_feeless_tx(who: &T::AccountId, consume: bool) -> bool {
+			match Self::next_feeless_slot(who) {
+				Some((era, used)) => {
+					if consume {
+						<FeelessUsage<T>>::insert(who, (era, used + 1));
+					}
+					true
+				},
+				None => false,
+			}
+		}
+
+		/// Whether `token_id` is a known access token owned by `who`.
+		pub fn validate_token(token_id: &[u8], who: &T::AccountId) -> bool {
+			TokenId::<T>::try_from(token_id.to_vec())
+				.ok()
+				.and_then(<Tokens<T>>::get)
+				.map_or(false, |token| &token.owner == who)
+		}
+
+		/// Everything needed to render the DID document of the identity under `email_hash`, for
+		/// the runtime API. Identities without an on-chain document still resolve, listing only
+		/// their linked accounts and services.
+		pub fn did_details(email_hash: EmailHash) -> Option<DidDetails<T::AccountId>> {
+			let id = <Identity1Of<T>>::get(&email_hash)?;
+			let doc = <DidDocuments<T>>::get(&email_hash);
+			let services = match id.info.web {
+				Data::Raw(service) => vec![service.into_inner()],
+				_ => Vec::new(),
+			};
+			Some(DidDetails {
+				email_hash,
+				controller: doc.as_ref().map(|doc| doc.controller.clone()),
+				verification_methods: doc.map_or_else(Vec::new, |doc| doc.verification_methods.into_inner()),
+				linked_accounts: Self::linked_accounts(&email_hash).into_inner(),
+				services,
+			})
+		}
+
+		fn encrypted_payload(
+			recipient_fingerprint: [u8; 20],
+			ciphertext: Vec<u8>,
+		) -> Result<EncryptedPayloadOf<T>, DispatchError> {
+			let ciphertext = ciphertext.try_into().map_err(|_| Error::<T>::CiphertextTooLong)?;
+			Ok(EncryptedPayload { recipient_fingerprint, ciphertext })
+		}
+
+		/// Apply `f` to the DID document of `email_hash` if `who` is its controller and still
+		/// linked to the identity.
+		fn mutate_did_as_controller(
+			email_hash: &EmailHash,
+			who: &T::AccountId,
+			f: impl FnOnce(&mut DidDocumentOf<T>) -> DispatchResult,
+		) -> DispatchResult {
+			Self::ensure_linked_or_owner(email_hash, who)?;
+			<DidDocuments<T>>::try_mutate(email_hash, |doc| {
+				let doc = doc.as_mut().ok_or(Error::<T>::NoDid)?;
+				ensure!(&doc.controller == who, Error::<T>::NotDidController);
+				f(doc)?;
+				doc.updated = <frame_system::Pallet<T>>::block_number();
+				Ok(())
+			})
+		}
+
+		/// Record the `referrer` -> `referred` edge and owe the referral reward to
+		/// `referrer_account`, unless `referred` was already referred or the referrer filled its
+		/// cap in the meantime.
+		fn record_referral(referrer: EmailHash, referrer_account: T::AccountId, referred: EmailHash) {
+			if <ReferredBy<T>>::contains_key(&referred) ||
+				Self::referral_count(&referrer) >= T::MaxReferralsPerReferrer::get()
+			{
+				return
+			}
+
+			<Referrals<T>>::insert(&referrer, &referred, <frame_system::Pallet<T>>::block_number());
+			<ReferredBy<T>>::insert(&referred, &referrer);
+			<ReferralCount<T>>::mutate(&referrer, |count| *count = count.saturating_add(1));
+
+			if !T::ReferralReward::get().is_zero() {
+				<UnpaidReferralRewards<T>>::insert(&referred, referrer_account);
+			}
+
+			Self::deposit_event(Event::ReferralRecorded { referrer, referred });
+		}
+
+		/// Ensure `origin` may change roles, yielding the account that did (`None` for root).
+		/// Root and accounts acting in the `Admin` role always may; `InstitutionOrigin` only
+		/// unless `admin_only`.
+		fn ensure_role_manager(
+			origin: OriginFor<T>,
+			admin_only: bool,
+		) -> Result<Option<T::AccountId>, DispatchError> {
+			let origin = if admin_only {
+				origin
+			} else {
+				match T::InstitutionOrigin::try_origin(origin) {
+					Ok(who) => return Ok(Some(who)),
+					Err(origin) => origin,
+				}
+			};
+			match EnsureStudentRole::<T, roles::Admin>::try_origin(origin) {
+				Ok(who) => Ok(Some(who)),
+				Err(origin) => ensure_root(origin).map(|_| None).map_err(Into::into),
+			}
+		}
+
+		/// The account referral rewards are paid from.
+		pub fn referral_pot() -> T::AccountId {
+			T::PalletId::get().into_account()
+		}
+
+		/// Pay the reward owed for referring `referred` out of the referral pot. While the pot
+		/// cannot cover it the reward stays owed, and is paid the next time an account is
+		/// linked to `referred`.
+		fn pay_referral_reward(referred: &EmailHash) {
+			let account = match <UnpaidReferralRewards<T>>::get(referred) {
+				Some(account) => account,
+				None => return,
+			};
+			let reward = T::ReferralReward::get();
+			let paid = T::Currency::transfer(
+				&Self::referral_pot(),
+				&account,
+				reward,
+				ExistenceRequirement::KeepAlive,
+			);
+			if paid.is_ok() {
+				<UnpaidReferralRewards<T>>::remove(referred);
+				Self::deposit_event(Event::ReferralRewardPaid { referred: *referred, account, reward });
+			}
+		}
+
+		/// Ensure `who` is linked to the identity or owns it.
+		fn ensure_linked_or_owner(email_hash: &EmailHash, who: &T::AccountId) -> DispatchResult {
+			let id = <Identity1Of<T>>::get(email_hash).ok_or(Error::<T>::NoIdentity)?;
+			ensure!(
+				*who == id.owner || Self::is_linked(email_hash, who),
+				Error::<T>::NotLinkedOrOwner
+			);
+			Ok(())
+		}
+
+		/// The identity an account has been linked to, together with its email hash.
+		pub fn identity_of_account(who: &T::AccountId) -> Option<(EmailHash, RegistrationOf<T>)> {
+			let email_hash = <EmailId<T>>::get(who)?;
+			<Identity1Of<T>>::get(&email_hash).map(|reg| (email_hash, reg))
+		}
+	}
 }
 
+impl<T: Config> IdentityProvider<T::AccountId> for Pallet<T> {
+	fn identity_of(who: &T::AccountId) -> Option<EmailHash> {
+		Self::identity_of_account(who).map(|(email_hash, _)| email_hash)
+	}
+
+	fn role_of(who: &T::AccountId) -> Option<Role> {
+		Self::identity_of(who).and_then(|email_hash| <Roles<T>>::get(email_hash))
+	}
+
+	fn has_access(who: &T::AccountId, service: &[u8]) -> bool {
+		Self::has_access(who, service)
+	}
+}
+
+/// Ensure the origin is signed by a registrar, yielding its account.
+pub struct EnsureRegistrar<T>(sp_std::marker::PhantomData<T>);
+
+impl<T: Config> EnsureOrigin<T::Origin> for EnsureRegistrar<T> {
+	type Success = T::AccountId;
+
+	fn try_origin(o: T::Origin) -> Result<Self::Success, T::Origin> {
+		o.into().and_then(|o| match o {
+			frame_system::RawOrigin::Signed(who) if <Registrars<T>>::get().contains(&who) => Ok(who),
+			r => Err(T::Origin::from(r)),
+		})
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn successful_origin() -> T::Origin {
+		let who: T::AccountId = frame_benchmarking::whitelisted_caller();
+		<Registrars<T>>::mutate(|registrars| {
+			if !registrars.contains(&who) {
+				let _ = registrars.try_push(who.clone());
+			}
+		});
+		frame_system::RawOrigin::Signed(who).into()
+	}
+}
+
+/// Ensure the origin is signed by an account linked to an identity with role `R`, yielding the
+/// account. Lets other pallets gate calls by role through a plain `EnsureOrigin` in their
+/// `Config`, e.g. `EnsureStudentRole<Runtime, roles::Faculty>`.
+pub struct EnsureStudentRole<T, R>(sp_std::marker::PhantomData<(T, R)>);
+
+impl<T: Config, R: Get<Role>> EnsureOrigin<T::Origin> for EnsureStudentRole<T, R> {
+	type Success = T::AccountId;
+
+	fn try_origin(o: T::Origin) -> Result<Self::Success, T::Origin> {
+		o.into().and_then(|o| match o {
+			frame_system::RawOrigin::Signed(who)
+				if <EmailId<T>>::get(&who).and_then(|email_hash| <Roles<T>>::get(email_hash)) == Some(R::get()) =>
+				Ok(who),
+			r => Err(T::Origin::from(r)),
+		})
+	}
+
+	#[cfg(feature = "runtime-benchmarks")]
+	fn successful_origin() -> T::Origin {
+		let who: T::AccountId = frame_benchmarking::whitelisted_caller();
+		let email_hash = Pallet::<T>::email_hash(b"benchmark@studentid");
+		<EmailId<T>>::insert(&who, email_hash);
+		<Roles<T>>::insert(email_hash, R::get());
+		frame_system::RawOrigin::Signed(who).into()
+	}
+}
+
+/// Role markers for [`EnsureStudentRole`].
+pub mod roles {
+	use super::Role;
+	use frame_support::traits::Get;
+
+	macro_rules! role_marker {
+		($($name:ident),*) => {$(
+			#[doc = concat!("[`Role::", stringify!($name), "`] as a type.")]
+			pub struct $name;
+			impl Get<Role> for $name {
+				fn get() -> Role {
+					Role::$name
+				}
+			}
+		)*};
+	}
+
+	role_marker!(Student, Faculty, Staff, Admin);
+}
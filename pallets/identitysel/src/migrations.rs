@@ -0,0 +1,321 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2019-2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Storage migrations for the student identity pallet.
+//!
+//! Every layout change bumps [`STORAGE_VERSION`] and adds a `vN` module here. [`migrate`] runs, in
+//! order, the steps the on-chain version has not seen yet and is called from the pallet's
+//! `on_runtime_upgrade`, so a runtime upgrade only has to ship the new code. With the
+//! `try-runtime` feature, [`pre_upgrade`] and [`post_upgrade`] check that no identity is lost or
+//! left undecodable on the way.
+
+use super::*;
+use frame_support::{
+	migration::{put_storage_value, storage_key_iter},
+	traits::{Get, PalletInfoAccess, StorageVersion},
+	weights::Weight,
+	StorageHasher, Twox64Concat,
+};
+#[cfg(feature = "try-runtime")]
+use frame_support::{migration::storage_iter, traits::OnRuntimeUpgradeHelpersExt};
+
+/// The migration steps, by the version they migrate to.
+fn steps<T: Config>() -> [(u16, fn() -> Weight); 6] {
+	[
+		(1, v1::migrate::<T>),
+		(2, v2::migrate::<T>),
+		(3, v3::migrate::<T>),
+		(4, v4::migrate::<T>),
+		(5, v5::migrate::<T>),
+		(6, v6::migrate::<T>),
+	]
+}
+
+/// Bring storage from the on-chain version up to [`STORAGE_VERSION`].
+pub fn migrate<T: Config>() -> Weight {
+	let on_chain = StorageVersion::get::<Pallet<T>>();
+	let mut weight = T::DbWeight::get().reads(1);
+
+	for (version, step) in steps::<T>() {
+		if on_chain < version {
+			log::info!(target: "runtime::studentid", "migrating storage to v{}", version);
+			weight = weight.saturating_add(step());
+			StorageVersion::new(version).put::<Pallet<T>>();
+			weight = weight.saturating_add(T::DbWeight::get().writes(1));
+		}
+	}
+
+	weight
+}
+
+/// Number of entries in the map `item` of this pallet, whatever the layout of their values.
+#[cfg(feature = "try-runtime")]
+fn count_entries<T: Config>(item: &[u8]) -> u32 {
+	let pallet_name = <Pallet<T> as PalletInfoAccess>::name().as_bytes();
+	storage_iter::<()>(pallet_name, item).count() as u32
+}
+
+#[cfg(feature = "try-runtime")]
+pub fn pre_upgrade<T: Config>() -> Result<(), &'static str> {
+	Pallet::<T>::set_temp_storage(count_entries::<T>(b"Identity1Of"), "identities");
+	Ok(())
+}
+
+#[cfg(feature = "try-runtime")]
+pub fn post_upgrade<T: Config>() -> Result<(), &'static str> {
+	if StorageVersion::get::<Pallet<T>>() != STORAGE_VERSION {
+		return Err("storage version was not bumped")
+	}
+
+	let before: u32 = Pallet::<T>::get_temp_storage("identities").ok_or("no pre_upgrade count")?;
+	if count_entries::<T>(b"Identity1Of") != before {
+		return Err("identities were lost or duplicated")
+	}
+	if <Identity1Of<T>>::iter_values().count() as u32 != before {
+		return Err("identities do not decode with the current layout")
+	}
+
+	for (email_hash, linked) in <LinkedAccounts<T>>::iter() {
+		if linked.iter().any(|account| <EmailId<T>>::get(account) != Some(email_hash)) {
+			return Err("LinkedAccounts and EmailId disagree")
+		}
+	}
+
+	Ok(())
+}
+
+/// Move from plaintext email keys to [`EmailHash`] keys.
+pub mod v1 {
+	use super::*;
+
+	/// Rekeys every `Identity1Of` entry by [`Pallet::email_hash`] of its old plaintext key,
+	/// rewrites `EmailId` to point at the hash and clears the plaintext copy held in the
+	/// identity's `email` field.
+	///
+	/// Does nothing once the on-chain storage version is 1 or later, so it cannot rehash
+	/// already hashed keys even when called outside [`migrate`](super::migrate).
+	pub fn migrate<T: Config>() -> Weight {
+		if StorageVersion::get::<Pallet<T>>() >= 1 {
+			return T::DbWeight::get().reads(1)
+		}
+		let pallet_name = <Pallet<T> as PalletInfoAccess>::name().as_bytes();
+
+		// Drain first: old and new entries share the `Identity1Of` prefix.
+		let old_identities: Vec<(Email<T>, v3::OldRegistrationOf<T>)> =
+			storage_key_iter::<Email<T>, v3::OldRegistrationOf<T>, Twox64Concat>(
+				pallet_name,
+				b"Identity1Of",
+			)
+			.drain()
+			.collect();
+
+		let identities = old_identities.len() as Weight;
+		for (email, mut reg) in old_identities {
+			reg.info.email = Data::None;
+			let key = Twox64Concat::hash(&Pallet::<T>::email_hash(&email));
+			put_storage_value(pallet_name, b"Identity1Of", &key, reg);
+		}
+
+		let mut links: Weight = 0;
+		<EmailId<T>>::translate::<Email<T>, _>(|_, email| {
+			links += 1;
+			Some(Pallet::<T>::email_hash(&email))
+		});
+
+		T::DbWeight::get().reads_writes(identities + links, identities * 2 + links)
+	}
+}
+
+/// Seed `LinkedAccounts` from the single account each identity could link before.
+pub mod v2 {
+	use super::*;
+
+	/// Pushes every account found in `EmailId` into the `LinkedAccounts` set of the identity it
+	/// points at. Accounts beyond `MaxLinkedAccounts`, or pointing at a missing identity, lose
+	/// their `EmailId` entry so the two maps stay consistent.
+	pub fn migrate<T: Config>() -> Weight {
+		let links: Vec<(T::AccountId, EmailHash)> = <EmailId<T>>::iter().collect();
+		let count = links.len() as Weight;
+
+		for (account, email_hash) in links {
+			let linked = <Identity1Of<T>>::contains_key(&email_hash) &&
+				<LinkedAccounts<T>>::try_mutate(&email_hash, |linked| {
+					linked.try_push(account.clone())
+				})
+				.is_ok();
+			if !linked {
+				<EmailId<T>>::remove(&account);
+			}
+		}
+
+		T::DbWeight::get().reads_writes(count * 3, count)
+	}
+}
+
+/// Clean up the registration layout: give `IdentityInfoSel` fields their real names, drop the
+/// unused `account` placeholder and rename `RegistrationSel::accountId` to `owner`.
+pub mod v3 {
+	use super::*;
+
+	/// `IdentityInfoSel` up to storage version 2.
+	#[derive(Encode, Decode)]
+	pub struct OldIdentityInfo<FieldLimit: Get<u32>> {
+		pub additional: BoundedVec<(Data, Data), FieldLimit>,
+		pub display: Data,
+		pub legal: Data,
+		pub web: Data,
+		pub referalhash: Data,
+		pub email: Data,
+		pub pgp_fingerprint: Option<[u8; 20]>,
+		pub account: Data,
+		pub image: Data,
+		pub passwordhash: Data,
+	}
+
+	/// `RegistrationSel` up to storage version 2.
+	#[derive(Encode)]
+	pub struct OldRegistration<Balance: Encode + Decode, AccountId: Encode + Decode, FieldLimit: Get<u32>> {
+		pub deposit: Balance,
+		pub account_id: AccountId,
+		pub info: OldIdentityInfo<FieldLimit>,
+	}
+
+	/// The old layout was decoded leniently, padding truncated values with zeros.
+	impl<Balance: Encode + Decode, AccountId: Encode + Decode, FieldLimit: Get<u32>> Decode
+		for OldRegistration<Balance, AccountId, FieldLimit>
+	{
+		fn decode<I: codec::Input>(input: &mut I) -> sp_std::result::Result<Self, codec::Error> {
+			let (deposit, account_id, info) = Decode::decode(&mut AppendZerosInput::new(input))?;
+			Ok(Self { deposit, account_id, info })
+		}
+	}
+
+	pub type OldRegistrationOf<T> = OldRegistration<
+		BalanceOf<T>,
+		<T as frame_system::Config>::AccountId,
+		<T as Config>::MaxAdditionalFields,
+	>;
+
+	pub fn migrate<T: Config>() -> Weight {
+		let mut count: Weight = 0;
+		<Identity1Of<T>>::translate::<OldRegistrationOf<T>, _>(|_, old| {
+			count += 1;
+			let info = old.info;
+			Some(RegistrationSel {
+				deposit: old.deposit,
+				owner: old.account_id,
+				info: IdentityInfoSel {
+					additional: info.additional,
+					display: info.display,
+					legal: info.legal,
+					web: info.web,
+					referral_hash: info.referalhash,
+					email: info.email,
+					pgp_fingerprint: info.pgp_fingerprint,
+					image: info.image,
+					password_hash: info.passwordhash,
+				},
+			})
+		});
+
+		T::DbWeight::get().reads_writes(count, count)
+	}
+}
+
+/// Key `ActiveRecoveries` by identity and new key, so a recovery no longer blocks other keys
+/// from recovering the same identity.
+pub mod v4 {
+	use super::*;
+
+	/// `ActiveRecovery` up to storage version 3, keyed by identity alone.
+	#[derive(Encode, Decode)]
+	pub struct OldActiveRecovery<AccountId, BlockNumber, MaxGuardians: Get<u32>> {
+		pub new_account: AccountId,
+		pub started: BlockNumber,
+		pub vouchers: BoundedVec<AccountId, MaxGuardians>,
+	}
+
+	pub type OldActiveRecoveryOf<T> = OldActiveRecovery<
+		<T as frame_system::Config>::AccountId,
+		<T as frame_system::Config>::BlockNumber,
+		<T as Config>::MaxGuardians,
+	>;
+
+	/// Moves every recovery in progress under its new key. Nothing was reserved for them, so
+	/// their deposit is zero.
+	pub fn migrate<T: Config>() -> Weight {
+		let pallet_name = <Pallet<T> as PalletInfoAccess>::name().as_bytes();
+
+		// Drain first: old and new entries share the `ActiveRecoveries` prefix.
+		let old: Vec<(EmailHash, OldActiveRecoveryOf<T>)> =
+			storage_key_iter::<EmailHash, OldActiveRecoveryOf<T>, Twox64Concat>(
+				pallet_name,
+				b"ActiveRecoveries",
+			)
+			.drain()
+			.collect();
+
+		let count = old.len() as Weight;
+		for (email_hash, recovery) in old {
+			let new = ActiveRecovery {
+				started: recovery.started,
+				deposit: Zero::zero(),
+				vouchers: recovery.vouchers,
+			};
+			<ActiveRecoveries<T>>::insert(&email_hash, &recovery.new_account, new);
+		}
+
+		T::DbWeight::get().reads_writes(count, count * 2)
+	}
+}
+
+/// Count failed logins per identity and caller instead of per identity.
+pub mod v5 {
+	use super::*;
+	use frame_support::migration::remove_storage_prefix;
+
+	/// Drops every per-identity `LoginAttempts` entry. They only hold short-lived lockouts, which
+	/// cannot be attributed to a caller, so every identity starts over unlocked.
+	pub fn migrate<T: Config>() -> Weight {
+		let pallet_name = <Pallet<T> as PalletInfoAccess>::name().as_bytes();
+		let count = storage_key_iter::<EmailHash, (u32, T::BlockNumber), Twox64Concat>(
+			pallet_name,
+			b"LoginAttempts",
+		)
+		.count() as Weight;
+		remove_storage_prefix(pallet_name, b"LoginAttempts", &[]);
+
+		T::DbWeight::get().reads_writes(count, count)
+	}
+}
+
+/// Count failed logins per identity again, whichever account sent them.
+pub mod v6 {
+	use super::*;
+	use frame_support::migration::{remove_storage_prefix, storage_iter};
+
+	/// Drops every per-caller `LoginAttempts` entry. A caller's count says nothing about how
+	/// often the identity as a whole was guessed at, so every identity starts over unlocked.
+	pub fn migrate<T: Config>() -> Weight {
+		let pallet_name = <Pallet<T> as PalletInfoAccess>::name().as_bytes();
+		let count =
+			storage_iter::<(u32, T::BlockNumber)>(pallet_name, b"LoginAttempts").count() as Weight;
+		remove_storage_prefix(pallet_name, b"LoginAttempts", &[]);
+
+		T::DbWeight::get().reads_writes(count, count)
+	}
+}
@@ -23,6 +23,7 @@ use crate as pallet_studentid;
 use codec::{Decode, Encode};
 use frame_support::{
 	assert_noop, assert_ok, ord_parameter_types, parameter_types,  BoundedVec,
+	traits::{GenesisBuild, StorageVersion}, StorageHasher, Twox64Concat,
 };
 use frame_system::{EnsureRoot, EnsureSignedBy, EnsureOneOf};
 use sp_core::H256;
@@ -44,6 +45,7 @@ frame_support::construct_runtime!(
 		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
 		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
 		Identity: pallet_studentid::{Pallet, Call, Storage, Event<T>},
+		TransactionPayment: pallet_transaction_payment::{Pallet, Storage},
 	}
 );
 
@@ -91,12 +93,36 @@ impl pallet_balances::Config for Test {
 	type ReserveIdentifier = [u8; 8];
 	type WeightInfo = ();
 }
+parameter_types! {
+	pub const TransactionByteFee: u64 = 1;
+}
+impl pallet_transaction_payment::Config for Test {
+	type OnChargeTransaction = pallet_transaction_payment::CurrencyAdapter<Balances, ()>;
+	type TransactionByteFee = TransactionByteFee;
+	type WeightToFee = frame_support::weights::IdentityFee<u64>;
+	type FeeMultiplierUpdate = ();
+}
 parameter_types! {
 	pub const BasicDeposit: u64 = 10;
 	pub const FieldDeposit: u64 = 10;
 	pub const SubAccountDeposit: u64 = 10;
 	pub const MaxSubAccounts: u32 = 2;
 	pub const MaxUseridentities: u32 = 2;
+	pub const MaxLinkedAccounts: u32 = 2;
+	pub const MaxGuardians: u32 = 3;
+	pub const RecoveryDelay: u64 = 10;
+	pub const RecoveryDeposit: u64 = 5;
+	pub const MaxReferralsPerReferrer: u32 = 1;
+	pub const ReferralReward: u64 = 5;
+	pub const StudentIdPalletId: PalletId = PalletId(*b"py/stdid");
+	pub const MaxDidKeys: u32 = 2;
+	pub const MaxEnrollmentBatch: u32 = 3;
+	pub const MaxCiphertextLen: u32 = 16;
+	pub const MaxFieldGrants: u32 = 1;
+	pub const FeelessTxPerEra: u32 = 2;
+	pub const FeelessEraLength: u64 = 10;
+	pub const MaxLoginAttempts: u32 = 3;
+	pub const LoginLockoutBase: u64 = 10;
 	pub const MaxAdditionalFields: u32 = 2;
 	pub const MaxRegistrars: u32 = 20;
 	pub const MaxEmailsize: u32 = 30;
@@ -120,6 +146,23 @@ impl pallet_studentid::Config for Test {
 	type SubAccountDeposit = SubAccountDeposit;
 	type MaxSubAccounts = MaxSubAccounts;
 	type MaxUseridentities = MaxUseridentities;
+	type MaxLinkedAccounts = MaxLinkedAccounts;
+	type MaxGuardians = MaxGuardians;
+	type RecoveryDelay = RecoveryDelay;
+	type RecoveryDeposit = RecoveryDeposit;
+	type MaxReferralsPerReferrer = MaxReferralsPerReferrer;
+	type ReferralReward = ReferralReward;
+	type PalletId = StudentIdPalletId;
+	type MaxDidKeys = MaxDidKeys;
+	type InstitutionOrigin = EnsureRegistrar<Test>;
+	type MaxEnrollmentBatch = MaxEnrollmentBatch;
+	type MaxCiphertextLen = MaxCiphertextLen;
+	type MaxFieldGrants = MaxFieldGrants;
+	type FeelessTxPerEra = FeelessTxPerEra;
+	type FeelessEraLength = FeelessEraLength;
+	type MaxLoginAttempts = MaxLoginAttempts;
+	type LoginLockoutBase = LoginLockoutBase;
+	type WeightInfo = ();
 	type MaxAdditionalFields = MaxAdditionalFields;
 	type MaxRegistrars = MaxRegistrars;
 	type MaxEmailsize = MaxEmailsize;
@@ -144,53 +187,154 @@ pub fn new_test_ext() -> sp_io::TestExternalities {
 #[test]
 fn userregistration_should_work_sela() {
 	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
 		let data = |x| Data::Raw(vec![x; 1].try_into().unwrap());
-        let user: Vec<u8> = b"a@b.com".to_vec().try_into().unwrap();
+        let user = Identity::email_hash(b"a@b.com");
         let pass: Vec<u8> = b"hello123".to_vec().try_into().unwrap();
         let wrongpass: Vec<u8> = b"xxhello123".to_vec().try_into().unwrap();
-        let wronguser: Vec<u8> = b"a@wrong.com".to_vec().try_into().unwrap();
-		assert_ok!(Identity::request_registration_sel11(Origin::signed(10),user.clone(), pass.clone()  ) );
-		assert_ok!(Identity::login_access_sel12(Origin::signed(10),user.clone(), pass.clone()  ) );
+        let wronguser = Identity::email_hash(b"a@wrong.com");
+		assert_ok!(Identity::request_registration_sel11(Origin::signed(10),user, pass.clone()  ) );
+		assert_ok!(Identity::login_access_sel12(Origin::signed(10),user, pass.clone()  ) );
 		assert_noop!(Identity::login_access_sel12(Origin::signed(10), wronguser, pass.clone()), Error::<Test>::NoIdentity);
-		assert_noop!(Identity::login_access_sel12(Origin::signed(10), user.clone(), wrongpass), Error::<Test>::LoginFailed);
+		assert_noop!(Identity::login_access_sel12(Origin::signed(10), user, wrongpass), Error::<Test>::LoginFailed);
 
 	});
 }
 
 
+/// Dispatch `call` from `who` through `failures::RecordFailures`, like the runtime does.
+fn dispatch_recorded(who: u64, call: crate::Call<Test>) -> DispatchResult {
+	use frame_support::weights::GetDispatchInfo;
+	use sp_runtime::traits::{Dispatchable, SignedExtension};
+
+	let call = Call::Identity(call);
+	let info = call.get_dispatch_info();
+	let pre = failures::RecordFailures::<Test>::new().pre_dispatch(&who, &call, &info, 0).unwrap();
+	let result = call.dispatch(Origin::signed(who)).map(|_| ()).map_err(|e| e.error);
+	assert_ok!(failures::RecordFailures::<Test>::post_dispatch(pre, &info, &Default::default(), 0, &result));
+	result
+}
+
+fn login(who: u64, email_hash: EmailHash, password: &[u8]) -> DispatchResult {
+	dispatch_recorded(who, crate::Call::login_access_sel12 { email_hash, password: password.to_vec() })
+}
+
+#[test]
+fn login_lockout_sela() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let user = Identity::email_hash(b"a@b.com");
+		let pass = b"hello123".to_vec();
+		let wrongpass = b"xxhello123".to_vec();
+		assert_ok!(Identity::request_registration_sel11(Origin::signed(10), user, pass.clone()));
+
+		// A failure is still an error, and is counted after dispatch.
+		assert_eq!(login(1, user, &wrongpass), Err(Error::<Test>::LoginFailed.into()));
+		System::assert_last_event(Event::Identity(crate::Event::UserLoginfailed { email_hash: user, who: 1 }));
+		assert_eq!(Identity::login_attempts(user), (1, 0));
+		// A success resets the count.
+		assert_ok!(login(1, user, &pass));
+		assert_eq!(Identity::login_attempts(user), (0, 0));
+
+		// Failures count against the identity, whichever account sends them.
+		for who in 1..=3 {
+			assert_eq!(login(who, user, &wrongpass), Err(Error::<Test>::LoginFailed.into()));
+		}
+		System::assert_has_event(Event::Identity(crate::Event::LoginLockedOut { email_hash: user, until: 11 }));
+		assert_eq!(login(4, user, &pass), Err(Error::<Test>::LoginLockedOut.into()));
+		assert_eq!(login(10, user, &pass), Err(Error::<Test>::LoginLockedOut.into()));
+
+		// Failing again after the lockout doubles it.
+		System::set_block_number(11);
+		assert_eq!(login(5, user, &wrongpass), Err(Error::<Test>::LoginFailed.into()));
+		assert_eq!(Identity::login_attempts(user), (4, 31));
+
+		assert_noop!(Identity::unlock_login_sel39(Origin::signed(10), user), BadOrigin);
+		assert_ok!(Identity::unlock_login_sel39(Origin::root(), user));
+		System::assert_last_event(Event::Identity(crate::Event::LoginUnlocked { email_hash: user }));
+		assert_eq!(Identity::login_attempts(user), (0, 0));
+		assert_ok!(login(1, user, &pass));
+	});
+}
+
 #[test]
 fn changepassword_should_work_sela() {
 	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
 		let data = |x| Data::Raw(vec![x; 1].try_into().unwrap());
-        let user: Vec<u8> = b"a@b.com".to_vec().try_into().unwrap();
+        let user = Identity::email_hash(b"a@b.com");
         let pass: Vec<u8> = b"hello123".to_vec().try_into().unwrap();
         let changedpass: Vec<u8> = b"welcome123".to_vec().try_into().unwrap();
         let wrongpass: Vec<u8> = b"xxhello123".to_vec().try_into().unwrap();
-        let wronguser: Vec<u8> = b"a@wrong.com".to_vec().try_into().unwrap();
-		assert_ok!(Identity::request_registration_sel11(Origin::signed(10),user.clone(), pass.clone()  ) );
-		assert_ok!(Identity::login_access_sel12(Origin::signed(10),user.clone(), pass.clone()  ) );
-		assert_ok!(Identity::change_password_sel13(Origin::signed(10),user.clone(), changedpass.clone()  ) );
-		assert_noop!(Identity::login_access_sel12(Origin::signed(10), user.clone(), pass), Error::<Test>::LoginFailed);
-		assert_ok!(Identity::login_access_sel12(Origin::signed(10),user.clone(), changedpass.clone()  ) );
+        let wronguser = Identity::email_hash(b"a@wrong.com");
+		assert_ok!(Identity::request_registration_sel11(Origin::signed(10),user, pass.clone()  ) );
+		assert_ok!(Identity::login_access_sel12(Origin::signed(10),user, pass.clone()  ) );
+		assert_ok!(Identity::change_password_sel13(Origin::signed(10),user, changedpass.clone()  ) );
+		assert_noop!(Identity::login_access_sel12(Origin::signed(10), user, pass), Error::<Test>::LoginFailed);
+		assert_ok!(Identity::login_access_sel12(Origin::signed(10),user, changedpass.clone()  ) );
 
 
 	});
 }
 
+#[test]
+fn strangers_cannot_take_over_an_identity_sela() {
+	new_test_ext().execute_with(|| {
+		let user = register_and_link(b"a@b.com", 1);
+		let stranger = 5;
+		let before = Identity::identity1(user).unwrap();
+
+		assert_noop!(
+			Identity::change_password_sel13(Origin::signed(stranger), user, b"mine".to_vec()),
+			Error::<Test>::NotLinkedOrOwner
+		);
+		assert_noop!(
+			Identity::set_referal_sel12(Origin::signed(stranger), user, b"referMINE".to_vec()),
+			Error::<Test>::NotLinkedOrOwner
+		);
+		assert_noop!(
+			Identity::create_web3link_sel15(Origin::signed(stranger), user, stranger, b"referMINE".to_vec()),
+			Error::<Test>::ReferalFailed
+		);
+
+		// Linked accounts may change the password, but the owner stays the same.
+		assert_ok!(Identity::change_password_sel13(Origin::signed(1), user, b"welcome123".to_vec()));
+		assert_ok!(Identity::set_referal_sel12(Origin::signed(1), user, b"referNEW".to_vec()));
+		assert_eq!(Identity::identity1(user).unwrap().owner, before.owner);
+		assert_eq!(before.owner, 10);
+	});
+}
+
+#[test]
+fn linking_and_setting_a_service_keep_the_deposit_sela() {
+	new_test_ext().execute_with(|| {
+		let user = Identity::email_hash(b"a@b.com");
+		let referal: Vec<u8> = b"referABCD".to_vec();
+		assert_ok!(Identity::request_registration_sel11(Origin::signed(10), user, b"hello123".to_vec()));
+		Identity1Of::<Test>::mutate(user, |id| id.as_mut().unwrap().deposit = 5);
+
+		assert_ok!(Identity::set_referal_sel12(Origin::signed(10), user, referal.clone()));
+		assert_ok!(Identity::create_web3link_sel15(Origin::signed(1), user, 1, referal));
+		assert_eq!(Identity::identity1(user).unwrap().deposit, 5);
+		assert_ok!(Identity::set_accessservice_sel17(Origin::signed(1), 1, b"library".to_vec()));
+		assert_eq!(Identity::identity1(user).unwrap().deposit, 5);
+	});
+}
+
 #[test]
 fn referal_setup_andusing_sela() {
 	new_test_ext().execute_with(|| {
 		let data = |x| Data::Raw(vec![x; 1].try_into().unwrap());
-        let user: Vec<u8> = b"a@b.com".to_vec().try_into().unwrap();
+        let user = Identity::email_hash(b"a@b.com");
         let pass: Vec<u8> = b"hello123".to_vec().try_into().unwrap();
         let referal: Vec<u8> = b"referABCD".to_vec().try_into().unwrap();
         let wrongreferal: Vec<u8> = b"wrongreferABCD".to_vec().try_into().unwrap();
-		assert_ok!(Identity::request_registration_sel11(Origin::signed(10),user.clone(), pass.clone()  ) );
-		assert_ok!(Identity::login_access_sel12(Origin::signed(10),user.clone(), pass.clone()  ) );
-		assert_ok!(Identity::set_referal_sel12(Origin::signed(10),user.clone(),referal.clone() ) );
-		assert_noop!(Identity::create_web3link_sel15(Origin::signed(10),user.clone(),10, wrongreferal.clone() ),  Error::<Test>::ReferalFailed );
-		assert_noop!(Identity::create_web3link_sel15(Origin::signed(10),user.clone(),9, referal.clone() ),  Error::<Test>::SignerNotmatching );
-		assert_ok!(Identity::create_web3link_sel15(Origin::signed(10),user.clone(),10, referal.clone() ) );
+		assert_ok!(Identity::request_registration_sel11(Origin::signed(10),user, pass.clone()  ) );
+		assert_ok!(Identity::login_access_sel12(Origin::signed(10),user, pass.clone()  ) );
+		assert_ok!(Identity::set_referal_sel12(Origin::signed(10),user,referal.clone() ) );
+		assert_noop!(Identity::create_web3link_sel15(Origin::signed(10),user,10, wrongreferal.clone() ),  Error::<Test>::ReferalFailed );
+		assert_noop!(Identity::create_web3link_sel15(Origin::signed(10),user,9, referal.clone() ),  Error::<Test>::SignerNotmatching );
+		assert_ok!(Identity::create_web3link_sel15(Origin::signed(10),user,10, referal.clone() ) );
 	});
 }
 
@@ -199,15 +343,15 @@ fn referal_setup_andusing_sela() {
 fn login_web3_method_sela() {
 	new_test_ext().execute_with(|| {
 		let data = |x| Data::Raw(vec![x; 1].try_into().unwrap());
-        let user: Vec<u8> = b"a@b.com".to_vec().try_into().unwrap();
+        let user = Identity::email_hash(b"a@b.com");
         let pass: Vec<u8> = b"hello123".to_vec().try_into().unwrap();
         let referal: Vec<u8> = b"referABCD".to_vec().try_into().unwrap();
-		assert_ok!(Identity::request_registration_sel11(Origin::signed(10),user.clone(), pass.clone()  ) );
-		assert_ok!(Identity::login_access_sel12(Origin::signed(10),user.clone(), pass.clone()  ) );
-		assert_ok!(Identity::set_referal_sel12(Origin::signed(10),user.clone(),referal.clone() ) );
-		assert_ok!(Identity::create_web3link_sel15(Origin::signed(10),user.clone(),10, referal.clone() ) );
-		assert_noop!(Identity::login_web3_sel16(Origin::signed(9),user.clone()  ), Error::<Test>::LoginFailed );
-		assert_ok!(Identity::login_web3_sel16(Origin::signed(10),user.clone()  ) );
+		assert_ok!(Identity::request_registration_sel11(Origin::signed(10),user, pass.clone()  ) );
+		assert_ok!(Identity::login_access_sel12(Origin::signed(10),user, pass.clone()  ) );
+		assert_ok!(Identity::set_referal_sel12(Origin::signed(10),user,referal.clone() ) );
+		assert_ok!(Identity::create_web3link_sel15(Origin::signed(10),user,10, referal.clone() ) );
+		assert_noop!(Identity::login_web3_sel16(Origin::signed(9),user  ), Error::<Test>::LoginFailed );
+		assert_ok!(Identity::login_web3_sel16(Origin::signed(10),user  ) );
 	});
 }
 
@@ -215,17 +359,17 @@ fn login_web3_method_sela() {
 fn access_web3_method_sela() {
 	new_test_ext().execute_with(|| {
 		let data = |x| Data::Raw(vec![x; 1].try_into().unwrap());
-        let user: Vec<u8> = b"a@b.com".to_vec().try_into().unwrap();
+        let user = Identity::email_hash(b"a@b.com");
         let pass: Vec<u8> = b"hello123".to_vec().try_into().unwrap();
         let referal: Vec<u8> = b"referABCD".to_vec().try_into().unwrap();
         let service: Vec<u8> = b"docsystem".to_vec().try_into().unwrap();
         let manager = 11;
         let staff = 10;
-		assert_ok!(Identity::request_registration_sel11(Origin::signed(staff),user.clone(), pass.clone()  ) );
-		assert_ok!(Identity::login_access_sel12(Origin::signed(staff),user.clone(), pass.clone()  ) );
-		assert_ok!(Identity::set_referal_sel12(Origin::signed(staff),user.clone(),referal.clone() ) );
-		assert_ok!(Identity::create_web3link_sel15(Origin::signed(staff),user.clone(),10, referal.clone() ) );
-		assert_ok!(Identity::login_web3_sel16(Origin::signed(staff),user.clone()  ) );
+		assert_ok!(Identity::request_registration_sel11(Origin::signed(staff),user, pass.clone()  ) );
+		assert_ok!(Identity::login_access_sel12(Origin::signed(staff),user, pass.clone()  ) );
+		assert_ok!(Identity::set_referal_sel12(Origin::signed(staff),user,referal.clone() ) );
+		assert_ok!(Identity::create_web3link_sel15(Origin::signed(staff),user,10, referal.clone() ) );
+		assert_ok!(Identity::login_web3_sel16(Origin::signed(staff),user  ) );
 
 		assert_noop!(Identity::check_web3access_sel18(Origin::signed(staff),service.clone()  ), Error::<Test>::ServiceAccessFailed );
 		assert_ok!(Identity::set_accessservice_sel17(Origin::signed(manager),staff, service.clone()  ) );
@@ -234,4 +378,698 @@ fn access_web3_method_sela() {
 	});
 }
 
+#[test]
+fn lifecycle_event_stream_sela() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let user = Identity::email_hash(b"a@b.com");
+		let pass = b"hello123".to_vec();
+		let referal = b"referABCD".to_vec();
+		let service = b"docsystem".to_vec();
+
+		assert_ok!(Identity::request_registration_sel11(Origin::signed(10), user, pass.clone()));
+		assert_ok!(Identity::set_referal_sel12(Origin::signed(10), user, referal.clone()));
+		assert_ok!(Identity::create_web3link_sel15(Origin::signed(1), user, 1, referal));
+		assert_ok!(Identity::set_accessservice_sel17(Origin::signed(11), 1, service.clone()));
+		assert_ok!(Identity::login_access_sel12(Origin::signed(10), user, pass));
+		assert_ok!(Identity::login_web3_sel16(Origin::signed(1), user));
+		assert_ok!(Identity::check_web3access_sel18(Origin::signed(1), service.clone()));
+
+		let events: Vec<_> = System::events()
+			.into_iter()
+			.filter_map(|record| match record.event {
+				Event::Identity(event) => Some(event),
+				_ => None,
+			})
+			.collect();
+		assert_eq!(
+			events,
+			vec![
+				crate::Event::UserRegistered { email_hash: user, owner: 10 },
+				crate::Event::ReferalSet { email_hash: user, by: 10 },
+				crate::Event::UserWeb3registered { email_hash: user, account: 1 },
+				crate::Event::ServiceAccessSet { email_hash: user, service: service.clone(), by: 11 },
+				crate::Event::UserLoginsuccess { email_hash: user, who: 10 },
+				crate::Event::UserWeb3loginsuccess { email_hash: user, account: 1 },
+				crate::Event::ServiceAccessChecked { email_hash: user, account: 1, service },
+			]
+		);
+	});
+}
+
+#[test]
+fn failed_calls_are_reported_sela() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let user = Identity::email_hash(b"a@b.com");
+		let missing = Identity::email_hash(b"x@y.com");
+		let pass = b"hello123".to_vec();
+		assert_ok!(Identity::request_registration_sel11(Origin::signed(10), user, pass.clone()));
+
+		assert!(dispatch_recorded(11, crate::Call::request_registration_sel11 { email_hash: user, password: pass.clone() }).is_err());
+		System::assert_last_event(Event::Identity(crate::Event::UserExists { email_hash: user, who: 11 }));
+
+		assert!(dispatch_recorded(1, crate::Call::create_web3link_sel15 { email_hash: user, idtolink: 1, referal: b"nope".to_vec() }).is_err());
+		System::assert_last_event(Event::Identity(crate::Event::UserWeb3registerfailed {
+			email_hash: user,
+			account: 1,
+			error: Error::<Test>::ReferalFailed.into(),
+		}));
+
+		assert!(login(1, missing, &pass).is_err());
+		System::assert_last_event(Event::Identity(crate::Event::UserDoesnotexist { email_hash: missing, who: 1 }));
+
+		assert!(dispatch_recorded(1, crate::Call::login_web3_sel16 { email_hash: user }).is_err());
+		System::assert_last_event(Event::Identity(crate::Event::UserWeb3loginfailed { email_hash: user, account: 1 }));
+		assert!(dispatch_recorded(1, crate::Call::login_web3_sel16 { email_hash: missing }).is_err());
+		System::assert_last_event(Event::Identity(crate::Event::UserWeb3doesnotexist { email_hash: missing, account: 1 }));
+	});
+}
+
+#[test]
+fn event_indices_are_stable_sela() {
+	let index = |event: crate::Event<Test>| event.encode()[0];
+	let user = [0u8; 32];
+	assert_eq!(index(crate::Event::UserRegistered { email_hash: user, owner: 1 }), 0);
+	assert_eq!(index(crate::Event::UserExists { email_hash: user, who: 1 }), 1);
+	assert_eq!(index(crate::Event::UserWeb3registered { email_hash: user, account: 1 }), 4);
+	assert_eq!(index(crate::Event::UserLoginfailed { email_hash: user, who: 1 }), 7);
+	assert_eq!(index(crate::Event::UserWeb3loginfailed { email_hash: user, account: 1 }), 10);
+	assert_eq!(index(crate::Event::IdentitySet { who: 1 }), 12);
+	assert_eq!(index(crate::Event::RegistrarAdded { registrar_index: 0 }), 16);
+	assert_eq!(index(crate::Event::SubIdentityRevoked { sub: 1, main: 2, deposit: 0 }), 19);
+}
+
+#[test]
+fn email_hash_is_normalised_sela() {
+	new_test_ext().execute_with(|| {
+        let user = Identity::email_hash(b"a@b.com");
+        let pass: Vec<u8> = b"hello123".to_vec();
+		assert_eq!(Identity::email_hash(b"  A@B.Com\n"), user);
+		assert_ne!(Identity::email_hash(b"a@b.co"), user);
+		assert_ne!(user, sp_io::hashing::blake2_256(b"a@b.com"));
+
+		assert_ok!(Identity::request_registration_sel11(Origin::signed(10), user, pass.clone()));
+		assert_noop!(
+			Identity::request_registration_sel11(Origin::signed(11), Identity::email_hash(b"A@b.com "), pass),
+			Error::<Test>::IdentityAlreadyClaimed
+		);
+		let reg = Identity::identity_by_email(b"a@B.com").unwrap();
+		assert_eq!(reg.owner, 10);
+		assert_eq!(reg.info.email, Data::None);
+		assert!(Identity::identity_of_account(&10).is_none());
+	});
+}
+
+#[test]
+fn migration_from_v0_sela() {
+	new_test_ext().execute_with(|| {
+		let email: Email<Test> = b"a@b.com".to_vec().try_into().unwrap();
+		let pass: Vec<u8> = b"hello123".to_vec();
+		let user = Identity::email_hash(b"a@b.com");
+
+		// Recreate the v0 layout: plaintext keys and the old `RegistrationSel`.
+		let new = Identity::new_registration(10, &pass);
+		let old = migrations::v3::OldRegistrationOf::<Test> {
+			deposit: 0,
+			account_id: 10,
+			info: migrations::v3::OldIdentityInfo {
+				additional: new.info.additional.clone(),
+				display: new.info.display.clone(),
+				legal: new.info.legal.clone(),
+				web: new.info.web.clone(),
+				referalhash: new.info.referral_hash.clone(),
+				email: Data::Raw(email.to_vec().try_into().unwrap()),
+				pgp_fingerprint: None,
+				account: Data::None,
+				image: new.info.image.clone(),
+				passwordhash: new.info.password_hash.clone(),
+			},
+		};
+		frame_support::migration::put_storage_value(b"Identity", b"Identity1Of", &email.using_encoded(|k| Twox64Concat::hash(k)), old);
+		frame_support::migration::put_storage_value(b"Identity", b"EmailId", &10u64.using_encoded(|k| Twox64Concat::hash(k)), email);
+		assert_eq!(StorageVersion::get::<Identity>(), 0);
+
+		migrations::migrate::<Test>();
+
+		assert_eq!(StorageVersion::get::<Identity>(), STORAGE_VERSION);
+		assert_eq!(Identity1Of::<Test>::iter().count(), 1);
+		assert_eq!(Identity::identity1(user), Some(new));
+		assert_eq!(Identity::emailid(10), Some(user));
+		assert_eq!(Identity::linked_accounts(user).into_inner(), vec![10]);
+		assert_ok!(Identity::login_access_sel12(Origin::signed(10), user, pass));
+
+		// Already up to date: nothing runs again.
+		migrations::migrate::<Test>();
+		assert_eq!(Identity::identity1(user).map(|reg| reg.owner), Some(10));
+		migrations::v1::migrate::<Test>();
+		assert_eq!(Identity1Of::<Test>::iter().count(), 1);
+		assert_eq!(Identity::emailid(10), Some(user));
+	});
+}
+
+#[test]
+fn multiple_web3_links_sela() {
+	new_test_ext().execute_with(|| {
+        let user = Identity::email_hash(b"a@b.com");
+        let pass: Vec<u8> = b"hello123".to_vec();
+        let referal: Vec<u8> = b"referABCD".to_vec();
+        let referal2: Vec<u8> = b"referEFGH".to_vec();
+		assert_ok!(Identity::request_registration_sel11(Origin::signed(10), user, pass.clone()));
+		assert_ok!(Identity::set_referal_sel12(Origin::signed(10), user, referal.clone()));
+		assert_ok!(Identity::create_web3link_sel15(Origin::signed(1), user, 1, referal.clone()));
+
+		// The referal is single-use, a second device needs a fresh one.
+		assert_noop!(Identity::create_web3link_sel15(Origin::signed(2), user, 2, referal.clone()), Error::<Test>::ReferalFailed);
+		assert_ok!(Identity::set_referal_sel12(Origin::signed(10), user, referal2.clone()));
+		assert_noop!(Identity::create_web3link_sel15(Origin::signed(1), user, 1, referal2.clone()), Error::<Test>::AlreadyLinked);
+		assert_ok!(Identity::create_web3link_sel15(Origin::signed(2), user, 2, referal2.clone()));
+
+		assert_eq!(Identity::linked_accounts(user).into_inner(), vec![1, 2]);
+		assert_eq!(Identity::emailid(1), Some(user));
+		assert_eq!(Identity::emailid(2), Some(user));
+		assert_ok!(Identity::login_web3_sel16(Origin::signed(1), user));
+		assert_ok!(Identity::login_web3_sel16(Origin::signed(2), user));
+
+		// MaxLinkedAccounts is 2 in the mock.
+		assert_ok!(Identity::set_referal_sel12(Origin::signed(10), user, referal.clone()));
+		assert_noop!(Identity::create_web3link_sel15(Origin::signed(3), user, 3, referal.clone()), Error::<Test>::TooManyLinkedAccounts);
+	});
+}
+
+#[test]
+fn unlink_and_relink_web3_sela() {
+	new_test_ext().execute_with(|| {
+        let user = Identity::email_hash(b"a@b.com");
+        let pass: Vec<u8> = b"hello123".to_vec();
+        let referal: Vec<u8> = b"referABCD".to_vec();
+        let referal2: Vec<u8> = b"referEFGH".to_vec();
+		assert_ok!(Identity::request_registration_sel11(Origin::signed(10), user, pass.clone()));
+		assert_ok!(Identity::set_referal_sel12(Origin::signed(10), user, referal.clone()));
+		assert_ok!(Identity::create_web3link_sel15(Origin::signed(1), user, 1, referal.clone()));
+		assert_ok!(Identity::set_referal_sel12(Origin::signed(10), user, referal2.clone()));
+		assert_ok!(Identity::create_web3link_sel15(Origin::signed(2), user, 2, referal2.clone()));
+
+		// Strangers cannot unlink, a linked device can unlink another one.
+		assert_noop!(Identity::unlink_web3_sel19(Origin::signed(3), user, 1), Error::<Test>::NotLinkedOrOwner);
+		assert_noop!(Identity::unlink_web3_sel19(Origin::signed(2), user, 3), Error::<Test>::NotLinked);
+		assert_ok!(Identity::unlink_web3_sel19(Origin::signed(2), user, 1));
+		assert_eq!(Identity::emailid(1), None);
+		assert_eq!(Identity::linked_accounts(user).into_inner(), vec![2]);
+		assert_noop!(Identity::login_web3_sel16(Origin::signed(1), user), Error::<Test>::LoginFailed);
+
+		// The owner can unlink too.
+		assert_ok!(Identity::unlink_web3_sel19(Origin::signed(10), user, 2));
+		assert_eq!(Identity::emailid(2), None);
+		assert!(Identity::linked_accounts(user).is_empty());
+
+		// Relinking needs a fresh referal.
+		assert_noop!(Identity::create_web3link_sel15(Origin::signed(1), user, 1, referal2.clone()), Error::<Test>::ReferalFailed);
+		assert_ok!(Identity::set_referal_sel12(Origin::signed(10), user, referal.clone()));
+		assert_ok!(Identity::create_web3link_sel15(Origin::signed(1), user, 1, referal.clone()));
+		assert_eq!(Identity::emailid(1), Some(user));
+	});
+}
+
+fn register_and_link(email: &[u8], account: u64) -> EmailHash {
+	let user = Identity::email_hash(email);
+	let referal: Vec<u8> = b"referABCD".to_vec();
+	assert_ok!(Identity::request_registration_sel11(Origin::signed(10), user, b"hello123".to_vec()));
+	assert_ok!(Identity::set_referal_sel12(Origin::signed(10), user, referal.clone()));
+	assert_ok!(Identity::create_web3link_sel15(Origin::signed(account), user, account, referal));
+	user
+}
+
+#[test]
+fn recovery_config_validation_sela() {
+	new_test_ext().execute_with(|| {
+		let user = register_and_link(b"a@b.com", 1);
+		assert_noop!(Identity::set_recovery_sel20(Origin::signed(5), user, vec![20, 30], 1), Error::<Test>::NotLinkedOrOwner);
+		assert_noop!(Identity::set_recovery_sel20(Origin::signed(1), user, vec![20, 30], 0), Error::<Test>::InvalidThreshold);
+		assert_noop!(Identity::set_recovery_sel20(Origin::signed(1), user, vec![20, 30], 3), Error::<Test>::InvalidThreshold);
+		assert_noop!(Identity::set_recovery_sel20(Origin::signed(1), user, vec![20, 20], 1), Error::<Test>::DuplicateGuardian);
+		assert_noop!(Identity::set_recovery_sel20(Origin::signed(1), user, vec![20, 30, 40, 50], 2), Error::<Test>::TooManyGuardians);
+		assert_noop!(Identity::initiate_recovery_sel22(Origin::signed(2), user), Error::<Test>::NoRecoveryConfig);
+
+		assert_ok!(Identity::set_recovery_sel20(Origin::signed(1), user, vec![30, 20], 2));
+		assert_eq!(Identity::recovery_config(user).unwrap().guardians.into_inner(), vec![20, 30]);
+		assert_noop!(Identity::initiate_recovery_sel22(Origin::signed(1), user), Error::<Test>::AlreadyLinked);
+		assert_ok!(Identity::remove_recovery_sel21(Origin::signed(1), user));
+		assert!(Identity::recovery_config(user).is_none());
+	});
+}
+
+#[test]
+fn social_recovery_should_work_sela() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let user = register_and_link(b"a@b.com", 1);
+		assert_ok!(Identity::set_recovery_sel20(Origin::signed(1), user, vec![20, 30, 40], 2));
+
+		assert_ok!(Identity::initiate_recovery_sel22(Origin::signed(2), user));
+		assert_eq!(Balances::reserved_balance(2), 5);
+		assert_noop!(Identity::initiate_recovery_sel22(Origin::signed(2), user), Error::<Test>::RecoveryAlreadyActive);
+		assert_noop!(Identity::set_recovery_sel20(Origin::signed(1), user, vec![20], 1), Error::<Test>::RecoveryAlreadyActive);
+
+		// A second key may try at the same time; the guardians decide which one wins.
+		assert_ok!(Identity::initiate_recovery_sel22(Origin::signed(3), user));
+		assert_noop!(Identity::vouch_recovery_sel23(Origin::signed(50), user, 2), Error::<Test>::NotGuardian);
+		assert_noop!(Identity::vouch_recovery_sel23(Origin::signed(20), user, 4), Error::<Test>::NoActiveRecovery);
+		assert_ok!(Identity::vouch_recovery_sel23(Origin::signed(20), user, 2));
+		assert_noop!(Identity::vouch_recovery_sel23(Origin::signed(20), user, 2), Error::<Test>::AlreadyVouched);
+		assert_noop!(Identity::claim_recovery_sel24(Origin::signed(2), user), Error::<Test>::ThresholdNotReached);
+		assert_ok!(Identity::vouch_recovery_sel23(Origin::signed(30), user, 2));
+
+		assert_noop!(Identity::claim_recovery_sel24(Origin::signed(3), user), Error::<Test>::ThresholdNotReached);
+		assert_noop!(Identity::claim_recovery_sel24(Origin::signed(4), user), Error::<Test>::NoActiveRecovery);
+		assert_noop!(Identity::claim_recovery_sel24(Origin::signed(2), user), Error::<Test>::RecoveryDelayNotPassed);
+		System::set_block_number(11);
+		assert_ok!(Identity::claim_recovery_sel24(Origin::signed(2), user));
+
+		assert_eq!(Identity::linked_accounts(user).into_inner(), vec![2]);
+		assert_eq!(Identity::emailid(1), None);
+		assert_eq!(Identity::emailid(2), Some(user));
+		assert_eq!(Identity::identity1(user).unwrap().owner, 2);
+		assert!(!Identity::recovery_in_progress(&user));
+		assert_eq!(Balances::reserved_balance(2), 0);
+		assert_eq!(Balances::reserved_balance(3), 0);
+		assert_noop!(Identity::login_web3_sel16(Origin::signed(1), user), Error::<Test>::LoginFailed);
+		assert_ok!(Identity::login_web3_sel16(Origin::signed(2), user));
+	});
+}
+
+#[test]
+fn old_key_can_cancel_recovery_sela() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let user = register_and_link(b"a@b.com", 1);
+		assert_ok!(Identity::set_recovery_sel20(Origin::signed(1), user, vec![20, 30], 1));
+		assert_ok!(Identity::initiate_recovery_sel22(Origin::signed(2), user));
+		assert_ok!(Identity::vouch_recovery_sel23(Origin::signed(20), user, 2));
+
+		assert_noop!(Identity::cancel_recovery_sel25(Origin::signed(2), user, 2), Error::<Test>::NotLinkedOrOwner);
+		assert_ok!(Identity::cancel_recovery_sel25(Origin::signed(1), user, 2));
+		assert_noop!(Identity::cancel_recovery_sel25(Origin::signed(1), user, 2), Error::<Test>::NoActiveRecovery);
+		// The deposit of the cancelled recovery goes to the old key.
+		assert_eq!(Balances::free_balance(2), 5);
+		assert_eq!(Balances::free_balance(1), 15);
+
+		System::set_block_number(20);
+		assert_noop!(Identity::claim_recovery_sel24(Origin::signed(2), user), Error::<Test>::NoActiveRecovery);
+		assert_eq!(Identity::emailid(1), Some(user));
+	});
+}
+
+#[test]
+fn stranger_cannot_block_recovery_sela() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let user = register_and_link(b"a@b.com", 1);
+		assert_ok!(Identity::set_recovery_sel20(Origin::signed(1), user, vec![20, 30], 1));
+
+		// A stranger opens a recovery onto its own key and never goes away.
+		let stranger = 3;
+		assert_ok!(Identity::initiate_recovery_sel22(Origin::signed(stranger), user));
+		assert_eq!(Balances::reserved_balance(stranger), 5);
+
+		// The real owner's new key can still recover the identity.
+		assert_ok!(Identity::initiate_recovery_sel22(Origin::signed(2), user));
+		assert_ok!(Identity::vouch_recovery_sel23(Origin::signed(20), user, 2));
+		System::set_block_number(11);
+		assert_noop!(Identity::claim_recovery_sel24(Origin::signed(stranger), user), Error::<Test>::ThresholdNotReached);
+		assert_ok!(Identity::claim_recovery_sel24(Origin::signed(2), user));
+		assert_eq!(Identity::emailid(2), Some(user));
+		assert!(Identity::active_recovery(user, stranger).is_none());
+		assert_eq!(Balances::reserved_balance(stranger), 0);
+
+		// Spamming recoveries costs the deposit once the identity cancels them.
+		assert_ok!(Identity::initiate_recovery_sel22(Origin::signed(stranger), user));
+		assert_ok!(Identity::cancel_recovery_sel25(Origin::signed(2), user, stranger));
+		assert_eq!(Balances::free_balance(stranger), 5);
+	});
+}
+
+#[test]
+fn referral_edge_and_reward_sela() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(3);
+		let referrer = register_and_link(b"a@b.com", 1);
+		let referred = Identity::email_hash(b"c@d.com");
+		let referal: Vec<u8> = b"referXYZ".to_vec();
+		// Account 1, linked to the referrer, registers the new identity and hands out the referal.
+		assert_ok!(Identity::request_registration_sel11(Origin::signed(1), referred, b"hello123".to_vec()));
+
+		// A referal the referrer sets on its own identity is stored, but refers nobody.
+		assert_ok!(Identity::set_referal_sel12(Origin::signed(1), referrer, b"referSELF".to_vec()));
+		assert_ok!(Identity::create_web3link_sel15(Origin::signed(3), referrer, 3, b"referSELF".to_vec()));
+		assert_eq!(Identity::referral_count(referrer), 0);
+		assert_eq!(Identity::referred_by(referrer), None);
+
+		assert_ok!(Identity::set_referal_sel12(Origin::signed(1), referred, referal.clone()));
+		assert_ok!(Identity::create_web3link_sel15(Origin::signed(2), referred, 2, referal.clone()));
+
+		assert_eq!(Identity::referrals(referrer, referred), Some(3));
+		assert_eq!(Identity::referred_by(referred), Some(referrer));
+		assert_eq!(Identity::referral_count(referrer), 1);
+		System::assert_last_event(Event::Identity(crate::Event::ReferralRecorded { referrer, referred }));
+
+		// The reward is paid out of the referral pot when the account is linked. The pot is
+		// empty, so it stays owed until the next account is linked to the referred identity.
+		assert_eq!(Balances::free_balance(1), 10);
+		assert_eq!(Identity::unpaid_referral_reward(referred), Some(1));
+
+		Balances::make_free_balance_be(&Identity::referral_pot(), 100);
+		let issuance = Balances::total_issuance();
+		assert_ok!(Identity::set_referal_sel12(Origin::signed(2), referred, b"referTWO".to_vec()));
+		assert_ok!(Identity::create_web3link_sel15(Origin::signed(4), referred, 4, b"referTWO".to_vec()));
+		assert_eq!(Balances::free_balance(1), 15);
+		assert_eq!(Balances::free_balance(Identity::referral_pot()), 95);
+		assert_eq!(Balances::total_issuance(), issuance);
+		assert_eq!(Identity::unpaid_referral_reward(referred), None);
+		System::assert_last_event(Event::Identity(crate::Event::ReferralRewardPaid { referred, account: 1, reward: 5 }));
+
+		// MaxReferralsPerReferrer is 1 in the mock.
+		let third = Identity::email_hash(b"e@f.com");
+		assert_ok!(Identity::request_registration_sel11(Origin::signed(1), third, b"hello123".to_vec()));
+		assert_noop!(Identity::set_referal_sel12(Origin::signed(1), third, referal), Error::<Test>::ReferralCapReached);
+	});
+}
+
+#[test]
+fn referral_from_unlinked_account_has_no_edge_sela() {
+	new_test_ext().execute_with(|| {
+		let user = register_and_link(b"a@b.com", 1);
+		assert_eq!(Identity::referred_by(user), None);
+		assert_eq!(Balances::free_balance(10), 100);
+
+		// A referal set by an owner that is not linked anywhere has no referrer, and strangers
+		// cannot attach their own referal to the identity.
+		let referred = Identity::email_hash(b"c@d.com");
+		let referal: Vec<u8> = b"referXYZ".to_vec();
+		assert_ok!(Identity::request_registration_sel11(Origin::signed(20), referred, b"hello123".to_vec()));
+		assert_noop!(Identity::set_referal_sel12(Origin::signed(1), referred, referal.clone()), Error::<Test>::NotLinkedOrOwner);
+		assert_ok!(Identity::set_referal_sel12(Origin::signed(20), referred, referal.clone()));
+		assert_ok!(Identity::create_web3link_sel15(Origin::signed(2), referred, 2, referal));
+		assert_eq!(Identity::referred_by(referred), None);
+		assert_eq!(Identity::referral_count(user), 0);
+		assert_eq!(Balances::free_balance(1), 10);
+	});
+}
+
+#[test]
+fn runtime_api_helpers_sela() {
+	new_test_ext().execute_with(|| {
+		let user = register_and_link(b"a@b.com", 1);
+		assert_ok!(Identity::set_accessservice_sel17(Origin::signed(10), 1, b"moodle".to_vec()));
+
+		let summary = Identity::identity_summary_of_account(&1).unwrap();
+		assert_eq!(summary.email_hash, user);
+		assert_eq!(summary.owner, 10);
+		assert_eq!(summary.linked_accounts, vec![1]);
+		assert_eq!(summary.service, Some(b"moodle".to_vec()));
+		assert!(!summary.recovery_configured);
+		assert_eq!(Identity::identity_summary(user), Some(summary));
+		assert_eq!(Identity::identity_summary_of_account(&2), None);
+
+		assert!(Identity::has_access(&1, b"moodle"));
+		assert!(!Identity::has_access(&1, b"library"));
+		assert!(!Identity::has_access(&2, b"moodle"));
+		assert!(!Identity::validate_token(b"token", &1));
+	});
+}
+
+fn did_key(byte: u8) -> DidVerificationMethod {
+	DidVerificationMethod { key_type: DidKeyType::Sr25519, public_key: [byte; 32] }
+}
+
+#[test]
+fn did_document_controller_only_sela() {
+	new_test_ext().execute_with(|| {
+		let user = register_and_link(b"a@b.com", 1);
+		assert_noop!(Identity::create_did_sel26(Origin::signed(5), user, vec![]), Error::<Test>::NotLinkedOrOwner);
+		assert_noop!(Identity::create_did_sel26(Origin::signed(1), user, vec![did_key(1), did_key(1)]), Error::<Test>::DuplicateDidKey);
+		assert_ok!(Identity::create_did_sel26(Origin::signed(1), user, vec![did_key(1)]));
+		assert_noop!(Identity::create_did_sel26(Origin::signed(1), user, vec![]), Error::<Test>::DidAlreadyExists);
+
+		// The owner is not the controller.
+		assert_noop!(Identity::add_did_key_sel27(Origin::signed(10), user, did_key(2)), Error::<Test>::NotDidController);
+		assert_noop!(Identity::add_did_key_sel27(Origin::signed(1), user, did_key(1)), Error::<Test>::DuplicateDidKey);
+		assert_ok!(Identity::add_did_key_sel27(Origin::signed(1), user, did_key(2)));
+		assert_noop!(Identity::add_did_key_sel27(Origin::signed(1), user, did_key(3)), Error::<Test>::TooManyDidKeys);
+
+		assert_noop!(Identity::rotate_did_key_sel28(Origin::signed(1), user, [9; 32], did_key(3)), Error::<Test>::DidKeyNotFound);
+		assert_ok!(Identity::rotate_did_key_sel28(Origin::signed(1), user, [1; 32], did_key(3)));
+		assert_ok!(Identity::remove_did_key_sel29(Origin::signed(1), user, [2; 32]));
+		assert_noop!(Identity::remove_did_key_sel29(Origin::signed(1), user, [2; 32]), Error::<Test>::DidKeyNotFound);
+		assert_eq!(Identity::did_document(user).unwrap().verification_methods.into_inner(), vec![did_key(3)]);
+
+		assert_ok!(Identity::set_accessservice_sel17(Origin::signed(10), 1, b"moodle".to_vec()));
+		let details = Identity::did_details(user).unwrap();
+		assert_eq!(details.controller, Some(1));
+		assert_eq!(details.linked_accounts, vec![1]);
+		assert_eq!(details.services, vec![b"moodle".to_vec()]);
+	});
+}
+
+#[test]
+fn did_controller_handover_sela() {
+	new_test_ext().execute_with(|| {
+		let user = register_and_link(b"a@b.com", 1);
+		let referal: Vec<u8> = b"referXYZ".to_vec();
+		assert_ok!(Identity::set_referal_sel12(Origin::signed(10), user, referal.clone()));
+		assert_ok!(Identity::create_web3link_sel15(Origin::signed(2), user, 2, referal));
+		assert_ok!(Identity::create_did_sel26(Origin::signed(1), user, vec![]));
+
+		assert_noop!(Identity::set_did_controller_sel30(Origin::signed(2), user, 2), Error::<Test>::NotDidController);
+		assert_noop!(Identity::set_did_controller_sel30(Origin::signed(1), user, 5), Error::<Test>::NotLinkedOrOwner);
+		// The owner can only take over once the controller is unlinked.
+		assert_noop!(Identity::set_did_controller_sel30(Origin::signed(10), user, 2), Error::<Test>::NotDidController);
+		assert_ok!(Identity::unlink_web3_sel19(Origin::signed(2), user, 1));
+		assert_noop!(Identity::add_did_key_sel27(Origin::signed(1), user, did_key(1)), Error::<Test>::NotLinkedOrOwner);
+		assert_ok!(Identity::set_did_controller_sel30(Origin::signed(10), user, 2));
+		assert_ok!(Identity::add_did_key_sel27(Origin::signed(2), user, did_key(1)));
+
+		// Resolves without an on-chain document too.
+		let other = register_and_link(b"c@d.com", 3);
+		assert_eq!(Identity::did_details(other).unwrap().controller, None);
+	});
+}
+
+#[test]
+fn genesis_config_seeds_identities_sela() {
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	GenesisBuild::<Test>::assimilate_storage(
+		&pallet_studentid::GenesisConfig::<Test> {
+			identities: vec![(b"a@b.com".to_vec(), 10, b"hello123".to_vec())],
+			email_links: vec![(b"A@b.com".to_vec(), 1), (b"a@b.com".to_vec(), 2)],
+			services: vec![(b"moodle".to_vec(), 30)],
+			registrars: vec![20],
+		},
+		&mut t,
+	)
+	.unwrap();
+
+	sp_io::TestExternalities::from(t).execute_with(|| {
+		let user = Identity::email_hash(b"a@b.com");
+		assert_eq!(Identity::identity1(user).unwrap().owner, 10);
+		assert_eq!(Identity::linked_accounts(user).into_inner(), vec![1, 2]);
+		assert_eq!(Identity::emailid(2), Some(user));
+		assert_eq!(Identity::service_provider(ServiceName::try_from(b"moodle".to_vec()).unwrap()), Some(30));
+		assert_eq!(Identity::registrars().into_inner(), vec![20]);
+		assert_ok!(Identity::login_access_sel12(Origin::signed(10), user, b"hello123".to_vec()));
+	});
+}
+
+#[test]
+fn registrars_and_services_sela() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(Identity::add_registrar_sel31(Origin::signed(1), 20), BadOrigin);
+		assert_ok!(Identity::add_registrar_sel31(Origin::root(), 20));
+		assert_noop!(Identity::add_registrar_sel31(Origin::root(), 20), Error::<Test>::AlreadyRegistrar);
+		assert_eq!(Identity::registrars().into_inner(), vec![20]);
+
+		assert_noop!(Identity::register_service_sel32(Origin::root(), vec![b'x'; 33], 30), Error::<Test>::ServiceNameTooLong);
+		assert_ok!(Identity::register_service_sel32(Origin::root(), b"moodle".to_vec(), 30));
+		assert_eq!(Identity::service_provider(ServiceName::try_from(b"moodle".to_vec()).unwrap()), Some(30));
+	});
+}
+
+#[test]
+fn batch_enrollment_sela() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let a = Identity::email_hash(b"a@b.com");
+		let b = Identity::email_hash(b"c@d.com");
+		let referal: Vec<u8> = b"referABCD".to_vec();
+		let entry = |email_hash| (email_hash, sha2_256(&referal), BoundedVec::default());
+
+		assert_noop!(Identity::enroll_batch_sel33(Origin::signed(20), vec![entry(a)]), BadOrigin);
+		assert_ok!(Identity::add_registrar_sel31(Origin::root(), 20));
+		assert_noop!(
+			Identity::enroll_batch_sel33(Origin::signed(20), vec![entry(a), entry(a), entry(a), entry(b)]),
+			Error::<Test>::BatchTooLarge
+		);
+
+		// The duplicate is reported and skipped; the rest of the batch goes ahead.
+		assert_ok!(Identity::enroll_batch_sel33(Origin::signed(20), vec![entry(a), entry(a), entry(b)]));
+		System::assert_has_event(Event::Identity(crate::Event::UserExists { email_hash: a, who: 20 }));
+		System::assert_last_event(Event::Identity(crate::Event::BatchEnrolled { institution: 20, enrolled: 2, failed: 1 }));
+		assert_eq!(Identity::identity1(b).unwrap().owner, 20);
+		assert_eq!(Identity::role(b), Some(Role::Student));
+
+		// Students link with the referal the institution handed out.
+		assert_ok!(Identity::create_web3link_sel15(Origin::signed(1), a, 1, referal));
+		// Enrolled identities have no password to log in with.
+		assert_noop!(Identity::login_access_sel12(Origin::signed(1), a, vec![]), Error::<Test>::LoginFailed);
+	});
+}
+
+#[test]
+fn roles_sela() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let user = register_and_link(b"a@b.com", 1);
+		// A self-registered identity has no role until an institution assigns one.
+		assert_eq!(Identity::role(user), None);
+		assert_eq!(<Identity as IdentityProvider<u64>>::role_of(&1), None);
+		assert!(EnsureStudentRole::<Test, roles::Student>::try_origin(Origin::signed(1)).is_err());
+		assert!(EnsureStudentRole::<Test, roles::Faculty>::try_origin(Origin::signed(1)).is_err());
+
+		assert_noop!(Identity::set_role_sel38(Origin::signed(20), user, Role::Faculty), BadOrigin);
+		assert_ok!(Identity::add_registrar_sel31(Origin::root(), 20));
+		assert_noop!(
+			Identity::set_role_sel38(Origin::signed(20), Identity::email_hash(b"x@y.com"), Role::Faculty),
+			Error::<Test>::NoIdentity
+		);
+		assert_ok!(Identity::set_role_sel38(Origin::signed(20), user, Role::Faculty));
+		System::assert_last_event(Event::Identity(crate::Event::RoleAssigned { email_hash: user, role: Role::Faculty, by: Some(20) }));
+
+		assert_eq!(<Identity as IdentityProvider<u64>>::role_of(&1), Some(Role::Faculty));
+		assert_eq!(EnsureStudentRole::<Test, roles::Faculty>::try_origin(Origin::signed(1)).ok(), Some(1));
+		assert!(EnsureStudentRole::<Test, roles::Student>::try_origin(Origin::signed(1)).is_err());
+		// Only accounts linked to the identity act in its role.
+		assert!(EnsureStudentRole::<Test, roles::Faculty>::try_origin(Origin::signed(10)).is_err());
+
+		assert_ok!(Identity::clear_role_sel41(Origin::signed(20), user));
+		System::assert_last_event(Event::Identity(crate::Event::RoleCleared { email_hash: user, by: Some(20) }));
+		assert_eq!(Identity::role(user), None);
+		assert_noop!(Identity::clear_role_sel41(Origin::signed(20), user), Error::<Test>::NoRole);
+	});
+}
+
+#[test]
+fn only_admins_manage_the_admin_role_sela() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let admin = register_and_link(b"a@b.com", 1);
+		let user = register_and_link(b"c@d.com", 2);
+		assert_ok!(Identity::add_registrar_sel31(Origin::root(), 20));
+
+		// A registrar cannot make anyone an admin, nor demote one.
+		assert_noop!(Identity::set_role_sel38(Origin::signed(20), admin, Role::Admin), BadOrigin);
+		assert_ok!(Identity::set_role_sel38(Origin::root(), admin, Role::Admin));
+		System::assert_last_event(Event::Identity(crate::Event::RoleAssigned { email_hash: admin, role: Role::Admin, by: None }));
+		assert_noop!(Identity::set_role_sel38(Origin::signed(20), admin, Role::Staff), BadOrigin);
+		assert_noop!(Identity::clear_role_sel41(Origin::signed(20), admin), BadOrigin);
 
+		// An account acting in the admin role may manage every role.
+		assert_ok!(Identity::set_role_sel38(Origin::signed(1), user, Role::Admin));
+		System::assert_last_event(Event::Identity(crate::Event::RoleAssigned { email_hash: user, role: Role::Admin, by: Some(1) }));
+		assert_ok!(Identity::set_role_sel38(Origin::signed(1), user, Role::Student));
+		assert_noop!(Identity::set_role_sel38(Origin::signed(2), admin, Role::Student), BadOrigin);
+		assert_ok!(Identity::clear_role_sel41(Origin::root(), admin));
+		assert_eq!(Identity::role(admin), None);
+		assert_noop!(Identity::set_role_sel38(Origin::signed(1), user, Role::Admin), BadOrigin);
+	});
+}
+
+/// Only `login_web3_sel16` is free in the mock.
+#[derive(Clone, Eq, PartialEq)]
+pub struct FeelessCalls;
+impl frame_support::traits::Contains<Call> for FeelessCalls {
+	fn contains(call: &Call) -> bool {
+		matches!(call, Call::Identity(crate::Call::login_web3_sel16 { .. }))
+	}
+}
+
+#[test]
+fn feeless_transactions_sela() {
+	use sp_runtime::traits::SignedExtension;
+	use frame_support::weights::DispatchInfo;
+
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let user = register_and_link(b"a@b.com", 1);
+		let free_call = Call::Identity(crate::Call::login_web3_sel16 { email_hash: user });
+		let paid_call = Call::Identity(crate::Call::unlink_web3_sel19 { email_hash: user, account: 1 });
+		// More than any test account can pay.
+		let info = DispatchInfo { weight: 1_000, ..Default::default() };
+		let ext = || fees::ChargeOrWaiveForStudents::<Test, FeelessCalls>::from(0);
+
+		assert!(ext().validate(&1, &paid_call, &info, 10).is_err());
+		assert!(ext().validate(&2, &free_call, &info, 10).is_err());
+		// Being linked is not enough, the identity must have been given a role.
+		assert!(ext().validate(&1, &free_call, &info, 10).is_err());
+		assert_ok!(Identity::add_registrar_sel31(Origin::root(), 30));
+		assert_ok!(Identity::set_role_sel38(Origin::signed(30), user, Role::Student));
+
+		let balance = Balances::free_balance(1);
+		let valid = ext().validate(&1, &free_call, &info, 10).unwrap();
+		assert_eq!(valid.longevity, 9);
+		// Pending free transactions claim the same quota slot until one is included.
+		assert_eq!(ext().validate(&1, &free_call, &info, 10).unwrap().provides, valid.provides);
+		assert_ok!(ext().pre_dispatch(&1, &free_call, &info, 10));
+		assert_ne!(ext().validate(&1, &free_call, &info, 10).unwrap().provides, valid.provides);
+		assert_ok!(ext().pre_dispatch(&1, &free_call, &info, 10));
+		assert_eq!(Balances::free_balance(1), balance);
+		assert_eq!(Identity::feeless_usage(1), (0, 2));
+		// Quota used up: back to paying, which account 1 cannot.
+		assert!(ext().pre_dispatch(&1, &free_call, &info, 10).is_err());
+
+		System::set_block_number(10);
+		assert_ok!(ext().pre_dispatch(&1, &free_call, &info, 10));
+		assert_eq!(Identity::feeless_usage(1), (1, 1));
+	});
+}
+
+#[test]
+fn encrypted_field_grants_sela() {
+	new_test_ext().execute_with(|| {
+		let user = register_and_link(b"a@b.com", 1);
+		let own_key = [1u8; 20];
+		let office_key = [2u8; 20];
+
+		assert_noop!(Identity::set_encrypted_field_sel34(Origin::signed(5), user, ProfileField::Legal, own_key, vec![1]), Error::<Test>::NotLinkedOrOwner);
+		assert_noop!(Identity::set_encrypted_field_sel34(Origin::signed(1), user, ProfileField::Legal, own_key, vec![1]), Error::<Test>::NoPgpFingerprint);
+
+		assert_noop!(Identity::set_pgp_fingerprint_sel40(Origin::signed(5), user, Some(own_key)), Error::<Test>::NotLinkedOrOwner);
+		assert_ok!(Identity::set_pgp_fingerprint_sel40(Origin::signed(1), user, Some(own_key)));
+		System::assert_last_event(Event::Identity(crate::Event::PgpFingerprintSet { email_hash: user, fingerprint: Some(own_key) }));
+		assert_eq!(Identity::identity1(user).unwrap().info.pgp_fingerprint, Some(own_key));
+
+		// Fields are only stored encrypted to the identity's own key.
+		assert_noop!(Identity::set_encrypted_field_sel34(Origin::signed(1), user, ProfileField::Legal, office_key, vec![1]), Error::<Test>::FingerprintMismatch);
+		assert_noop!(Identity::set_encrypted_field_sel34(Origin::signed(1), user, ProfileField::Legal, own_key, vec![0; 17]), Error::<Test>::CiphertextTooLong);
+		assert_noop!(Identity::grant_field_sel36(Origin::signed(1), user, ProfileField::Legal, 30, office_key, vec![1]), Error::<Test>::NoEncryptedField);
+
+		assert_ok!(Identity::set_encrypted_field_sel34(Origin::signed(1), user, ProfileField::Legal, own_key, vec![1, 2, 3]));
+		assert_eq!(Identity::identity1(user).unwrap().info.legal, Data::None);
+		assert_eq!(Identity::encrypted_field(user, ProfileField::Legal).unwrap().recipient_fingerprint, own_key);
+
+		// Only one viewer per field in the mock; re-granting the same viewer is fine.
+		assert_ok!(Identity::grant_field_sel36(Origin::signed(1), user, ProfileField::Legal, 30, office_key, vec![4]));
+		assert_ok!(Identity::grant_field_sel36(Origin::signed(1), user, ProfileField::Legal, 30, office_key, vec![5]));
+		assert_noop!(Identity::grant_field_sel36(Origin::signed(1), user, ProfileField::Legal, 20, office_key, vec![4]), Error::<Test>::TooManyFieldGrants);
+		assert_eq!(Identity::field_grant((user, ProfileField::Legal, 30)).unwrap().ciphertext.into_inner(), vec![5]);
+		assert_eq!(Identity::field_grant((user, ProfileField::Display, 30)), None);
+
+		assert_ok!(Identity::revoke_field_sel37(Origin::signed(1), user, ProfileField::Legal, 30));
+		assert_noop!(Identity::revoke_field_sel37(Origin::signed(1), user, ProfileField::Legal, 30), Error::<Test>::NoFieldGrant);
+		assert_ok!(Identity::grant_field_sel36(Origin::signed(1), user, ProfileField::Legal, 20, office_key, vec![4]));
+
+		assert_ok!(Identity::clear_encrypted_field_sel35(Origin::signed(10), user, ProfileField::Legal));
+		assert_eq!(Identity::field_grant((user, ProfileField::Legal, 20)), None);
+		assert_eq!(FieldGrantCount::<Test>::get(user, ProfileField::Legal), 0);
+		assert_noop!(Identity::clear_encrypted_field_sel35(Origin::signed(10), user, ProfileField::Legal), Error::<Test>::NoEncryptedField);
+	});
+}
@@ -13,6 +13,8 @@ use scale_info::{
 };
 use sp_runtime::{traits::Zero, RuntimeDebug, traits::AccountIdConversion};
 use sp_std::{fmt::Debug, iter::once, ops::Add, prelude::*};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
 
 
 /// Either underlying data blob if it is at most 32 bytes, or a hash of it. If the data is greater
@@ -248,35 +250,30 @@ pub struct IdentityInfoSel<FieldLimit: Get<u32>> {
 	/// Stored as UTF-8.
 	pub web: Data,
 
-	/// The Riot/Matrix handle held by the controller of the account.
-	///
-	/// Stored as UTF-8.
-	pub referalhash: Data,
+	/// SHA2-256 of the referal an identity can be linked with, or `null` once it has been used.
+	pub referral_hash: Data,
 
-	/// The email address of the controller of the account.
-	///
-	/// Stored as UTF-8.
+	/// Left as `Data::None`. The email address is only known on-chain through the hash the
+	/// identity is keyed by, so it is never stored in plaintext.
 	pub email: Data,
 
 	/// The PGP/GPG public key of the controller of the account.
 	pub pgp_fingerprint: Option<[u8; 20]>,
 
-	pub account: Data,
-
 	/// A graphic image representing the controller of the account. Should be a company,
 	/// organization or project logo or a headshot in the case of a human.
 	pub image: Data,
 
-	/// The Twitter identity. The leading `@` character may be elided.
-	pub passwordhash: Data,
+	/// Blake2-256 of the identity's password.
+	pub password_hash: Data,
 }
 
 
 
-/// NOTE: This is stored separately primarily to facilitate the addition of extra fields in a
-/// backwards compatible way through a specialized `Decode` impl.
+/// A registered identity. Layout changes go through a versioned migration in
+/// [`crate::migrations`].
 #[derive(
-	CloneNoBound, Encode, Eq, MaxEncodedLen, PartialEqNoBound, RuntimeDebugNoBound, TypeInfo,
+	CloneNoBound, Encode, Decode, Eq, MaxEncodedLen, PartialEqNoBound, RuntimeDebugNoBound, TypeInfo,
 )]
 #[codec(mel_bound(
 	Balance: Encode + Decode + MaxEncodedLen + Copy + Clone + Debug + Eq + PartialEq + Zero + Add,
@@ -293,11 +290,8 @@ pub struct RegistrationSel<
 	/// Amount held on deposit for this information.
 	pub deposit: Balance,
 
-
-	pub accountId: AccountId,
-    
-	// Public-key of user  
-	//pub account: AccountId,
+	/// The account that registered the identity, or the key it was recovered onto.
+	pub owner: AccountId,
 
 	/// Information on the identity.
 	pub info: IdentityInfoSel<MaxAdditionalFields>,
@@ -315,18 +309,157 @@ impl<
 	}
 }
 
-impl<
-		Balance: Encode + Decode + MaxEncodedLen + Copy + Clone + Debug + Eq + PartialEq,
-		AccountId: Encode + Decode + MaxEncodedLen + Clone + Debug + Eq + PartialEq ,
-		MaxAdditionalFields: Get<u32>,
-	> Decode for RegistrationSel<Balance, AccountId,    MaxAdditionalFields>
-{
-	fn decode<I: codec::Input>(input: &mut I) -> sp_std::result::Result<Self, codec::Error> {
-		let ( deposit, accountId, info) = Decode::decode(&mut AppendZerosInput::new(input))?;
-		Ok(Self {  deposit, accountId, info })
-	}
+/// Guardians that may together vouch for moving an identity to a new key.
+#[derive(
+	CloneNoBound, Encode, Decode, Eq, MaxEncodedLen, PartialEqNoBound, RuntimeDebugNoBound, TypeInfo,
+)]
+#[codec(mel_bound(
+	AccountId: Encode + Decode + MaxEncodedLen + Clone + Debug + Eq + PartialEq,
+	MaxGuardians: Get<u32>,
+))]
+#[scale_info(skip_type_params(MaxGuardians))]
+pub struct RecoveryConfig<
+	AccountId: Encode + Decode + MaxEncodedLen + Clone + Debug + Eq + PartialEq,
+	MaxGuardians: Get<u32>,
+> {
+	/// The guardian accounts, sorted and without duplicates.
+	pub guardians: BoundedVec<AccountId, MaxGuardians>,
+	/// How many guardians must vouch before the identity can be claimed.
+	pub threshold: u16,
+}
+
+/// A recovery in progress: a new key asking to take over an identity. Keyed by the identity
+/// and the new key, so several keys can try to recover the same identity at once.
+#[derive(
+	CloneNoBound, Encode, Decode, Eq, MaxEncodedLen, PartialEqNoBound, RuntimeDebugNoBound, TypeInfo,
+)]
+#[codec(mel_bound(
+	Balance: Encode + Decode + MaxEncodedLen + Copy + Clone + Debug + Eq + PartialEq,
+	AccountId: Encode + Decode + MaxEncodedLen + Clone + Debug + Eq + PartialEq,
+	BlockNumber: Encode + Decode + MaxEncodedLen + Copy + Clone + Debug + Eq + PartialEq,
+	MaxGuardians: Get<u32>,
+))]
+#[scale_info(skip_type_params(MaxGuardians))]
+pub struct ActiveRecovery<
+	Balance: Encode + Decode + MaxEncodedLen + Copy + Clone + Debug + Eq + PartialEq,
+	AccountId: Encode + Decode + MaxEncodedLen + Clone + Debug + Eq + PartialEq,
+	BlockNumber: Encode + Decode + MaxEncodedLen + Copy + Clone + Debug + Eq + PartialEq,
+	MaxGuardians: Get<u32>,
+> {
+	/// The block the recovery was initiated in; the delay counts from here.
+	pub started: BlockNumber,
+	/// Reserved from the new key until the recovery is claimed or cancelled.
+	pub deposit: Balance,
+	/// The guardians who have vouched so far.
+	pub vouchers: BoundedVec<AccountId, MaxGuardians>,
+}
+
+/// A profile field that can be stored encrypted instead of in [`IdentityInfoSel`].
+#[derive(Encode, Decode, Clone, Copy, Eq, PartialEq, MaxEncodedLen, RuntimeDebug, TypeInfo)]
+pub enum ProfileField {
+	/// See [`IdentityInfoSel::display`].
+	Display,
+	/// See [`IdentityInfoSel::legal`].
+	Legal,
+	/// The email address, which is otherwise never stored.
+	Email,
+	/// See [`IdentityInfoSel::image`].
+	Image,
 }
 
+/// A field value encrypted off-chain to a single key.
+#[derive(
+	CloneNoBound, Encode, Decode, Eq, MaxEncodedLen, PartialEqNoBound, RuntimeDebugNoBound, TypeInfo,
+)]
+#[codec(mel_bound(MaxCiphertextLen: Get<u32>))]
+#[scale_info(skip_type_params(MaxCiphertextLen))]
+pub struct EncryptedPayload<MaxCiphertextLen: Get<u32>> {
+	/// Fingerprint of the key the payload is encrypted to, in the same format as
+	/// [`IdentityInfoSel::pgp_fingerprint`].
+	pub recipient_fingerprint: [u8; 20],
+	/// The encrypted value. The chain never sees the plaintext.
+	pub ciphertext: BoundedVec<u8, MaxCiphertextLen>,
+}
+
+/// Kind of public key listed as a DID verification method.
+#[derive(Encode, Decode, Clone, Copy, Eq, PartialEq, MaxEncodedLen, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum DidKeyType {
+	/// Schnorrkel/Ristretto x25519 public key.
+	Sr25519,
+	/// Ed25519 public key.
+	Ed25519,
+}
+
+/// A public key that can authenticate as, or make assertions for, a DID.
+#[derive(Encode, Decode, Clone, Copy, Eq, PartialEq, MaxEncodedLen, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct DidVerificationMethod {
+	/// The key's scheme.
+	pub key_type: DidKeyType,
+	/// The raw public key.
+	pub public_key: [u8; 32],
+}
+
+/// The parts of an identity's DID document that are kept on chain. Linked accounts and services
+/// are taken from the identity itself when the document is resolved.
+#[derive(
+	CloneNoBound, Encode, Decode, Eq, MaxEncodedLen, PartialEqNoBound, RuntimeDebugNoBound, TypeInfo,
+)]
+#[codec(mel_bound(
+	AccountId: Encode + Decode + MaxEncodedLen + Clone + Debug + Eq + PartialEq,
+	BlockNumber: Encode + Decode + MaxEncodedLen + Copy + Clone + Debug + Eq + PartialEq,
+	MaxDidKeys: Get<u32>,
+))]
+#[scale_info(skip_type_params(MaxDidKeys))]
+pub struct DidDocument<
+	AccountId: Encode + Decode + MaxEncodedLen + Clone + Debug + Eq + PartialEq,
+	BlockNumber: Encode + Decode + MaxEncodedLen + Copy + Clone + Debug + Eq + PartialEq,
+	MaxDidKeys: Get<u32>,
+> {
+	/// The only account that may change the verification methods.
+	pub controller: AccountId,
+	/// Keys that can authenticate as the DID, in the order they were added.
+	pub verification_methods: BoundedVec<DidVerificationMethod, MaxDidKeys>,
+	/// The block the document last changed in.
+	pub updated: BlockNumber,
+}
+
+/// Everything a resolver needs to render the DID document of an identity, as returned by the
+/// runtime API.
+#[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct DidDetails<AccountId> {
+	/// The identity the DID names.
+	pub email_hash: EmailHash,
+	/// The DID controller, if a document has been created.
+	pub controller: Option<AccountId>,
+	/// Keys registered in the on-chain document.
+	pub verification_methods: Vec<DidVerificationMethod>,
+	/// The web3 accounts linked to the identity.
+	pub linked_accounts: Vec<AccountId>,
+	/// Services the identity has been granted access to.
+	pub services: Vec<Vec<u8>>,
+}
+
+/// What front ends need to know about an identity, as returned by the runtime API.
+#[derive(Encode, Decode, Clone, Eq, PartialEq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct IdentitySummary<AccountId> {
+	/// The key the identity is stored under.
+	pub email_hash: EmailHash,
+	/// The account that owns the identity.
+	pub owner: AccountId,
+	/// The web3 accounts linked to the identity.
+	pub linked_accounts: Vec<AccountId>,
+	/// The service the identity was last granted access to, if any.
+	pub service: Option<Vec<u8>>,
+	/// Whether recovery guardians have been set.
+	pub recovery_configured: bool,
+}
 
 
 #[cfg(test)]
@@ -0,0 +1,68 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Placeholder weights for pallet_studentid.
+//!
+//! `enroll_batch` has not been benchmarked yet: its base and per-entry weights are guesses
+//! sized from the storage accesses listed below, not measurements. Overwrite this file with
+//! the generated one once the benchmarks have been run on reference hardware:
+//!
+//! target/release/node-template benchmark --chain=dev --steps=50 --repeat=20
+//! --pallet=pallet_studentid --extrinsic=* --execution=wasm --wasm-execution=compiled
+//! --heap-pages=4096 --output=./pallets/identitysel/src/weights.rs
+//! --template=./.maintain/frame-weight-template.hbs
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use sp_std::marker::PhantomData;
+
+/// Weight functions needed for pallet_studentid.
+pub trait WeightInfo {
+	fn enroll_batch(n: u32, ) -> Weight;
+}
+
+/// Unbenchmarked weights for pallet_studentid, see the module docs.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	// Storage: Identity Registrars (r:1 w:0)
+	// Storage: Identity Identity1Of (r:1 w:1)
+	// Storage: Identity Roles (r:0 w:1)
+	fn enroll_batch(n: u32, ) -> Weight {
+		(12_000_000 as Weight)
+			.saturating_add((21_000_000 as Weight).saturating_mul(n as Weight))
+			.saturating_add(T::DbWeight::get().reads(1 as Weight))
+			.saturating_add(T::DbWeight::get().reads((1 as Weight).saturating_mul(n as Weight)))
+			.saturating_add(T::DbWeight::get().writes((2 as Weight).saturating_mul(n as Weight)))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	// Storage: Identity Registrars (r:1 w:0)
+	// Storage: Identity Identity1Of (r:1 w:1)
+	// Storage: Identity Roles (r:0 w:1)
+	fn enroll_batch(n: u32, ) -> Weight {
+		(12_000_000 as Weight)
+			.saturating_add((21_000_000 as Weight).saturating_mul(n as Weight))
+			.saturating_add(RocksDbWeight::get().reads(1 as Weight))
+			.saturating_add(RocksDbWeight::get().reads((1 as Weight).saturating_mul(n as Weight)))
+			.saturating_add(RocksDbWeight::get().writes((2 as Weight).saturating_mul(n as Weight)))
+	}
+}
@@ -0,0 +1,84 @@
+// This file is part of Substrate.
+
+// Copyright (C) 2020-2021 Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Kitties pallet benchmarking.
+//!
+//! Both benchmarks fill the sender's and the recipient's ownership index with `n` Kitties; the
+//! weights they produce should not depend on `n`. `buy_kitty` sells a bred Kitty at
+//! `MaxRoyalty`, so the breeder's cut is paid too.
+
+#![cfg(feature = "runtime-benchmarks")]
+
+use super::*;
+
+use crate::Pallet as SubstrateKitties;
+use codec::Encode;
+use frame_benchmarking::{account, benchmarks, impl_benchmark_test_suite, whitelisted_caller};
+use frame_support::traits::{Currency, Get};
+use frame_system::RawOrigin;
+use sp_io::hashing::blake2_128;
+use sp_runtime::traits::Bounded;
+
+const SEED: u32 = 0;
+
+// Mint `n` Kitties to `owner` and return the last one.
+fn fill<T: Config>(owner: &T::AccountId, n: u32) -> T::Hash {
+	let mut last = None;
+	for i in 0..n {
+		let dna = (owner, i).using_encoded(blake2_128);
+		last = Some(SubstrateKitties::<T>::mint(owner, Some(dna), Some(Gender::Female)).unwrap());
+	}
+	last.unwrap()
+}
+
+fn max_owned<T: Config>() -> u32 {
+	T::MaxKittyOwned::get().saturating_sub(1).min(1_000)
+}
+
+benchmarks! {
+	transfer {
+		let n in 1 .. max_owned::<T>();
+		let caller: T::AccountId = whitelisted_caller();
+		let recipient: T::AccountId = account("recipient", 0, SEED);
+		let kitty_id = fill::<T>(&caller, n);
+		fill::<T>(&recipient, n - 1);
+	}: _(RawOrigin::Signed(caller), recipient.clone(), kitty_id)
+	verify {
+		assert!(SubstrateKitties::<T>::is_kitty_owner(&kitty_id, &recipient).unwrap());
+		assert_eq!(SubstrateKitties::<T>::owned_kitty_count(&recipient), n);
+	}
+
+	buy_kitty {
+		let n in 1 .. max_owned::<T>();
+		let caller: T::AccountId = whitelisted_caller();
+		let seller: T::AccountId = account("seller", 0, SEED);
+		let breeder: T::AccountId = account("breeder", 0, SEED);
+		T::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value() / 2u32.into());
+		T::Currency::make_free_balance_be(&seller, T::Currency::minimum_balance());
+		T::Currency::make_free_balance_be(&breeder, T::Currency::minimum_balance());
+		let kitty_id = fill::<T>(&seller, n);
+		fill::<T>(&caller, n - 1);
+		<Royalties<T>>::insert(&kitty_id, Royalty { breeder, rate: T::MaxRoyalty::get() });
+		let price = T::Currency::minimum_balance() * 100u32.into();
+		SubstrateKitties::<T>::set_price(RawOrigin::Signed(seller).into(), kitty_id, Some(price))?;
+	}: _(RawOrigin::Signed(caller.clone()), kitty_id, price)
+	verify {
+		assert!(SubstrateKitties::<T>::is_kitty_owner(&kitty_id, &caller).unwrap());
+	}
+}
+
+impl_benchmark_test_suite!(SubstrateKitties, crate::mock::new_test_ext(), crate::mock::Test);
@@ -5,26 +5,36 @@
 /// <https://substrate.io/docs/en/knowledgebase/runtime/frame>
 pub use pallet::*;
 
+mod benchmarking;
+pub mod migrations;
 mod mock;
+#[cfg(test)]
 mod tests;
+pub mod weights;
+
+pub use weights::WeightInfo;
 
 #[frame_support::pallet]
 pub mod pallet {
+	use crate::WeightInfo;
 	use frame_support::pallet_prelude::*;
 	use frame_system::pallet_prelude::*;
 	use frame_support::{
-		sp_runtime::traits::Hash,
-		traits::{ Randomness, Currency, tokens::ExistenceRequirement },
-		transactional
+		sp_runtime::{traits::{AccountIdConversion, Hash, Saturating, UniqueSaturatedInto, Zero}, Permill},
+		traits::{ Randomness, Currency, ReservableCurrency, BalanceStatus, tokens::ExistenceRequirement },
+		transactional, PalletId,
 	};
 	use sp_io::hashing::blake2_128;
 	use scale_info::TypeInfo;
+	use sp_std::{collections::btree_set::BTreeSet, vec::Vec};
 
 	#[cfg(feature = "std")]
 	use frame_support::serde::{Deserialize, Serialize};
 
-	type AccountOf<T> = <T as frame_system::Config>::AccountId;
-	type BalanceOf<T> =
+	pub const STORAGE_VERSION: StorageVersion = StorageVersion::new(3);
+
+	pub(crate) type AccountOf<T> = <T as frame_system::Config>::AccountId;
+	pub(crate) type BalanceOf<T> =
 		<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
 
 	// Struct for holding Kitty information.
@@ -35,11 +45,66 @@ pub mod pallet {
 		pub price: Option<BalanceOf<T>>,
 		pub gender: Gender,
 		pub owner: AccountOf<T>,
+		pub parents: Option<(T::Hash, T::Hash)>,   // `None` for kitties that were not bred
+		pub generation: u32,
+		pub breed_ready_at: T::BlockNumber,   // The kitty cannot breed before this block
 	}
 
-	// Set Gender type in Kitty struct.
+	// An English auction: the highest bid at `end` wins, if it meets `reserve`.
+	#[derive(Clone, Encode, Decode, PartialEq, RuntimeDebug, TypeInfo)]
+	#[scale_info(skip_type_params(T))]
+	pub struct Auction<T: Config> {
+		pub seller: AccountOf<T>,
+		pub reserve: BalanceOf<T>,
+		pub end: T::BlockNumber,
+		// The highest bidder and the amount reserved from them.
+		pub best_bid: Option<(AccountOf<T>, BalanceOf<T>)>,
+	}
+
+	// A standing offer to buy a Kitty, whether or not it is for sale. `amount` is reserved from
+	// the bidder until the offer is accepted, cancelled or expires.
+	#[derive(Clone, Encode, Decode, PartialEq, RuntimeDebug, TypeInfo)]
+	#[scale_info(skip_type_params(T))]
+	pub struct Offer<T: Config> {
+		pub amount: BalanceOf<T>,
+		pub expires: T::BlockNumber,
+	}
+
+	// Terms on which an owner lends a Kitty out.
+	#[derive(Clone, Encode, Decode, PartialEq, RuntimeDebug, TypeInfo)]
+	#[scale_info(skip_type_params(T))]
+	pub struct RentalListing<T: Config> {
+		pub price_per_block: BalanceOf<T>,
+		pub max_duration: T::BlockNumber,
+	}
+
+	// A running rental: `renter` uses the Kitty until the end of block `ends`.
 	#[derive(Clone, Encode, Decode, PartialEq, RuntimeDebug, TypeInfo)]
 	#[scale_info(skip_type_params(T))]
+	pub struct Rental<T: Config> {
+		pub renter: AccountOf<T>,
+		pub ends: T::BlockNumber,
+	}
+
+	// The account that bred a Kitty and its cut of every `buy_kitty` sale.
+	#[derive(Clone, Encode, Decode, PartialEq, RuntimeDebug, TypeInfo)]
+	#[scale_info(skip_type_params(T))]
+	pub struct Royalty<T: Config> {
+		pub breeder: AccountOf<T>,
+		pub rate: Permill,
+	}
+
+	// The `TokenData` of the orml_nft token backing each Kitty.
+	#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo)]
+	#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+	pub struct KittyTokenData {
+		pub dna: [u8; 16],
+		pub gender: Gender,
+	}
+
+	// Set Gender type in Kitty struct.
+	#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo)]
+	#[scale_info(skip_type_params(T))]
 	#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 	pub enum Gender {
 		Male,
@@ -48,16 +113,17 @@ pub mod pallet {
 
 	#[pallet::pallet]
 	#[pallet::generate_store(pub(super) trait Store)]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	// Configure the pallet by specifying the parameters and types on which it depends.
 	#[pallet::config]
-	pub trait Config: frame_system::Config {
+	pub trait Config: frame_system::Config + orml_nft::Config<ClassData = (), TokenData = KittyTokenData> {
 		/// Because this pallet emits events, it depends on the runtime's definition of an event.
 		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
 
-		/// The Currency handler for the Kitties pallet.
-		type Currency: Currency<Self::AccountId>;
+		/// The Currency handler for the Kitties pallet. Auction bids are reserved.
+		type Currency: ReservableCurrency<Self::AccountId>;
 
 		/// The maximum amount of Kitties a single account can own.
 		#[pallet::constant]
@@ -65,6 +131,35 @@ pub mod pallet {
 
 		/// The type of Randomness we want to specify for this pallet.
 		type KittyRandomness: Randomness<Self::Hash, Self::BlockNumber>;
+
+		/// The maximum number of auctions that can end in the same block.
+		#[pallet::constant]
+		type MaxAuctionsPerBlock: Get<u32>;
+
+		/// The maximum number of offers that can expire in the same block.
+		#[pallet::constant]
+		type MaxOffersPerBlock: Get<u32>;
+
+		/// The maximum number of rentals that can end in the same block.
+		#[pallet::constant]
+		type MaxRentalsPerBlock: Get<u32>;
+
+		/// The Kitties pallet's id, used to derive the account that owns the orml_nft class of
+		/// Kitty tokens.
+		#[pallet::constant]
+		type PalletId: Get<PalletId>;
+
+		/// Blocks a generation 0 kitty must wait between breedings. Each generation waits this
+		/// much longer than the previous one.
+		#[pallet::constant]
+		type BreedCooldown: Get<Self::BlockNumber>;
+
+		/// The highest royalty a breeder can take from the sales of their Kitties.
+		#[pallet::constant]
+		type MaxRoyalty: Get<Permill>;
+
+		/// Weight information for extrinsics in this pallet.
+		type WeightInfo: WeightInfo;
 	}
 
 	// Errors.
@@ -88,6 +183,52 @@ pub mod pallet {
 		KittyBidPriceTooLow,
 		/// Ensures that an account has enough funds to purchase a Kitty. 
 		NotEnoughBalance,
+		/// The Kitty is being auctioned, so it cannot be listed, transferred or auctioned again.
+		KittyInAuction,
+		/// The Kitty is not being auctioned.
+		AuctionNotExist,
+		/// An auction must end after the current block.
+		AuctionEndInPast,
+		/// The auction has already ended.
+		AuctionEnded,
+		/// Too many auctions already end in that block.
+		TooManyAuctionsEnding,
+		/// The bid is below the reserve or not above the highest bid.
+		BidTooLow,
+		/// Kitties of the same gender cannot breed.
+		SameGender,
+		/// A parent is still cooling down from its last breeding.
+		BreedingCooldown,
+		/// The orml_nft token backing a Kitty could not be created or moved.
+		KittyTokenFailed,
+		/// The account already has an offer on this Kitty.
+		OfferExists,
+		/// There is no such offer.
+		OfferNotExist,
+		/// An offer must expire after the current block.
+		OfferExpiryInPast,
+		/// Too many offers already expire in that block.
+		TooManyOffersExpiring,
+		/// The offer is below the amount the owner agreed to.
+		OfferTooLow,
+		/// Only the breeder of a Kitty can set its royalty.
+		NotKittyBreeder,
+		/// The royalty is above `MaxRoyalty`.
+		RoyaltyTooHigh,
+		/// The account does not have user rights on the Kitty.
+		NotKittyUser,
+		/// The Kitty is not listed for rent.
+		KittyNotForRent,
+		/// The Kitty is rented out, so it cannot be sold, transferred, auctioned or rented again.
+		KittyRented,
+		/// The Kitty is not rented by the sender.
+		KittyNotRented,
+		/// A rental must last at least one block and at most the listing's `max_duration`.
+		InvalidRentalDuration,
+		/// Too many rentals already end in that block.
+		TooManyRentalsEnding,
+		/// A Kitty with the same id already exists.
+		KittyExists,
 	}
 
 	#[pallet::event]
@@ -101,6 +242,37 @@ pub mod pallet {
 		Transferred(T::AccountId, T::AccountId, T::Hash),
 		/// A Kitty was sucessfully bought. \[buyer, seller, kitty_id, bid_price\]
 		Bought(T::AccountId, T::AccountId, T::Hash, BalanceOf<T>),
+		/// A Kitty was put up for auction. \[seller, kitty_id, reserve, end\]
+		AuctionCreated(T::AccountId, T::Hash, BalanceOf<T>, T::BlockNumber),
+		/// A bid was placed and the previous highest bidder refunded. \[bidder, kitty_id, amount\]
+		BidPlaced(T::AccountId, T::Hash, BalanceOf<T>),
+		/// An auction ended with a winner. \[winner, seller, kitty_id, price\]
+		AuctionWon(T::AccountId, T::AccountId, T::Hash, BalanceOf<T>),
+		/// An auction ended without a winning bid; the seller keeps the Kitty. \[seller, kitty_id\]
+		AuctionUnsold(T::AccountId, T::Hash),
+		/// Two Kitties were bred. \[owner, kitty_id, parent1, parent2\]
+		Bred(T::AccountId, T::Hash, T::Hash, T::Hash),
+		/// An offer was made on a Kitty. \[bidder, kitty_id, amount, expires\]
+		OfferMade(T::AccountId, T::Hash, BalanceOf<T>, T::BlockNumber),
+		/// An offer was withdrawn by its bidder. \[bidder, kitty_id\]
+		OfferCancelled(T::AccountId, T::Hash),
+		/// An offer expired and its funds were released. \[bidder, kitty_id\]
+		OfferExpired(T::AccountId, T::Hash),
+		/// The owner accepted an offer. \[bidder, seller, kitty_id, amount\]
+		OfferAccepted(T::AccountId, T::AccountId, T::Hash, BalanceOf<T>),
+		/// A breeder set the royalty on a Kitty. \[breeder, kitty_id, rate\]
+		RoyaltySet(T::AccountId, T::Hash, Permill),
+		/// A sale was split between seller and breeder.
+		/// \[kitty_id, seller, seller_proceeds, breeder, royalty\]
+		RoyaltyPaid(T::Hash, T::AccountId, BalanceOf<T>, T::AccountId, BalanceOf<T>),
+		/// A Kitty was listed for rent. \[owner, kitty_id, price_per_block, max_duration\]
+		RentalListed(T::AccountId, T::Hash, BalanceOf<T>, T::BlockNumber),
+		/// A Kitty's rental listing was withdrawn. \[owner, kitty_id\]
+		RentalUnlisted(T::AccountId, T::Hash),
+		/// A Kitty was rented. \[renter, kitty_id, ends, cost\]
+		Rented(T::AccountId, T::Hash, T::BlockNumber, BalanceOf<T>),
+		/// A rental ended and user rights went back to the owner. \[renter, kitty_id\]
+		RentalEnded(T::AccountId, T::Hash),
 	}
 
 	// Storage items.
@@ -116,10 +288,76 @@ pub mod pallet {
 	pub(super) type Kitties<T: Config> = StorageMap<_, Twox64Concat, T::Hash, Kitty<T>>;
 
 	#[pallet::storage]
-	#[pallet::getter(fn kitties_owned)]
 	/// Keeps track of what accounts own what Kitty.
-	pub(super) type KittiesOwned<T: Config> =
-		StorageMap<_, Twox64Concat, T::AccountId, BoundedVec<T::Hash, T::MaxKittyOwned>, ValueQuery>;
+	pub(super) type OwnedKitties<T: Config> =
+		StorageDoubleMap<_, Twox64Concat, T::AccountId, Twox64Concat, T::Hash, ()>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn owned_kitty_count)]
+	/// The number of Kitties each account owns, at most `MaxKittyOwned`.
+	pub(super) type OwnedKittyCount<T: Config> = StorageMap<_, Twox64Concat, T::AccountId, u32, ValueQuery>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn kitty_class)]
+	/// The orml_nft class all Kitty tokens belong to, created with the first Kitty.
+	pub(super) type KittyClass<T: Config> = StorageValue<_, T::ClassId>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn kitty_token)]
+	/// The orml_nft token backing each Kitty.
+	pub(super) type KittyTokens<T: Config> = StorageMap<_, Twox64Concat, T::Hash, (T::ClassId, T::TokenId)>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn auctions)]
+	/// Running auctions, by Kitty.
+	pub(super) type Auctions<T: Config> = StorageMap<_, Twox64Concat, T::Hash, Auction<T>>;
+
+	#[pallet::storage]
+	/// The auctions settled at the end of each block.
+	pub(super) type AuctionsEndingAt<T: Config> =
+		StorageMap<_, Twox64Concat, T::BlockNumber, BoundedVec<T::Hash, T::MaxAuctionsPerBlock>, ValueQuery>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn rental_listings)]
+	/// Kitties their owners offer for rent.
+	pub(super) type RentalListings<T: Config> = StorageMap<_, Twox64Concat, T::Hash, RentalListing<T>>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn rentals)]
+	/// Running rentals, by Kitty.
+	pub(super) type Rentals<T: Config> = StorageMap<_, Twox64Concat, T::Hash, Rental<T>>;
+
+	#[pallet::storage]
+	/// The rentals ended at the end of each block.
+	pub(super) type RentalsEndingAt<T: Config> =
+		StorageMap<_, Twox64Concat, T::BlockNumber, BoundedVec<T::Hash, T::MaxRentalsPerBlock>, ValueQuery>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn royalties)]
+	/// The breeder of each bred Kitty and the royalty they take from its sales.
+	pub(super) type Royalties<T: Config> = StorageMap<_, Twox64Concat, T::Hash, Royalty<T>>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn offers)]
+	/// Open offers, by Kitty and bidder.
+	pub(super) type Offers<T: Config> =
+		StorageDoubleMap<_, Twox64Concat, T::Hash, Twox64Concat, T::AccountId, Offer<T>>;
+
+	#[pallet::storage]
+	#[pallet::getter(fn offers_by_bidder)]
+	/// The Kitties each account has an open offer on.
+	pub(super) type OffersByBidder<T: Config> =
+		StorageDoubleMap<_, Twox64Concat, T::AccountId, Twox64Concat, T::Hash, ()>;
+
+	#[pallet::storage]
+	/// The offers released at the end of each block.
+	pub(super) type OffersExpiringAt<T: Config> = StorageMap<
+		_,
+		Twox64Concat,
+		T::BlockNumber,
+		BoundedVec<(T::Hash, T::AccountId), T::MaxOffersPerBlock>,
+		ValueQuery,
+	>;
 
 	// Our pallet's genesis configuration.
 	#[pallet::genesis_config]
@@ -146,7 +384,42 @@ pub mod pallet {
 	}
 
 	#[pallet::hooks]
-	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {}
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_runtime_upgrade() -> Weight {
+			crate::migrations::migrate::<T>()
+		}
+
+		fn on_initialize(n: T::BlockNumber) -> Weight {
+			// Account for the settlement and expiry done in `on_finalize`.
+			let ending = <AuctionsEndingAt<T>>::decode_len(n).unwrap_or(0) as Weight;
+			let expiring = <OffersExpiringAt<T>>::decode_len(n).unwrap_or(0) as Weight;
+			let returning = <RentalsEndingAt<T>>::decode_len(n).unwrap_or(0) as Weight;
+			T::DbWeight::get().reads_writes(
+				3 + 3 * ending + 2 * expiring + returning,
+				3 + 6 * ending + 3 * expiring + returning,
+			)
+		}
+
+		fn on_finalize(n: T::BlockNumber) {
+			for kitty_id in <AuctionsEndingAt<T>>::take(n) {
+				if let Some(auction) = <Auctions<T>>::take(&kitty_id) {
+					Self::settle_auction(kitty_id, auction);
+				}
+			}
+			for (kitty_id, bidder) in <OffersExpiringAt<T>>::take(n) {
+				if let Some(offer) = <Offers<T>>::take(&kitty_id, &bidder) {
+					<OffersByBidder<T>>::remove(&bidder, &kitty_id);
+					T::Currency::unreserve(&bidder, offer.amount);
+					Self::deposit_event(Event::OfferExpired(bidder, kitty_id));
+				}
+			}
+			for kitty_id in <RentalsEndingAt<T>>::take(n) {
+				if let Some(rental) = <Rentals<T>>::take(&kitty_id) {
+					Self::deposit_event(Event::RentalEnded(rental.renter, kitty_id));
+				}
+			}
+		}
+	}
 
 	// Dispatchable functions allows users to interact with the pallet and invoke state changes.
 	// These functions materialize as "extrinsics", which are often compared to transactions.
@@ -183,6 +456,8 @@ pub mod pallet {
 
 			// Ensure the kitty exists and is called by the kitty owner
 			ensure!(Self::is_kitty_owner(&kitty_id, &sender)?, <Error<T>>::NotKittyOwner);
+			ensure!(!<Auctions<T>>::contains_key(&kitty_id), <Error<T>>::KittyInAuction);
+			ensure!(!<Rentals<T>>::contains_key(&kitty_id), <Error<T>>::KittyRented);
 
 			let mut kitty = Self::kitties(&kitty_id).ok_or(<Error<T>>::KittyNotExist)?;
 
@@ -199,7 +474,7 @@ pub mod pallet {
 		///
 		/// Any account that holds a kitty can send it to another Account. This will reset the asking
 		/// price of the kitty, marking it not for sale.
-		#[pallet::weight(100)]
+		#[pallet::weight(T::WeightInfo::transfer())]
 		pub fn transfer(
 			origin: OriginFor<T>, 
 			to: T::AccountId, 
@@ -209,13 +484,14 @@ pub mod pallet {
 
 			// Ensure the kitty exists and is called by the kitty owner
 			ensure!(Self::is_kitty_owner(&kitty_id, &from)?, <Error<T>>::NotKittyOwner);
+			ensure!(!<Auctions<T>>::contains_key(&kitty_id), <Error<T>>::KittyInAuction);
+			ensure!(!<Rentals<T>>::contains_key(&kitty_id), <Error<T>>::KittyRented);
 
 			// Verify the kitty is not transferring back to its owner.
 			ensure!(from != to, <Error<T>>::TransferToSelf);
 
 			// Verify the recipient has the capacity to receive one more kitty
-			let to_owned = <KittiesOwned<T>>::get(&to);
-			ensure!((to_owned.len() as u32) < T::MaxKittyOwned::get(), <Error<T>>::ExceedMaxKittyOwned);
+			Self::ensure_can_own(&to)?;
 
 			Self::transfer_kitty_to(&kitty_id, &to)?;
 
@@ -230,7 +506,7 @@ pub mod pallet {
 		/// This will reset the asking price of the kitty, marking it not for sale.
 		/// Marking this method `transactional` so when an error is returned, we ensure no storage is changed.
 		#[transactional]
-		#[pallet::weight(100)]
+		#[pallet::weight(T::WeightInfo::buy_kitty())]
 		pub fn buy_kitty(
 			origin: OriginFor<T>, 
 			kitty_id: T::Hash, 
@@ -253,13 +529,12 @@ pub mod pallet {
 			ensure!(T::Currency::free_balance(&buyer) >= bid_price, <Error<T>>::NotEnoughBalance);
 
 			// Verify the buyer has the capacity to receive one more kitty
-			let to_owned = <KittiesOwned<T>>::get(&buyer);
-			ensure!((to_owned.len() as u32) < T::MaxKittyOwned::get(), <Error<T>>::ExceedMaxKittyOwned);
+			Self::ensure_can_own(&buyer)?;
 
 			let seller = kitty.owner.clone();
 
-			// Transfer the amount from buyer to seller
-			T::Currency::transfer(&buyer, &seller, bid_price, ExistenceRequirement::KeepAlive)?;
+			// Transfer the amount from buyer to seller, less the breeder's royalty
+			Self::pay_sale(&kitty_id, &buyer, &seller, bid_price, false)?;
 
 			// Transfer the kitty from seller to buyer
 			Self::transfer_kitty_to(&kitty_id, &buyer)?;
@@ -281,12 +556,279 @@ pub mod pallet {
 		) -> DispatchResult {
 			let sender = ensure_signed(origin)?;
 
-			// Check: Verify `sender` owns both kitties (and both kitties exist).
-			ensure!(Self::is_kitty_owner(&kid1, &sender)?, <Error<T>>::NotKittyOwner);
-			ensure!(Self::is_kitty_owner(&kid2, &sender)?, <Error<T>>::NotKittyOwner);
+			// Check: Verify `sender` may use both kitties (and both kitties exist). A rented kitty is
+			// bred by its renter, not its owner.
+			ensure!(Self::is_kitty_user(&kid1, &sender)?, <Error<T>>::NotKittyUser);
+			ensure!(Self::is_kitty_user(&kid2, &sender)?, <Error<T>>::NotKittyUser);
+
+			let mut parent1 = Self::kitties(&kid1).ok_or(<Error<T>>::KittyNotExist)?;
+			let mut parent2 = Self::kitties(&kid2).ok_or(<Error<T>>::KittyNotExist)?;
+			ensure!(parent1.gender != parent2.gender, <Error<T>>::SameGender);
+
+			let now = <frame_system::Pallet<T>>::block_number();
+			ensure!(parent1.breed_ready_at <= now && parent2.breed_ready_at <= now, <Error<T>>::BreedingCooldown);
+
+			let generation = parent1.generation.max(parent2.generation).saturating_add(1);
+			let kitty = Kitty::<T> {
+				dna: Self::breed_dna(&kid1, &kid2)?,
+				price: None,
+				gender: Self::gen_gender(),
+				owner: sender.clone(),
+				parents: Some((kid1, kid2)),
+				generation,
+				breed_ready_at: now.saturating_add(Self::breed_cooldown(generation)),
+			};
+			let kitty_id = Self::insert_kitty(kitty)?;
+			<Royalties<T>>::insert(&kitty_id, Royalty { breeder: sender.clone(), rate: Permill::zero() });
+
+			parent1.breed_ready_at = now.saturating_add(Self::breed_cooldown(parent1.generation));
+			parent2.breed_ready_at = now.saturating_add(Self::breed_cooldown(parent2.generation));
+			<Kitties<T>>::insert(&kid1, parent1);
+			<Kitties<T>>::insert(&kid2, parent2);
+
+			Self::deposit_event(Event::Bred(sender, kitty_id, kid1, kid2));
+
+			Ok(())
+		}
+
+		/// Set the royalty the breeder of a Kitty takes from each sale of it, whether through
+		/// `buy_kitty`, an accepted offer or an auction, up to `MaxRoyalty`.
+		#[pallet::weight(100)]
+		pub fn set_royalty(
+			origin: OriginFor<T>,
+			kitty_id: T::Hash,
+			rate: Permill,
+		) -> DispatchResult {
+			let sender = ensure_signed(origin)?;
+
+			ensure!(rate <= T::MaxRoyalty::get(), <Error<T>>::RoyaltyTooHigh);
+			<Royalties<T>>::try_mutate(&kitty_id, |royalty| match royalty {
+				Some(royalty) if royalty.breeder == sender => {
+					royalty.rate = rate;
+					Ok(())
+				},
+				_ => Err(<Error<T>>::NotKittyBreeder),
+			})?;
+
+			Self::deposit_event(Event::RoyaltySet(sender, kitty_id, rate));
+
+			Ok(())
+		}
+
+		/// List a Kitty for rent at `price_per_block`, for at most `max_duration` blocks at a
+		/// time. Listing again replaces the terms for future rentals.
+		#[pallet::weight(100)]
+		pub fn list_for_rent(
+			origin: OriginFor<T>,
+			kitty_id: T::Hash,
+			price_per_block: BalanceOf<T>,
+			max_duration: T::BlockNumber,
+		) -> DispatchResult {
+			let owner = ensure_signed(origin)?;
+
+			ensure!(Self::is_kitty_owner(&kitty_id, &owner)?, <Error<T>>::NotKittyOwner);
+			ensure!(!max_duration.is_zero(), <Error<T>>::InvalidRentalDuration);
+			<RentalListings<T>>::insert(&kitty_id, RentalListing { price_per_block, max_duration });
+
+			Self::deposit_event(Event::RentalListed(owner, kitty_id, price_per_block, max_duration));
+
+			Ok(())
+		}
+
+		/// Stop renting a Kitty out. A running rental is not affected.
+		#[pallet::weight(100)]
+		pub fn unlist_rental(origin: OriginFor<T>, kitty_id: T::Hash) -> DispatchResult {
+			let owner = ensure_signed(origin)?;
+
+			ensure!(Self::is_kitty_owner(&kitty_id, &owner)?, <Error<T>>::NotKittyOwner);
+			ensure!(<RentalListings<T>>::take(&kitty_id).is_some(), <Error<T>>::KittyNotForRent);
+
+			Self::deposit_event(Event::RentalUnlisted(owner, kitty_id));
+
+			Ok(())
+		}
+
+		/// Rent a listed Kitty for `duration` blocks, paying the owner up front. The renter
+		/// gets user rights, such as breeding, until the rental ends in `on_finalize`; the owner
+		/// cannot sell or transfer the Kitty meanwhile.
+		#[transactional]
+		#[pallet::weight(100)]
+		pub fn rent(
+			origin: OriginFor<T>,
+			kitty_id: T::Hash,
+			duration: T::BlockNumber,
+		) -> DispatchResult {
+			let renter = ensure_signed(origin)?;
+
+			let listing = Self::rental_listings(&kitty_id).ok_or(<Error<T>>::KittyNotForRent)?;
+			let kitty = Self::kitties(&kitty_id).ok_or(<Error<T>>::KittyNotExist)?;
+			ensure!(kitty.owner != renter, <Error<T>>::BuyerIsKittyOwner);
+			ensure!(!<Rentals<T>>::contains_key(&kitty_id), <Error<T>>::KittyRented);
+			ensure!(!<Auctions<T>>::contains_key(&kitty_id), <Error<T>>::KittyInAuction);
+			ensure!(
+				!duration.is_zero() && duration <= listing.max_duration,
+				<Error<T>>::InvalidRentalDuration
+			);
+
+			let ends = <frame_system::Pallet<T>>::block_number().saturating_add(duration);
+			<RentalsEndingAt<T>>::try_mutate(ends, |ending| ending.try_push(kitty_id))
+				.map_err(|_| <Error<T>>::TooManyRentalsEnding)?;
+
+			let blocks: u32 = duration.unique_saturated_into();
+			let cost = listing.price_per_block.saturating_mul(blocks.into());
+			T::Currency::transfer(&renter, &kitty.owner, cost, ExistenceRequirement::KeepAlive)?;
+			<Rentals<T>>::insert(&kitty_id, Rental { renter: renter.clone(), ends });
+
+			Self::deposit_event(Event::Rented(renter, kitty_id, ends, cost));
+
+			Ok(())
+		}
+
+		/// Hand a rented Kitty back before the rental ends. Nothing is refunded.
+		#[pallet::weight(100)]
+		pub fn return_kitty(origin: OriginFor<T>, kitty_id: T::Hash) -> DispatchResult {
+			let renter = ensure_signed(origin)?;
+
+			let rental = Self::rentals(&kitty_id)
+				.filter(|rental| rental.renter == renter)
+				.ok_or(<Error<T>>::KittyNotRented)?;
+			<Rentals<T>>::remove(&kitty_id);
+			<RentalsEndingAt<T>>::mutate(rental.ends, |ending| ending.retain(|id| *id != kitty_id));
+
+			Self::deposit_event(Event::RentalEnded(renter, kitty_id));
+
+			Ok(())
+		}
+
+		/// Put a Kitty up for an English auction ending at block `end`.
+		///
+		/// Any asking price is removed; the Kitty cannot be listed or transferred until the
+		/// auction is settled in `on_finalize` of block `end`.
+		#[pallet::weight(100)]
+		pub fn create_auction(
+			origin: OriginFor<T>,
+			kitty_id: T::Hash,
+			reserve: BalanceOf<T>,
+			end: T::BlockNumber,
+		) -> DispatchResult {
+			let seller = ensure_signed(origin)?;
+
+			ensure!(Self::is_kitty_owner(&kitty_id, &seller)?, <Error<T>>::NotKittyOwner);
+			ensure!(!<Auctions<T>>::contains_key(&kitty_id), <Error<T>>::KittyInAuction);
+			ensure!(!<Rentals<T>>::contains_key(&kitty_id), <Error<T>>::KittyRented);
+			ensure!(end > <frame_system::Pallet<T>>::block_number(), <Error<T>>::AuctionEndInPast);
+
+			<AuctionsEndingAt<T>>::try_mutate(end, |ending| ending.try_push(kitty_id))
+				.map_err(|_| <Error<T>>::TooManyAuctionsEnding)?;
+			<Kitties<T>>::mutate(&kitty_id, |kitty| {
+				if let Some(kitty) = kitty {
+					kitty.price = None;
+				}
+			});
+			<Auctions<T>>::insert(&kitty_id, Auction { seller: seller.clone(), reserve, end, best_bid: None });
+
+			Self::deposit_event(Event::AuctionCreated(seller, kitty_id, reserve, end));
+
+			Ok(())
+		}
+
+		/// Bid on an auctioned Kitty. The bid is reserved until the bidder is outbid or the
+		/// auction ends.
+		#[transactional]
+		#[pallet::weight(100)]
+		pub fn bid(
+			origin: OriginFor<T>,
+			kitty_id: T::Hash,
+			amount: BalanceOf<T>,
+		) -> DispatchResult {
+			let bidder = ensure_signed(origin)?;
+
+			let mut auction = Self::auctions(&kitty_id).ok_or(<Error<T>>::AuctionNotExist)?;
+			ensure!(auction.seller != bidder, <Error<T>>::BuyerIsKittyOwner);
+			ensure!(<frame_system::Pallet<T>>::block_number() < auction.end, <Error<T>>::AuctionEnded);
+			ensure!(amount >= auction.reserve, <Error<T>>::BidTooLow);
+			if let Some((_, best)) = &auction.best_bid {
+				ensure!(amount > *best, <Error<T>>::BidTooLow);
+			}
+
+			// The winner must be able to receive the Kitty when the auction is settled.
+			Self::ensure_can_own(&bidder)?;
+
+			if let Some((previous, refund)) = auction.best_bid.take() {
+				T::Currency::unreserve(&previous, refund);
+			}
+			T::Currency::reserve(&bidder, amount).map_err(|_| <Error<T>>::NotEnoughBalance)?;
+			auction.best_bid = Some((bidder.clone(), amount));
+			<Auctions<T>>::insert(&kitty_id, auction);
+
+			Self::deposit_event(Event::BidPlaced(bidder, kitty_id, amount));
+
+			Ok(())
+		}
+
+		/// Offer to buy a Kitty, listed or not. `amount` is reserved until the owner accepts
+		/// the offer, the bidder cancels it or it expires at the end of block `expires`.
+		#[transactional]
+		#[pallet::weight(100)]
+		pub fn make_offer(
+			origin: OriginFor<T>,
+			kitty_id: T::Hash,
+			amount: BalanceOf<T>,
+			expires: T::BlockNumber,
+		) -> DispatchResult {
+			let bidder = ensure_signed(origin)?;
+
+			ensure!(!Self::is_kitty_owner(&kitty_id, &bidder)?, <Error<T>>::BuyerIsKittyOwner);
+			ensure!(!<Offers<T>>::contains_key(&kitty_id, &bidder), <Error<T>>::OfferExists);
+			ensure!(expires > <frame_system::Pallet<T>>::block_number(), <Error<T>>::OfferExpiryInPast);
+
+			<OffersExpiringAt<T>>::try_mutate(expires, |expiring| expiring.try_push((kitty_id, bidder.clone())))
+				.map_err(|_| <Error<T>>::TooManyOffersExpiring)?;
+			T::Currency::reserve(&bidder, amount).map_err(|_| <Error<T>>::NotEnoughBalance)?;
+			<Offers<T>>::insert(&kitty_id, &bidder, Offer { amount, expires });
+			<OffersByBidder<T>>::insert(&bidder, &kitty_id, ());
+
+			Self::deposit_event(Event::OfferMade(bidder, kitty_id, amount, expires));
+
+			Ok(())
+		}
+
+		/// Withdraw an offer and release its funds.
+		#[pallet::weight(100)]
+		pub fn cancel_offer(origin: OriginFor<T>, kitty_id: T::Hash) -> DispatchResult {
+			let bidder = ensure_signed(origin)?;
+
+			let offer = Self::remove_offer(&kitty_id, &bidder).ok_or(<Error<T>>::OfferNotExist)?;
+			T::Currency::unreserve(&bidder, offer.amount);
 
-			let new_dna = Self::breed_dna(&kid1, &kid2)?;
-			Self::mint(&sender, Some(new_dna), None)?;
+			Self::deposit_event(Event::OfferCancelled(bidder, kitty_id));
+
+			Ok(())
+		}
+
+		/// Accept `bidder`'s offer: the Kitty goes to the bidder and the reserved amount to the
+		/// owner, less the breeder's royalty. `min_amount` guards against the offer being
+		/// replaced by a lower one.
+		#[transactional]
+		#[pallet::weight(100)]
+		pub fn accept_offer(
+			origin: OriginFor<T>,
+			kitty_id: T::Hash,
+			bidder: T::AccountId,
+			min_amount: BalanceOf<T>,
+		) -> DispatchResult {
+			let seller = ensure_signed(origin)?;
+
+			ensure!(Self::is_kitty_owner(&kitty_id, &seller)?, <Error<T>>::NotKittyOwner);
+			ensure!(!<Auctions<T>>::contains_key(&kitty_id), <Error<T>>::KittyInAuction);
+
+			let offer = Self::remove_offer(&kitty_id, &bidder).ok_or(<Error<T>>::OfferNotExist)?;
+			ensure!(offer.amount >= min_amount, <Error<T>>::OfferTooLow);
+
+			Self::transfer_kitty_to(&kitty_id, &bidder)?;
+			Self::pay_sale(&kitty_id, &bidder, &seller, offer.amount, true)?;
+
+			Self::deposit_event(Event::OfferAccepted(bidder, seller, kitty_id, offer.amount));
 
 			Ok(())
 		}
@@ -295,8 +837,15 @@ pub mod pallet {
 	//** Our helper functions.**//
 
 	impl<T: Config> Pallet<T> {
+		/// The subject to draw randomness for `purpose` with. The randomness source may only
+		/// change once per round (or never, on a fresh chain), so the mint count and the parent
+		/// block hash are mixed in to give every kitty its own draw.
+		fn random_subject(purpose: &[u8]) -> Vec<u8> {
+			(purpose, Self::kitty_cnt(), <frame_system::Pallet<T>>::parent_hash()).encode()
+		}
+
 		fn gen_gender() -> Gender {
-			let random = T::KittyRandomness::random(&b"gender"[..]).0;
+			let random = T::KittyRandomness::random(&Self::random_subject(b"gender")).0;
 			match random.as_ref()[0] % 2 {
 				0 => Gender::Male,
 				_ => Gender::Female,
@@ -305,7 +854,7 @@ pub mod pallet {
 
 		fn gen_dna() -> [u8; 16] {
 			let payload = (
-				T::KittyRandomness::random(&b"dna"[..]).0,
+				T::KittyRandomness::random(&Self::random_subject(b"dna")).0,
 				<frame_system::Pallet<T>>::block_number(),
 			);
 			payload.using_encoded(blake2_128)
@@ -322,6 +871,43 @@ pub mod pallet {
 			Ok(new_dna)
 		}
 
+		// How long a kitty of `generation` waits between breedings.
+		fn breed_cooldown(generation: u32) -> T::BlockNumber {
+			T::BreedCooldown::get().saturating_mul(generation.saturating_add(1).into())
+		}
+
+		/// The family tree of `kitty_id`, walked breadth-first for up to `generations` levels:
+		/// each known ancestor (and the kitty itself first) with its parents. Shared ancestors
+		/// are listed once.
+		pub fn lineage(
+			kitty_id: T::Hash,
+			generations: u32,
+		) -> Vec<(T::Hash, Option<(T::Hash, T::Hash)>)> {
+			let mut tree = Vec::new();
+			let mut seen = BTreeSet::new();
+			let mut level = sp_std::vec![kitty_id];
+			for _ in 0..=generations {
+				let mut next = Vec::new();
+				for id in level {
+					if !seen.insert(id) {
+						continue;
+					}
+					if let Some(kitty) = Self::kitties(&id) {
+						if let Some((p1, p2)) = kitty.parents {
+							next.push(p1);
+							next.push(p2);
+						}
+						tree.push((id, kitty.parents));
+					}
+				}
+				if next.is_empty() {
+					break;
+				}
+				level = next;
+			}
+			tree
+		}
+
 		// Helper to mint a Kitty.
 		pub fn mint(
 			owner: &T::AccountId,
@@ -333,24 +919,125 @@ pub mod pallet {
 				price: None,
 				gender: gender.unwrap_or_else(Self::gen_gender),
 				owner: owner.clone(),
+				parents: None,
+				generation: 0,
+				breed_ready_at: Zero::zero(),
 			};
+			Self::insert_kitty(kitty)
+		}
 
-			let kitty_id = T::Hashing::hash_of(&kitty);
+		// Store a new Kitty, add it to its owner's Kitties and mint its token.
+		#[transactional]
+		fn insert_kitty(kitty: Kitty<T>) -> Result<T::Hash, Error<T>> {
+			let owner = kitty.owner.clone();
+			// Two Kitties with the same owner, dna and gender would otherwise share an id, so the
+			// id also commits to the mint count.
+			let kitty_id = T::Hashing::hash_of(&(&kitty, Self::kitty_cnt()));
+			ensure!(!<Kitties<T>>::contains_key(&kitty_id), <Error<T>>::KittyExists);
 
 			// Performs this operation first as it may fail
 			let new_cnt = Self::kitty_cnt().checked_add(1)
 				.ok_or(<Error<T>>::KittyCntOverflow)?;
 
 			// Performs this operation first because as it may fail
-			<KittiesOwned<T>>::try_mutate(&owner, |kitty_vec| {
-				kitty_vec.try_push(kitty_id)
-			}).map_err(|_| <Error<T>>::ExceedMaxKittyOwned)?;
+			Self::add_owned(&owner, &kitty_id)?;
 
+			Self::mint_token(&kitty_id, &kitty)?;
 			<Kitties<T>>::insert(kitty_id, kitty);
 			<KittyCnt<T>>::put(new_cnt);
 			Ok(kitty_id)
 		}
 
+		// Hand the Kitty to the highest bidder and pay the seller, less the breeder's royalty,
+		// from the reserved bid. If the winner can no longer receive the Kitty, the bid is
+		// refunded instead.
+		fn settle_auction(kitty_id: T::Hash, auction: Auction<T>) {
+			let Auction { seller, best_bid, .. } = auction;
+			if let Some((winner, price)) = best_bid {
+				if Self::transfer_kitty_to(&kitty_id, &winner).is_ok() {
+					let _ = Self::pay_sale(&kitty_id, &winner, &seller, price, true);
+					Self::deposit_event(Event::AuctionWon(winner, seller, kitty_id, price));
+					return;
+				}
+				T::Currency::unreserve(&winner, price);
+			}
+			Self::deposit_event(Event::AuctionUnsold(seller, kitty_id));
+		}
+
+		// Pay `price` for a sold Kitty from `buyer` to `seller`, splitting off the breeder's
+		// royalty. The price is taken from the buyer's reserved balance if `reserved`, e.g. for
+		// offers and auction bids, and from their free balance otherwise.
+		fn pay_sale(
+			kitty_id: &T::Hash,
+			buyer: &T::AccountId,
+			seller: &T::AccountId,
+			price: BalanceOf<T>,
+			reserved: bool,
+		) -> DispatchResult {
+			let pay = |to: &T::AccountId, amount: BalanceOf<T>| -> DispatchResult {
+				if reserved {
+					let missing = T::Currency::repatriate_reserved(buyer, to, amount, BalanceStatus::Free)?;
+					ensure!(missing.is_zero(), <Error<T>>::NotEnoughBalance);
+					Ok(())
+				} else {
+					T::Currency::transfer(buyer, to, amount, ExistenceRequirement::KeepAlive)
+				}
+			};
+
+			match Self::royalties(kitty_id) {
+				Some(Royalty { breeder, rate }) if breeder != *seller && !rate.is_zero() => {
+					let royalty = rate * price;
+					let proceeds = price.saturating_sub(royalty);
+					pay(&breeder, royalty)?;
+					pay(seller, proceeds)?;
+					Self::deposit_event(Event::RoyaltyPaid(*kitty_id, seller.clone(), proceeds, breeder, royalty));
+				},
+				_ => pay(seller, price)?,
+			}
+			Ok(())
+		}
+
+		// Remove an offer from all its indexes, leaving its funds reserved.
+		fn remove_offer(kitty_id: &T::Hash, bidder: &T::AccountId) -> Option<Offer<T>> {
+			let offer = <Offers<T>>::take(kitty_id, bidder)?;
+			<OffersByBidder<T>>::remove(bidder, kitty_id);
+			<OffersExpiringAt<T>>::mutate(offer.expires, |expiring| {
+				expiring.retain(|(k, b)| k != kitty_id || b != bidder)
+			});
+			Some(offer)
+		}
+
+		/// The Kitties `owner` owns, in no particular order.
+		pub fn kitties_owned(owner: &T::AccountId) -> Vec<T::Hash> {
+			<OwnedKitties<T>>::iter_key_prefix(owner).collect()
+		}
+
+		fn ensure_can_own(who: &T::AccountId) -> Result<(), Error<T>> {
+			ensure!(Self::owned_kitty_count(who) < T::MaxKittyOwned::get(), <Error<T>>::ExceedMaxKittyOwned);
+			Ok(())
+		}
+
+		// Record `kitty_id` as owned by `owner`, within `MaxKittyOwned`.
+		fn add_owned(owner: &T::AccountId, kitty_id: &T::Hash) -> Result<(), Error<T>> {
+			<OwnedKittyCount<T>>::try_mutate(owner, |count| {
+				ensure!(*count < T::MaxKittyOwned::get(), <Error<T>>::ExceedMaxKittyOwned);
+				*count += 1;
+				Ok(())
+			})?;
+			<OwnedKitties<T>>::insert(owner, kitty_id, ());
+			Ok(())
+		}
+
+		/// Whether `acct` has user rights on a Kitty: its renter while it is rented out, its
+		/// owner otherwise.
+		pub fn is_kitty_user(kitty_id: &T::Hash, acct: &T::AccountId) -> Result<bool, Error<T>> {
+			let kitty = Self::kitties(kitty_id).ok_or(<Error<T>>::KittyNotExist)?;
+			Ok(match Self::rentals(kitty_id) {
+				Some(rental) => rental.renter == *acct,
+				None => kitty.owner == *acct,
+			})
+		}
+
 		pub fn is_kitty_owner(kitty_id: &T::Hash, acct: &T::AccountId) -> Result<bool, Error<T>> {
 			match Self::kitties(kitty_id) {
 				Some(kitty) => Ok(kitty.owner == *acct),
@@ -358,35 +1045,59 @@ pub mod pallet {
 			}
 		}
 
+		/// The account that owns the orml_nft class of Kitty tokens.
+		pub fn account_id() -> T::AccountId {
+			T::PalletId::get().into_account()
+		}
+
+		// Mint the orml_nft token backing `kitty`, creating the Kitty class first if needed.
+		pub(crate) fn mint_token(kitty_id: &T::Hash, kitty: &Kitty<T>) -> Result<(), Error<T>> {
+			let class_id = match Self::kitty_class() {
+				Some(class_id) => class_id,
+				None => {
+					let class_id = orml_nft::Pallet::<T>::create_class(&Self::account_id(), Vec::new(), ())
+						.map_err(|_| <Error<T>>::KittyTokenFailed)?;
+					<KittyClass<T>>::put(class_id);
+					class_id
+				},
+			};
+			let data = KittyTokenData { dna: kitty.dna, gender: kitty.gender.clone() };
+			let token_id = orml_nft::Pallet::<T>::mint(&kitty.owner, class_id, Vec::new(), data)
+				.map_err(|_| <Error<T>>::KittyTokenFailed)?;
+			<KittyTokens<T>>::insert(kitty_id, (class_id, token_id));
+			Ok(())
+		}
+
+		// The one path that moves a Kitty: its record, both owners' lists and its token change
+		// together or not at all.
 		#[transactional]
 		pub fn transfer_kitty_to(
 			kitty_id: &T::Hash,
 			to: &T::AccountId,
 		) -> Result<(), Error<T>> {
 			let mut kitty = Self::kitties(&kitty_id).ok_or(<Error<T>>::KittyNotExist)?;
+			ensure!(!<Rentals<T>>::contains_key(kitty_id), <Error<T>>::KittyRented);
 
 			let prev_owner = kitty.owner.clone();
 
-			// Remove `kitty_id` from the KittyOwned vector of `prev_kitty_owner`
-			<KittiesOwned<T>>::try_mutate(&prev_owner, |owned| {
-				if let Some(ind) = owned.iter().position(|&id| id == *kitty_id) {
-					owned.swap_remove(ind);
-					return Ok(());
-				}
-				Err(())
-			}).map_err(|_| <Error<T>>::KittyNotExist)?;
+			// Remove `kitty_id` from the Kitties owned by `prev_owner`
+			ensure!(<OwnedKitties<T>>::take(&prev_owner, kitty_id).is_some(), <Error<T>>::KittyNotExist);
+			<OwnedKittyCount<T>>::mutate(&prev_owner, |count| *count = count.saturating_sub(1));
 
 			// Update the kitty owner
 			kitty.owner = to.clone();
 			// Reset the ask price so the kitty is not for sale until `set_price()` is called
 			// by the current owner.
 			kitty.price = None;
+			// Nor is it for rent until the new owner lists it.
+			<RentalListings<T>>::remove(kitty_id);
 
 			<Kitties<T>>::insert(kitty_id, kitty);
 
-			<KittiesOwned<T>>::try_mutate(to, |vec| {
-				vec.try_push(*kitty_id)
-			}).map_err(|_| <Error<T>>::ExceedMaxKittyOwned)?;
+			Self::add_owned(to, kitty_id)?;
+
+			let token = Self::kitty_token(kitty_id).ok_or(<Error<T>>::KittyTokenFailed)?;
+			orml_nft::Pallet::<T>::transfer(&prev_owner, to, token).map_err(|_| <Error<T>>::KittyTokenFailed)?;
 
 			Ok(())
 		}
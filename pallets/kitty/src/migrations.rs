@@ -0,0 +1,112 @@
+//! Storage migrations for the kitties pallet.
+//!
+//! Every layout change bumps [`STORAGE_VERSION`] and adds a `vN` module here. [`migrate`] runs, in
+//! order, the steps the on-chain version has not seen yet and is called from the pallet's
+//! `on_runtime_upgrade`.
+
+use crate::pallet::*;
+use frame_support::{
+	traits::{Get, StorageVersion},
+	weights::Weight,
+};
+
+/// The migration steps, by the version they migrate to.
+fn steps<T: Config>() -> [(u16, fn() -> Weight); 3] {
+	[(1, v1::migrate::<T>), (2, v2::migrate::<T>), (3, v3::migrate::<T>)]
+}
+
+/// Bring storage from the on-chain version up to [`STORAGE_VERSION`].
+pub fn migrate<T: Config>() -> Weight {
+	let on_chain = StorageVersion::get::<Pallet<T>>();
+	let mut weight = T::DbWeight::get().reads(1);
+
+	for (version, step) in steps::<T>() {
+		if on_chain < version {
+			log::info!(target: "runtime::kitty", "migrating storage to v{}", version);
+			weight = weight.saturating_add(step());
+			StorageVersion::new(version).put::<Pallet<T>>();
+			weight = weight.saturating_add(T::DbWeight::get().writes(1));
+		}
+	}
+
+	weight
+}
+
+/// v1: kitties record their parents, generation and breeding cooldown. Existing kitties become
+/// generation 0 founders that can breed straight away.
+pub mod v1 {
+	use super::*;
+	use codec::{Decode, Encode};
+	use sp_runtime::traits::Zero;
+
+	#[derive(Encode, Decode)]
+	struct OldKitty<T: Config> {
+		dna: [u8; 16],
+		price: Option<BalanceOf<T>>,
+		gender: Gender,
+		owner: AccountOf<T>,
+	}
+
+	pub fn migrate<T: Config>() -> Weight {
+		let mut translated: Weight = 0;
+		<Kitties<T>>::translate::<OldKitty<T>, _>(|_, old| {
+			translated += 1;
+			Some(Kitty {
+				dna: old.dna,
+				price: old.price,
+				gender: old.gender,
+				owner: old.owner,
+				parents: None,
+				generation: 0,
+				breed_ready_at: Zero::zero(),
+			})
+		});
+		T::DbWeight::get().reads_writes(translated, translated)
+	}
+}
+
+/// v2: every kitty is backed by an orml_nft token. Existing kitties get theirs minted to their
+/// current owner.
+pub mod v2 {
+	use super::*;
+
+	pub fn migrate<T: Config>() -> Weight {
+		let mut minted: Weight = 0;
+		for (kitty_id, kitty) in <Kitties<T>>::iter() {
+			if Pallet::<T>::kitty_token(&kitty_id).is_some() {
+				continue
+			}
+			match Pallet::<T>::mint_token(&kitty_id, &kitty) {
+				Ok(()) => minted += 1,
+				Err(e) => log::error!(target: "runtime::kitty", "no token for kitty {:?}: {:?}", kitty_id, e),
+			}
+		}
+		T::DbWeight::get().reads_writes(minted + 1, minted * 4 + 1)
+	}
+}
+
+/// v3: the Kitties an account owns move from a `BoundedVec` under `KittiesOwned` to the
+/// `OwnedKitties` double map and `OwnedKittyCount`.
+pub mod v3 {
+	use super::*;
+	use frame_support::{storage::migration::storage_key_iter, traits::PalletInfoAccess, Twox64Concat};
+	use sp_std::vec::Vec;
+
+	pub fn migrate<T: Config>() -> Weight {
+		let mut moved: Weight = 0;
+		let mut accounts: Weight = 0;
+		let old = storage_key_iter::<T::AccountId, Vec<T::Hash>, Twox64Concat>(
+			Pallet::<T>::name().as_bytes(),
+			b"KittiesOwned",
+		);
+		for (owner, kitties) in old.drain() {
+			accounts += 1;
+			moved += kitties.len() as Weight;
+			<OwnedKittyCount<T>>::insert(&owner, kitties.len() as u32);
+			for kitty_id in kitties {
+				<OwnedKitties<T>>::insert(&owner, kitty_id, ());
+			}
+		}
+		T::DbWeight::get().reads_writes(accounts, 2 * accounts + moved)
+	}
+}
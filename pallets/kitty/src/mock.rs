@@ -2,12 +2,12 @@
 
 use crate as pallet_kitties;
 use pallet_kitties::Gender;
-use frame_support::parameter_types;
+use frame_support::{parameter_types, PalletId};
 use sp_core::H256;
 use sp_runtime::{
 	testing::Header,
 	traits::{BlakeTwo256, IdentityLookup},
-	BuildStorage,
+	BuildStorage, Permill,
 };
 
 type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
@@ -22,7 +22,8 @@ frame_support::construct_runtime!(
 	{
 		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
 		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
-		RandomnessCollectiveFlip: pallet_randomness_collective_flip::{Pallet, Storage},
+		Beacon: pallet_beacon::{Pallet, Call, Storage, Config<T>, Event<T>},
+		OrmlNFT: orml_nft::{Pallet, Storage, Config<T>},
 		SubstrateKitties: pallet_kitties::{Pallet, Call, Storage, Config<T>, Event<T>},
 	}
 );
@@ -77,22 +78,63 @@ impl pallet_balances::Config for Test {
 parameter_types! {
 	// One can owned at most 9,999 Kitties
 	pub const MaxKittyOwned: u32 = 9999;
+	pub const MaxAuctionsPerBlock: u32 = 2;
+	pub const MaxOffersPerBlock: u32 = 2;
+	pub const MaxRentalsPerBlock: u32 = 2;
+	pub const BreedCooldown: u64 = 5;
+	pub const MaxRoyalty: Permill = Permill::from_percent(20);
+	pub const KittiesPalletId: PalletId = PalletId(*b"py/kitty");
 }
 
 impl pallet_kitties::Config for Test {
 	type Event = Event;
 	type Currency = Balances;
-	type KittyRandomness = RandomnessCollectiveFlip;
+	type KittyRandomness = Beacon;
 	type MaxKittyOwned = MaxKittyOwned;
+	type MaxAuctionsPerBlock = MaxAuctionsPerBlock;
+	type MaxOffersPerBlock = MaxOffersPerBlock;
+	type MaxRentalsPerBlock = MaxRentalsPerBlock;
+	type PalletId = KittiesPalletId;
+	type BreedCooldown = BreedCooldown;
+	type MaxRoyalty = MaxRoyalty;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const MaxClassMetadata: u32 = 1;
+	pub const MaxTokenMetadata: u32 = 1;
+}
+
+impl orml_nft::Config for Test {
+	type ClassId = u64;
+	type TokenId = u64;
+	type ClassData = ();
+	type TokenData = pallet_kitties::KittyTokenData;
+	type MaxClassMetadata = MaxClassMetadata;
+	type MaxTokenMetadata = MaxTokenMetadata;
 }
 
-impl pallet_randomness_collective_flip::Config for Test {}
+parameter_types! {
+	pub const BeaconMaxParticipants: u32 = 2;
+	pub const BeaconCommitPeriod: u64 = 3;
+	pub const BeaconRevealPeriod: u64 = 2;
+	pub const BeaconMaxMissedReveals: u32 = 2;
+}
+
+impl pallet_beacon::Config for Test {
+	type Event = Event;
+	type RegistrarOrigin = frame_system::EnsureRoot<u64>;
+	type MaxParticipants = BeaconMaxParticipants;
+	type CommitPeriod = BeaconCommitPeriod;
+	type RevealPeriod = BeaconRevealPeriod;
+	type MaxMissedReveals = BeaconMaxMissedReveals;
+}
 
 pub(crate) fn new_test_ext() -> sp_io::TestExternalities {
 	let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
 	GenesisConfig {
 		balances: BalancesConfig {
-			balances: vec![(1,  10), (2,  10)]
+			balances: vec![(1,  10), (2,  10), (3,  10)]
 		},
 		substrate_kitties: SubstrateKittiesConfig {
 			kitties: vec![
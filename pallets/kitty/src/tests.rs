@@ -1,29 +1,390 @@
-use super::*;
+use crate::{mock::*, Error, Event as KittyEvent, Gender, KittyTokenData};
+use codec::Encode;
+use frame_support::{
+	assert_noop, assert_ok,
+	storage::migration::{get_storage_value, put_storage_value},
+	traits::Hooks,
+	StorageHasher, Twox64Concat,
+};
+use sp_runtime::Permill;
+
+fn kitty_of(owner: u64) -> sp_core::H256 {
+	SubstrateKitties::kitties_owned(&owner)[0]
+}
+
+// The Kitty `owner` bred last.
+fn kitten_of(owner: u64) -> sp_core::H256 {
+	System::events().into_iter().rev().find_map(|record| match record.event {
+		Event::SubstrateKitties(KittyEvent::Bred(o, kitten, ..)) if o == owner => Some(kitten),
+		_ => None,
+	}).unwrap()
+}
+
+fn end_block(n: u64) {
+	SubstrateKitties::on_finalize(n);
+	System::set_block_number(n + 1);
+}
+
+#[test]
+fn auction_goes_to_highest_bidder() {
+	new_test_ext().execute_with(|| {
+		let kitty = kitty_of(1);
+		assert_ok!(SubstrateKitties::set_price(Origin::signed(1), kitty, Some(5)));
+
+		assert_noop!(SubstrateKitties::create_auction(Origin::signed(2), kitty, 3, 5), Error::<Test>::NotKittyOwner);
+		assert_noop!(SubstrateKitties::create_auction(Origin::signed(1), kitty, 3, 1), Error::<Test>::AuctionEndInPast);
+		assert_ok!(SubstrateKitties::create_auction(Origin::signed(1), kitty, 3, 5));
+		assert_eq!(SubstrateKitties::kitties(kitty).unwrap().price, None);
+
+		// Listed, transferred or auctioned again while the auction runs: all blocked.
+		assert_noop!(SubstrateKitties::set_price(Origin::signed(1), kitty, Some(5)), Error::<Test>::KittyInAuction);
+		assert_noop!(SubstrateKitties::transfer(Origin::signed(1), 2, kitty), Error::<Test>::KittyInAuction);
+		assert_noop!(SubstrateKitties::create_auction(Origin::signed(1), kitty, 3, 6), Error::<Test>::KittyInAuction);
+
+		assert_noop!(SubstrateKitties::bid(Origin::signed(1), kitty, 4), Error::<Test>::BuyerIsKittyOwner);
+		assert_noop!(SubstrateKitties::bid(Origin::signed(2), kitty, 2), Error::<Test>::BidTooLow);
+		assert_noop!(SubstrateKitties::bid(Origin::signed(2), kitty, 11), Error::<Test>::NotEnoughBalance);
+		assert_ok!(SubstrateKitties::bid(Origin::signed(2), kitty, 4));
+		assert_eq!(Balances::reserved_balance(2), 4);
+		assert_noop!(SubstrateKitties::bid(Origin::signed(3), kitty, 4), Error::<Test>::BidTooLow);
+
+		// Outbidding refunds the previous bidder.
+		assert_ok!(SubstrateKitties::bid(Origin::signed(3), kitty, 6));
+		assert_eq!(Balances::reserved_balance(2), 0);
+		assert_eq!(Balances::reserved_balance(3), 6);
+
+		end_block(4);
+		assert_eq!(SubstrateKitties::kitties(kitty).unwrap().owner, 1);
+		end_block(5);
+		assert_noop!(SubstrateKitties::bid(Origin::signed(2), kitty, 8), Error::<Test>::AuctionNotExist);
+
+		assert_eq!(SubstrateKitties::kitties(kitty).unwrap().owner, 3);
+		assert_eq!(Balances::free_balance(3), 4);
+		assert_eq!(Balances::reserved_balance(3), 0);
+		assert_eq!(Balances::free_balance(1), 16);
+		System::assert_has_event(Event::SubstrateKitties(KittyEvent::AuctionWon(3, 1, kitty, 6)));
+	});
+}
+
+#[test]
+fn auction_without_bids_keeps_kitty_with_seller() {
+	new_test_ext().execute_with(|| {
+		let kitty = kitty_of(1);
+		assert_ok!(SubstrateKitties::create_auction(Origin::signed(1), kitty, 3, 2));
+		end_block(1);
+		assert_noop!(SubstrateKitties::bid(Origin::signed(2), kitty, 4), Error::<Test>::AuctionEnded);
+		end_block(2);
+
+		assert_eq!(SubstrateKitties::kitties(kitty).unwrap().owner, 1);
+		System::assert_has_event(Event::SubstrateKitties(KittyEvent::AuctionUnsold(1, kitty)));
+		assert_ok!(SubstrateKitties::set_price(Origin::signed(1), kitty, Some(5)));
+	});
+}
+
+#[test]
+fn auctions_ending_per_block_are_bounded() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(SubstrateKitties::create_auction(Origin::signed(1), kitty_of(1), 1, 3));
+		assert_ok!(SubstrateKitties::create_auction(Origin::signed(2), kitty_of(2), 1, 3));
+		assert_ok!(SubstrateKitties::create_kitty(Origin::signed(3)));
+		assert_noop!(
+			SubstrateKitties::create_auction(Origin::signed(3), kitty_of(3), 1, 3),
+			Error::<Test>::TooManyAuctionsEnding
+		);
+	});
+}
 
 #[test]
-fn error_works(){
-    new_test_ext().execute_with(|| {
-        assert_err!(
-            TestingPallet::add_value(Origin::signed(1), 51),
-            "value must be <= maximum add amount constant"
-        );
-    })
+fn breeding_records_lineage_and_cools_down() {
+	new_test_ext().execute_with(|| {
+		let (mum, dad) = (kitty_of(1), kitty_of(2));
+		assert_ok!(SubstrateKitties::transfer(Origin::signed(2), 1, dad));
+
+		assert_noop!(SubstrateKitties::breed_kitty(Origin::signed(1), mum, mum), Error::<Test>::SameGender);
+		assert_ok!(SubstrateKitties::breed_kitty(Origin::signed(1), mum, dad));
+		let kitten = kitten_of(1);
+		System::assert_last_event(Event::SubstrateKitties(KittyEvent::Bred(1, kitten, mum, dad)));
+
+		let kitty = SubstrateKitties::kitties(kitten).unwrap();
+		assert_eq!(kitty.parents, Some((mum, dad)));
+		assert_eq!(kitty.generation, 1);
+		// The kitten waits twice as long as its generation 0 parents.
+		assert_eq!(kitty.breed_ready_at, 11);
+		assert_eq!(SubstrateKitties::kitties(mum).unwrap().breed_ready_at, 6);
+
+		assert_noop!(SubstrateKitties::breed_kitty(Origin::signed(1), mum, dad), Error::<Test>::BreedingCooldown);
+		System::set_block_number(6);
+		assert_ok!(SubstrateKitties::breed_kitty(Origin::signed(1), mum, dad));
+
+		assert_eq!(
+			SubstrateKitties::lineage(kitten, 5),
+			vec![(kitten, Some((mum, dad))), (mum, None), (dad, None)]
+		);
+		assert_eq!(SubstrateKitties::lineage(kitten, 0), vec![(kitten, Some((mum, dad)))]);
+	});
 }
 
 #[test]
-fn test_should_work() {
-    new_test_ext().execute_with(|| {
-        assert_ok!(
-            TestingPallet::add_value(Origin::signed(1), 10)
-        );
-    })
+fn kitties_are_backed_by_orml_nft_tokens() {
+	new_test_ext().execute_with(|| {
+		let kitty = kitty_of(1);
+		let (class, token) = SubstrateKitties::kitty_token(kitty).unwrap();
+		assert_eq!(SubstrateKitties::kitty_class(), Some(class));
+		assert_eq!(OrmlNFT::classes(class).unwrap().owner, SubstrateKitties::account_id());
+
+		let info = OrmlNFT::tokens(class, token).unwrap();
+		assert_eq!(info.owner, 1);
+		assert_eq!(info.data, KittyTokenData { dna: *b"1234567890123456", gender: Gender::Female });
+
+		assert_ok!(SubstrateKitties::transfer(Origin::signed(1), 3, kitty));
+		assert_eq!(OrmlNFT::tokens(class, token).unwrap().owner, 3);
+
+		assert_ok!(SubstrateKitties::set_price(Origin::signed(3), kitty, Some(4)));
+		assert_ok!(SubstrateKitties::buy_kitty(Origin::signed(2), kitty, 4));
+		assert_eq!(OrmlNFT::tokens(class, token).unwrap().owner, 2);
+		assert_eq!(SubstrateKitties::kitties(kitty).unwrap().owner, 2);
+	});
+}
+
+#[test]
+fn offers_reserve_funds_until_accepted_cancelled_or_expired() {
+	new_test_ext().execute_with(|| {
+		let (kitty1, kitty2) = (kitty_of(1), kitty_of(2));
+		assert_noop!(SubstrateKitties::make_offer(Origin::signed(1), kitty1, 4, 5), Error::<Test>::BuyerIsKittyOwner);
+		assert_noop!(SubstrateKitties::make_offer(Origin::signed(2), kitty1, 4, 1), Error::<Test>::OfferExpiryInPast);
+		assert_noop!(SubstrateKitties::make_offer(Origin::signed(2), kitty1, 11, 5), Error::<Test>::NotEnoughBalance);
+
+		// Kitty 1 is not for sale, offers are made anyway.
+		assert_ok!(SubstrateKitties::make_offer(Origin::signed(2), kitty1, 4, 5));
+		assert_ok!(SubstrateKitties::make_offer(Origin::signed(3), kitty1, 6, 3));
+		assert_ok!(SubstrateKitties::make_offer(Origin::signed(3), kitty2, 2, 5));
+		assert_noop!(SubstrateKitties::make_offer(Origin::signed(2), kitty1, 5, 5), Error::<Test>::OfferExists);
+		assert_noop!(SubstrateKitties::make_offer(Origin::signed(1), kitty2, 1, 5), Error::<Test>::TooManyOffersExpiring);
+		assert_eq!(Balances::reserved_balance(3), 8);
+		assert_eq!(crate::Offers::<Test>::iter_prefix(kitty1).count(), 2);
+		assert_eq!(crate::OffersByBidder::<Test>::iter_prefix(3).count(), 2);
+
+		assert_ok!(SubstrateKitties::cancel_offer(Origin::signed(3), kitty2));
+		assert_noop!(SubstrateKitties::cancel_offer(Origin::signed(3), kitty2), Error::<Test>::OfferNotExist);
+		assert_eq!(Balances::reserved_balance(3), 6);
+		assert_eq!(SubstrateKitties::offers_by_bidder(3, kitty2), None);
+		// Cancelling freed a slot among the offers expiring at block 5.
+		assert_ok!(SubstrateKitties::make_offer(Origin::signed(1), kitty2, 1, 5));
+
+		end_block(1);
+		end_block(2);
+		end_block(3);
+		System::assert_has_event(Event::SubstrateKitties(KittyEvent::OfferExpired(3, kitty1)));
+		assert_eq!(Balances::reserved_balance(3), 0);
+		assert_eq!(SubstrateKitties::offers(kitty1, 3), None);
+
+		assert_noop!(SubstrateKitties::accept_offer(Origin::signed(2), kitty1, 2, 4), Error::<Test>::NotKittyOwner);
+		assert_noop!(SubstrateKitties::accept_offer(Origin::signed(1), kitty1, 3, 6), Error::<Test>::OfferNotExist);
+		assert_noop!(SubstrateKitties::accept_offer(Origin::signed(1), kitty1, 2, 5), Error::<Test>::OfferTooLow);
+		assert_ok!(SubstrateKitties::accept_offer(Origin::signed(1), kitty1, 2, 4));
+		System::assert_last_event(Event::SubstrateKitties(KittyEvent::OfferAccepted(2, 1, kitty1, 4)));
+		assert_eq!(SubstrateKitties::kitties(kitty1).unwrap().owner, 2);
+		assert_eq!(Balances::free_balance(1), 13);
+		assert_eq!(Balances::reserved_balance(1), 1);
+		assert_eq!(Balances::free_balance(2), 6);
+		assert_eq!(Balances::reserved_balance(2), 0);
+		assert_eq!(SubstrateKitties::offers_by_bidder(2, kitty1), None);
+
+		// The accepted offer is gone from the expiry queue; the other one is released.
+		end_block(4);
+		end_block(5);
+		assert_eq!(Balances::free_balance(1), 14);
+		System::assert_last_event(Event::SubstrateKitties(KittyEvent::OfferExpired(1, kitty2)));
+	});
+}
+
+#[test]
+fn breeder_takes_royalty_on_sales() {
+	new_test_ext().execute_with(|| {
+		let (mum, dad) = (kitty_of(1), kitty_of(2));
+		assert_ok!(SubstrateKitties::transfer(Origin::signed(2), 1, dad));
+		assert_ok!(SubstrateKitties::breed_kitty(Origin::signed(1), mum, dad));
+		let kitten = kitten_of(1);
+		assert_eq!(SubstrateKitties::royalties(kitten).unwrap().rate, Permill::zero());
+		// Only bred kitties have a breeder.
+		assert_noop!(
+			SubstrateKitties::set_royalty(Origin::signed(1), mum, Permill::from_percent(10)),
+			Error::<Test>::NotKittyBreeder
+		);
+
+		assert_noop!(
+			SubstrateKitties::set_royalty(Origin::signed(1), kitten, Permill::from_percent(30)),
+			Error::<Test>::RoyaltyTooHigh
+		);
+		assert_ok!(SubstrateKitties::set_royalty(Origin::signed(1), kitten, Permill::from_percent(20)));
+		System::assert_last_event(Event::SubstrateKitties(KittyEvent::RoyaltySet(1, kitten, Permill::from_percent(20))));
+
+		// The breeder selling their own kitten keeps the whole price.
+		assert_ok!(SubstrateKitties::set_price(Origin::signed(1), kitten, Some(5)));
+		assert_ok!(SubstrateKitties::buy_kitty(Origin::signed(2), kitten, 5));
+		assert_eq!(Balances::free_balance(1), 15);
+		assert_noop!(
+			SubstrateKitties::set_royalty(Origin::signed(2), kitten, Permill::zero()),
+			Error::<Test>::NotKittyBreeder
+		);
+
+		assert_ok!(SubstrateKitties::set_price(Origin::signed(2), kitten, Some(5)));
+		assert_ok!(SubstrateKitties::buy_kitty(Origin::signed(3), kitten, 5));
+		System::assert_has_event(Event::SubstrateKitties(KittyEvent::RoyaltyPaid(kitten, 2, 4, 1, 1)));
+		assert_eq!(Balances::free_balance(3), 5);
+		assert_eq!(Balances::free_balance(2), 9);
+		assert_eq!(Balances::free_balance(1), 16);
+	});
+}
+
+#[test]
+fn breeder_takes_royalty_on_offers_and_auctions() {
+	new_test_ext().execute_with(|| {
+		let (mum, dad) = (kitty_of(1), kitty_of(2));
+		assert_ok!(SubstrateKitties::transfer(Origin::signed(2), 1, dad));
+		assert_ok!(SubstrateKitties::breed_kitty(Origin::signed(1), mum, dad));
+		let kitten = kitten_of(1);
+		assert_ok!(SubstrateKitties::set_royalty(Origin::signed(1), kitten, Permill::from_percent(20)));
+		assert_ok!(SubstrateKitties::transfer(Origin::signed(1), 2, kitten));
+
+		// An accepted offer is split like a purchase.
+		assert_ok!(SubstrateKitties::make_offer(Origin::signed(3), kitten, 5, 10));
+		assert_ok!(SubstrateKitties::accept_offer(Origin::signed(2), kitten, 3, 5));
+		System::assert_has_event(Event::SubstrateKitties(KittyEvent::RoyaltyPaid(kitten, 2, 4, 1, 1)));
+		assert_eq!(Balances::free_balance(3), 5);
+		assert_eq!(Balances::reserved_balance(3), 0);
+		assert_eq!(Balances::free_balance(2), 14);
+		assert_eq!(Balances::free_balance(1), 11);
+
+		// So is a won auction.
+		assert_ok!(SubstrateKitties::create_auction(Origin::signed(3), kitten, 3, 5));
+		assert_ok!(SubstrateKitties::bid(Origin::signed(2), kitten, 5));
+		end_block(5);
+		System::assert_has_event(Event::SubstrateKitties(KittyEvent::RoyaltyPaid(kitten, 3, 4, 1, 1)));
+		System::assert_has_event(Event::SubstrateKitties(KittyEvent::AuctionWon(2, 3, kitten, 5)));
+		assert_eq!(SubstrateKitties::kitties(kitten).unwrap().owner, 2);
+		assert_eq!(Balances::free_balance(2), 9);
+		assert_eq!(Balances::reserved_balance(2), 0);
+		assert_eq!(Balances::free_balance(3), 9);
+		assert_eq!(Balances::free_balance(1), 12);
+	});
+}
+
+#[test]
+fn ownership_index_migrates_from_vectors() {
+	new_test_ext().execute_with(|| {
+		let (kitty1, kitty2) = (kitty_of(1), kitty_of(2));
+		// Put account 1's Kitties back in the old layout.
+		crate::OwnedKitties::<Test>::remove_all(None);
+		crate::OwnedKittyCount::<Test>::remove_all(None);
+		let key = Twox64Concat::hash(&1u64.encode());
+		put_storage_value(b"SubstrateKitties", b"KittiesOwned", &key, vec![kitty1, kitty2]);
+
+		crate::migrations::v3::migrate::<Test>();
+		assert_eq!(SubstrateKitties::owned_kitty_count(1), 2);
+		let mut owned = SubstrateKitties::kitties_owned(&1);
+		owned.sort();
+		let mut expected = vec![kitty1, kitty2];
+		expected.sort();
+		assert_eq!(owned, expected);
+		assert_eq!(get_storage_value::<Vec<sp_core::H256>>(b"SubstrateKitties", b"KittiesOwned", &key), None);
+	});
+}
+
+#[test]
+fn renter_uses_kitty_until_rental_ends() {
+	new_test_ext().execute_with(|| {
+		let (kitty1, kitty2) = (kitty_of(1), kitty_of(2));
+		assert_noop!(SubstrateKitties::list_for_rent(Origin::signed(2), kitty1, 1, 3), Error::<Test>::NotKittyOwner);
+		assert_noop!(SubstrateKitties::list_for_rent(Origin::signed(1), kitty1, 1, 0), Error::<Test>::InvalidRentalDuration);
+		assert_ok!(SubstrateKitties::list_for_rent(Origin::signed(1), kitty1, 1, 3));
+
+		assert_noop!(SubstrateKitties::rent(Origin::signed(3), kitty2, 1), Error::<Test>::KittyNotForRent);
+		assert_noop!(SubstrateKitties::rent(Origin::signed(1), kitty1, 1), Error::<Test>::BuyerIsKittyOwner);
+		assert_noop!(SubstrateKitties::rent(Origin::signed(2), kitty1, 0), Error::<Test>::InvalidRentalDuration);
+		assert_noop!(SubstrateKitties::rent(Origin::signed(2), kitty1, 4), Error::<Test>::InvalidRentalDuration);
+		assert_ok!(SubstrateKitties::rent(Origin::signed(2), kitty1, 3));
+		System::assert_last_event(Event::SubstrateKitties(KittyEvent::Rented(2, kitty1, 4, 3)));
+		assert_eq!(Balances::free_balance(2), 7);
+		assert_eq!(Balances::free_balance(1), 13);
+		assert_noop!(SubstrateKitties::rent(Origin::signed(3), kitty1, 1), Error::<Test>::KittyRented);
+
+		// The owner keeps the Kitty but can neither move it nor use it.
+		assert_noop!(SubstrateKitties::transfer(Origin::signed(1), 3, kitty1), Error::<Test>::KittyRented);
+		assert_noop!(SubstrateKitties::set_price(Origin::signed(1), kitty1, Some(5)), Error::<Test>::KittyRented);
+		assert_noop!(SubstrateKitties::create_auction(Origin::signed(1), kitty1, 1, 9), Error::<Test>::KittyRented);
+		assert_ok!(SubstrateKitties::make_offer(Origin::signed(3), kitty1, 2, 20));
+		assert_noop!(SubstrateKitties::accept_offer(Origin::signed(1), kitty1, 3, 2), Error::<Test>::KittyRented);
+		assert_noop!(SubstrateKitties::breed_kitty(Origin::signed(1), kitty1, kitty1), Error::<Test>::NotKittyUser);
+
+		// The renter breeds with it and keeps the kitten.
+		assert_ok!(SubstrateKitties::breed_kitty(Origin::signed(2), kitty1, kitty2));
+		let kitten = kitten_of(2);
+		assert_eq!(SubstrateKitties::kitties(kitten).unwrap().owner, 2);
+		assert_eq!(SubstrateKitties::kitties(kitty1).unwrap().owner, 1);
+
+		assert_noop!(SubstrateKitties::return_kitty(Origin::signed(3), kitty1), Error::<Test>::KittyNotRented);
+		end_block(1);
+		end_block(2);
+		end_block(3);
+		assert!(SubstrateKitties::rentals(kitty1).is_some());
+		end_block(4);
+		System::assert_last_event(Event::SubstrateKitties(KittyEvent::RentalEnded(2, kitty1)));
+		assert_eq!(SubstrateKitties::rentals(kitty1), None);
+		assert!(SubstrateKitties::is_kitty_user(&kitty1, &1).unwrap());
+
+		// Returned early, the rental no longer ends at its original block.
+		assert_ok!(SubstrateKitties::rent(Origin::signed(3), kitty1, 2));
+		assert_ok!(SubstrateKitties::return_kitty(Origin::signed(3), kitty1));
+		assert!(crate::RentalsEndingAt::<Test>::get(7).is_empty());
+
+		// A new owner does not inherit the listing.
+		assert_ok!(SubstrateKitties::transfer(Origin::signed(1), 3, kitty1));
+		assert_eq!(SubstrateKitties::rental_listings(kitty1), None);
+	});
+}
+
+#[test]
+fn beacon_randomness_varies_per_kitty() {
+	new_test_ext().execute_with(|| {
+		// No beacon round has completed, so the seed is still the genesis default.
+		assert_eq!(Beacon::seed(), Default::default());
+
+		let of_gender = |gender: Gender| {
+			SubstrateKitties::kitties_owned(&3)
+				.into_iter()
+				.find(|id| SubstrateKitties::kitties(id).unwrap().gender == gender)
+		};
+		for _ in 0..16 {
+			assert_ok!(SubstrateKitties::create_kitty(Origin::signed(3)));
+			if of_gender(Gender::Male).is_some() && of_gender(Gender::Female).is_some() {
+				break
+			}
+		}
+
+		let mut dnas: Vec<_> = SubstrateKitties::kitties_owned(&3)
+			.iter()
+			.map(|id| SubstrateKitties::kitties(id).unwrap().dna)
+			.collect();
+		let minted = dnas.len();
+		dnas.sort();
+		dnas.dedup();
+		assert_eq!(dnas.len(), minted);
+
+		// Kitties minted in the same block from the same seed can still be bred.
+		let (mum, dad) = (of_gender(Gender::Female).unwrap(), of_gender(Gender::Male).unwrap());
+		assert_ok!(SubstrateKitties::breed_kitty(Origin::signed(3), mum, dad));
+	});
 }
 
 #[test]
-fn test_should_fail() {
-    new_test_ext().execute_with(|| {
-        assert_ok!(
-            TestingPallet::add_value(Origin::signed(1), 100)
-        );
-    })
+fn identical_kitties_get_distinct_ids() {
+	new_test_ext().execute_with(|| {
+		let first = SubstrateKitties::mint(&3, Some(*b"1234567890123456"), Some(Gender::Female)).unwrap();
+		let second = SubstrateKitties::mint(&3, Some(*b"1234567890123456"), Some(Gender::Female)).unwrap();
+		assert_ne!(first, second);
+		assert_eq!(SubstrateKitties::kitty_cnt(), 4);
+		assert_eq!(SubstrateKitties::owned_kitty_count(&3), 2);
+		assert!(SubstrateKitties::kitty_token(&first).is_some() && SubstrateKitties::kitty_token(&second).is_some());
+	});
 }
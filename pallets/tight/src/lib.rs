@@ -11,13 +11,17 @@ pub mod pallet {
 	use frame_support::pallet_prelude::*;
 	use frame_system::pallet_prelude::*;
 	use sp_std::vec::Vec; // Step 3.1 will include this in `Cargo.toml`
+	use pallet_studentid_primitives::IdentityProvider;
 
 	#[pallet::config]  // <-- Step 2. code block will replace this.
-	pub trait Config: frame_system::Config + pallet_balances::Config + orml_nft::Config  + pallet_studentid::Config  {
+	pub trait Config: frame_system::Config + pallet_balances::Config + orml_nft::Config  {
 
 		/// The overarching event type.
 		type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
 
+		/// Where student identities come from, usually `pallet_studentid::Pallet<Runtime>`.
+		type Identity: IdentityProvider<Self::AccountId>;
+
 
 	}
 
@@ -252,7 +256,7 @@ pub mod pallet {
 			// let res = orml_nft::Pallet::<T>::create_class(&who,vec![1],() as <T as orml_nft::Config>::ClassData);
 //             let xx : BoundedVec<_,  pallet_studentid::Pallet::<T>::Config::MaxEmailsize> = metadata.clone().try_into().unwrap();
 
-			let res1 = pallet_studentid::Pallet::<T>::identity(who.clone());
+			let res1 = T::Identity::identity_of(&who);
 
 			//let res1 = pallet_studentid::MaxEmailsize;
             //IdentityOf
@@ -43,7 +43,7 @@ pub use frame_support::{
 		constants::{BlockExecutionWeight, ExtrinsicBaseWeight, RocksDbWeight, WEIGHT_PER_SECOND},
 		IdentityFee, Weight,
 	},
-	StorageValue,
+	PalletId, StorageValue,
 };
 pub use pallet_balances::Call as BalancesCall;
 pub use pallet_timestamp::Call as TimestampCall;
@@ -123,12 +123,14 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	// The version of the runtime specification. A full node will not attempt to use its native
 	//   runtime in substitute for the on-chain Wasm runtime unless all of `spec_name`,
 	//   `spec_version`, and `authoring_version` are the same between Wasm and native.
-	// This value is set to 100 to notify Polkadot-JS App (https://polkadot.js.org/apps) to use
-	//   the compatible custom types.
-	spec_version: 100,
+	// This value started at 100 to notify Polkadot-JS App (https://polkadot.js.org/apps) to use
+	//   the compatible custom types. Bump it with every runtime change.
+	spec_version: 101,
 	impl_version: 1,
 	apis: RUNTIME_API_VERSIONS,
-	transaction_version: 1,
+	// Bump whenever call indices or the `SignedExtra` change, so signed transactions built
+	//   against an older runtime are rejected.
+	transaction_version: 2,
 };
 
 /// This determines the average expected block time that we are targeting.
@@ -353,6 +355,21 @@ parameter_types! {
     pub const MaxEmailsize: u32 = 30;
     pub const MaxTokenid: u32 = 30;
     pub const MaxUseridentities: u32 = 20;
+    pub const MaxLinkedAccounts: u32 = 5;
+    pub const MaxGuardians: u32 = 5;
+    pub const RecoveryDelay: BlockNumber = 2 * DAYS;
+    pub const RecoveryDeposit: Balance = 10_000;
+    pub const MaxReferralsPerReferrer: u32 = 50;
+    pub const ReferralReward: Balance = 1_000;
+    pub const StudentIdPalletId: PalletId = PalletId(*b"py/stdid");
+    pub const MaxDidKeys: u32 = 8;
+    pub const MaxEnrollmentBatch: u32 = 500;
+    pub const MaxCiphertextLen: u32 = 1024;
+    pub const MaxFieldGrants: u32 = 16;
+    pub const FeelessTxPerEra: u32 = 20;
+    pub const FeelessEraLength: BlockNumber = DAYS;
+    pub const MaxLoginAttempts: u32 = 5;
+    pub const LoginLockoutBase: BlockNumber = MINUTES;
 }
 ord_parameter_types! {
     pub const One: u64 = 1;
@@ -372,6 +389,23 @@ impl pallet_studentid::Config for Runtime {
     type MaxSubAccounts = MaxSubAccounts;
     type MaxAdditionalFields = MaxAdditionalFields;
     type MaxUseridentities = MaxUseridentities;
+    type MaxLinkedAccounts = MaxLinkedAccounts;
+    type MaxGuardians = MaxGuardians;
+    type RecoveryDelay = RecoveryDelay;
+    type RecoveryDeposit = RecoveryDeposit;
+    type MaxReferralsPerReferrer = MaxReferralsPerReferrer;
+    type ReferralReward = ReferralReward;
+    type PalletId = StudentIdPalletId;
+    type MaxDidKeys = MaxDidKeys;
+    type InstitutionOrigin = pallet_studentid::EnsureRegistrar<Runtime>;
+    type MaxEnrollmentBatch = MaxEnrollmentBatch;
+    type MaxCiphertextLen = MaxCiphertextLen;
+    type MaxFieldGrants = MaxFieldGrants;
+    type FeelessTxPerEra = FeelessTxPerEra;
+    type FeelessEraLength = FeelessEraLength;
+    type MaxLoginAttempts = MaxLoginAttempts;
+    type LoginLockoutBase = LoginLockoutBase;
+    type WeightInfo = pallet_studentid::weights::SubstrateWeight<Runtime>;
     type MaxRegistrars = MaxRegistrars;
     type MaxEmailsize = MaxEmailsize;
     type MaxAccessTokenMetadata = MaxAccessTokenMetadata;
@@ -392,37 +426,70 @@ impl pallet_template::Config for Runtime {
     //type Balance = Balance;
 }
 
+parameter_types! {
+	pub const BeaconMaxParticipants: u32 = 64;
+	pub const BeaconCommitPeriod: BlockNumber = 5 * MINUTES;
+	pub const BeaconRevealPeriod: BlockNumber = 5 * MINUTES;
+	pub const BeaconMaxMissedReveals: u32 = 3;
+}
+
+impl pallet_beacon::Config for Runtime {
+	type Event = Event;
+	type RegistrarOrigin = EnsureRoot<AccountId>;
+	type MaxParticipants = BeaconMaxParticipants;
+	type CommitPeriod = BeaconCommitPeriod;
+	type RevealPeriod = BeaconRevealPeriod;
+	type MaxMissedReveals = BeaconMaxMissedReveals;
+}
+
 parameter_types! {
 	// One can owned at most 9,999 Kitties
 	pub const MaxKittyOwned: u32 = 9999;
+	pub const MaxAuctionsPerBlock: u32 = 50;
+	pub const MaxOffersPerBlock: u32 = 50;
+	pub const MaxRentalsPerBlock: u32 = 50;
+	pub const BreedCooldown: BlockNumber = HOURS;
+	pub const MaxRoyalty: Permill = Permill::from_percent(10);
+	pub const KittiesPalletId: PalletId = PalletId(*b"py/kitty");
 }
 
 /// Configure the pallet-kitties in pallets/kitties.
 impl pallet_kitty::Config for Runtime {
 	type Event = Event;
 	type Currency = Balances;
-	type KittyRandomness = RandomnessCollectiveFlip;
+	type KittyRandomness = Beacon;
 	type MaxKittyOwned = MaxKittyOwned;
+	type MaxAuctionsPerBlock = MaxAuctionsPerBlock;
+	type MaxOffersPerBlock = MaxOffersPerBlock;
+	type MaxRentalsPerBlock = MaxRentalsPerBlock;
+	type PalletId = KittiesPalletId;
+	type BreedCooldown = BreedCooldown;
+	type MaxRoyalty = MaxRoyalty;
+	type WeightInfo = pallet_kitty::weights::SubstrateWeight<Runtime>;
 }
 
 impl pallet_docverify::Config for Runtime {
 	type Event = Event;
-
+	type VerifierOrigin = pallet_studentid::EnsureStudentRole<Runtime, pallet_studentid::roles::Faculty>;
+	type Identity = Identity;
 }
 
 parameter_types! {
-    pub const  SubmissionDeposit: u128 = 100;              
-    pub const  MinContribution: u128 = 100;              
-    pub const  RetirementPeriod: u128 = 100;              
+	pub const CrowdPalletId: PalletId = PalletId(*b"py/crowd");
+	// Opens the fund's account, so no less than the existential deposit.
+	pub const SubmissionDeposit: Balance = 500;
+	pub const MinContribution: Balance = 100;
+	// Long enough for contributors to withdraw from a failed fund before it can be dissolved.
+	pub const RetirementPeriod: BlockNumber = 14 * DAYS;
 }
 
 impl pallet_crowd::Config for Runtime {
 	type Event = Event;
-	type StakeCurrency = Balances;
-//    type Currency: ReservableCurrency<Self::AccountId>;
-    //type SubmissionDeposit: SubmissionDeposit;
-    //type MinContribution: MinContribution;
-    //type RetirementPeriod: RetirementPeriod;
+	type Currency = Balances;
+	type PalletId = CrowdPalletId;
+	type SubmissionDeposit = SubmissionDeposit;
+	type MinContribution = MinContribution;
+	type RetirementPeriod = RetirementPeriod;
 }
 
 impl pallet_loose::Config for Runtime {
@@ -440,7 +507,7 @@ impl orml_nft::Config for Runtime {
 	type ClassId = u64;
 	type TokenId = u64;
 	type ClassData = ();
-	type TokenData = ();
+	type TokenData = pallet_kitty::KittyTokenData;
 	type MaxClassMetadata = MaxClassMetadata;
 	type MaxTokenMetadata = MaxTokenMetadata;
 }
@@ -468,8 +535,9 @@ construct_runtime!(
 		TemplateModule: pallet_template::{Pallet, Call, Storage, Event<T>},
 		CrowdModule: pallet_crowd::{Pallet, Call, Storage, Event<T>},
         LooseTemplateModule: pallet_loose::{Pallet, Call, Storage, Event<T>},
+		Beacon: pallet_beacon::{Pallet, Call, Storage, Config<T>, Event<T>},
 		subkitty: pallet_kitty::{Pallet, Call, Storage, Event<T>},
-        Identity: pallet_studentid::{Pallet, Call,  Storage, Event<T>},
+        Identity: pallet_studentid::{Pallet, Call,  Storage, Config<T>, Event<T>},
         Docverify: pallet_docverify::{Pallet, Call,  Storage, Event<T>},
 	}
 );
@@ -480,6 +548,19 @@ pub type Address = sp_runtime::MultiAddress<AccountId, ()>;
 pub type Header = generic::Header<BlockNumber, BlakeTwo256>;
 /// Block type as expected by this runtime.
 pub type Block = generic::Block<Header, UncheckedExtrinsic>;
+/// Calls students can send without paying fees, see [`pallet_studentid::fees`].
+#[derive(Clone, Eq, PartialEq)]
+pub struct FeelessCalls;
+impl frame_support::traits::Contains<Call> for FeelessCalls {
+	fn contains(call: &Call) -> bool {
+		matches!(
+			call,
+			Call::Identity(pallet_studentid::Call::login_web3_sel16 { .. }) |
+				Call::Docverify(pallet_docverify::Call::document_submit_sel31 { .. })
+		)
+	}
+}
+
 /// The SignedExtension to the basic transaction logic.
 pub type SignedExtra = (
 	frame_system::CheckSpecVersion<Runtime>,
@@ -488,7 +569,8 @@ pub type SignedExtra = (
 	frame_system::CheckEra<Runtime>,
 	frame_system::CheckNonce<Runtime>,
 	frame_system::CheckWeight<Runtime>,
-	pallet_transaction_payment::ChargeTransactionPayment<Runtime>,
+	pallet_studentid::fees::ChargeOrWaiveForStudents<Runtime, FeelessCalls>,
+	pallet_studentid::failures::RecordFailures<Runtime>,
 );
 /// Unchecked extrinsic type as expected by this runtime.
 pub type UncheckedExtrinsic = generic::UncheckedExtrinsic<Address, Call, Signature, SignedExtra>;
@@ -632,6 +714,28 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl pallet_studentid_rpc_runtime_api::StudentIdApi<Block, AccountId> for Runtime {
+		fn identity_of_account(who: AccountId) -> Option<pallet_studentid::IdentitySummary<AccountId>> {
+			Identity::identity_summary_of_account(&who)
+		}
+		fn identity_by_email_hash(
+			email_hash: pallet_studentid::EmailHash,
+		) -> Option<pallet_studentid::IdentitySummary<AccountId>> {
+			Identity::identity_summary(email_hash)
+		}
+		fn has_access(who: AccountId, service: Vec<u8>) -> bool {
+			Identity::has_access(&who, &service)
+		}
+		fn validate_token(token_id: Vec<u8>, who: AccountId) -> bool {
+			Identity::validate_token(&token_id, &who)
+		}
+		fn did_details(
+			email_hash: pallet_studentid::EmailHash,
+		) -> Option<pallet_studentid::DidDetails<AccountId>> {
+			Identity::did_details(email_hash)
+		}
+	}
+
 	#[cfg(feature = "runtime-benchmarks")]
 	impl frame_benchmarking::Benchmark<Block> for Runtime {
 		fn benchmark_metadata(extra: bool) -> (
@@ -648,6 +752,8 @@ impl_runtime_apis! {
 			list_benchmark!(list, extra, pallet_balances, Balances);
 			list_benchmark!(list, extra, pallet_timestamp, Timestamp);
 			list_benchmark!(list, extra, pallet_template, TemplateModule);
+			list_benchmark!(list, extra, pallet_studentid, Identity);
+			list_benchmark!(list, extra, pallet_kitty, subkitty);
 
 			let storage_info = AllPalletsWithSystem::storage_info();
 
@@ -682,6 +788,8 @@ impl_runtime_apis! {
 			add_benchmark!(params, batches, pallet_balances, Balances);
 			add_benchmark!(params, batches, pallet_timestamp, Timestamp);
 			add_benchmark!(params, batches, pallet_template, TemplateModule);
+			add_benchmark!(params, batches, pallet_studentid, Identity);
+			add_benchmark!(params, batches, pallet_kitty, subkitty);
 
 			if batches.is_empty() { return Err("Benchmark not found for this pallet.".into()) }
 			Ok(batches)